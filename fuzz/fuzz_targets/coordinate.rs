@@ -0,0 +1,18 @@
+#![no_main]
+
+//! Feeds arbitrary text to the coordinate parsers: none may panic, and a
+//! position that parses must print in a form that parses back to it.
+
+use libfuzzer_sys::fuzz_target;
+use polish_airports::{Coordinate, Position};
+use std::str::FromStr;
+
+fuzz_target!(|data: &str| {
+    let _ = Coordinate::from_str(data);
+    let _ = Coordinate::parse_decimal(data);
+    if let Ok(position) = Position::from_str(data) {
+        let back = Position::from_str(&position.to_string()).expect("a printed position parses");
+        assert!((back.lat.to_decimal_degrees() - position.lat.to_decimal_degrees()).abs() < 1e-4);
+        assert!((back.lon.to_decimal_degrees() - position.lon.to_decimal_degrees()).abs() < 1e-4);
+    }
+});
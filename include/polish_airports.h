@@ -0,0 +1,36 @@
+/* C interface of the polish_airports library (libpolish_airports.so /
+ * polish_airports.dll). Functions return 0 on success and -1 on failure.
+ * Strings returned through out parameters belong to the library and must be
+ * released with polish_airports_free_string. */
+#ifndef POLISH_AIRPORTS_H
+#define POLISH_AIRPORTS_H
+
+#include <stddef.h>
+#include <stdint.h>
+
+#ifdef __cplusplus
+extern "C" {
+#endif
+
+/* Converts SkyDemon airfields XML to userpoints CSV. *out receives the CSV on
+ * success and the error message on failure. */
+int polish_airports_convert_skydemon_to_csv(const uint8_t *buf, size_t len, char delimiter, char **out);
+
+/* Converts the SkyDemon airfields XML file at input into the userpoints CSV
+ * file output, as the command line does without options. *error receives
+ * the message on failure and is left untouched on success. */
+int polish_airports_convert_file(const char *input, const char *output, char **error);
+
+/* Parses decimal degrees or a coordinate such as "N520101.50". */
+int polish_airports_parse_coordinate(const char *text, float *degrees);
+
+/* Parses a position such as "N530956.01 E0174449.00" into decimal degrees. */
+int polish_airports_parse_position(const char *text, float *latitude, float *longitude);
+
+void polish_airports_free_string(char *text);
+
+#ifdef __cplusplus
+}
+#endif
+
+#endif
@@ -0,0 +1,81 @@
+//! Aerodromes of the Polish AIP as published by PANSA in its AIXM 5.1
+//! digital data set.
+
+use crate::elevation::FEET_PER_METRE;
+use crate::format::{Fields, InputFormat, ReadOptions, Records};
+use crate::xml::{Element, Elements};
+use crate::{input, region, Waypoint};
+use std::error::Error;
+
+/// The PANSA AIXM 5.1 aerodrome data set, updated every AIRAC cycle at
+/// the same address. Use `fetch aip --url` when it moves.
+pub const AERODROMES_URL: &str = "https://www.ais.pansa.pl/aixm/AD_EP.xml";
+
+fn feet(node: &Element) -> Option<f32> {
+    let value = node.text.trim().parse::<f32>().ok()?;
+    match node.attribute("uom") {
+        Some("M") => Some((value * FEET_PER_METRE).round()),
+        _ => Some(value),
+    }
+}
+
+/// The userpoint type for an AIXM `CodeAirportHeliportType`.
+fn userpoint_type(code: &str) -> &'static str {
+    match code {
+        "HP" => "Helipad",
+        "LS" => "Airstrip",
+        _ => "Airport",
+    }
+}
+
+/// Reads the `AirportHeliport` features of an AIXM 5.1 data set. Features
+/// without a reference point are skipped.
+pub fn read_aixm(path: &str) -> Result<Vec<Waypoint>, Box<dyn Error>> {
+    let mut waypoints = vec![];
+    for feature in Elements::new(input::open(path)?, "AirportHeliport") {
+        let feature = feature?;
+        let text = |name: &str| feature.descendant(name).map(|n| n.text.trim()).filter(|t| !t.is_empty());
+        let Some(pos) = feature.descendant("ARP").and_then(|arp| arp.descendant("pos")) else {
+            continue;
+        };
+        let mut pos = pos.text.split_whitespace().map(str::parse::<f32>);
+        let (Some(Ok(latitude)), Some(Ok(longitude))) = (pos.next(), pos.next()) else {
+            return Err(format!("invalid gml:pos in {:?}", feature.attribute("id")).into());
+        };
+        let ident = text("locationIndicatorICAO").or_else(|| text("designator"));
+        let mut waypoint = Waypoint::builder(text("name").or(ident).unwrap_or_default())
+            .waypoint_type(userpoint_type(text("type").unwrap_or("AD")))
+            .coordinates(latitude, longitude)
+            .elevation(feature.descendant("fieldElevation").and_then(feet))
+            .region(region::POLAND)
+            .import_filename(path);
+        if let Some(ident) = ident {
+            waypoint = waypoint.ident(ident);
+        }
+        let waypoint = waypoint
+            .build()
+            .map_err(|e| e.in_record(format!("{:?}", feature.attribute("id").unwrap_or_default())))?;
+        waypoints.push(waypoint);
+    }
+    Ok(waypoints)
+}
+
+pub struct AipAixm;
+
+impl InputFormat for AipAixm {
+    fn name(&self) -> &'static str {
+        "aip-aixm"
+    }
+
+    fn description(&self) -> &'static str {
+        "Polish AIP aerodromes as AIXM 5.1 AirportHeliport features"
+    }
+
+    fn fields(&self) -> Fields {
+        Fields { elevation: true, ..Fields::default() }
+    }
+
+    fn read(&self, path: &str, _: &ReadOptions) -> Result<Records, Box<dyn Error>> {
+        Ok(Box::new(read_aixm(path)?.into_iter()))
+    }
+}
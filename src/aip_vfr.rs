@@ -0,0 +1,194 @@
+//! A best-effort reader for the AD 4 sections of AIP VFR Poland, the
+//! authoritative source for the uncontrolled aerodromes that are otherwise
+//! transcribed by hand. The HTML and PDF editions are laid out for reading,
+//! so this looks for each aerodrome's heading, ARP, elevation and radio
+//! frequencies in the text instead of parsing a structure. Check what it
+//! finds against the document before relying on it.
+
+use crate::elevation::FEET_PER_METRE;
+use crate::format::{Fields, InputFormat, ReadOptions, Records};
+use crate::{input, region, Waypoint};
+use regex::Regex;
+use std::error::Error;
+use std::sync::LazyLock;
+use tracing::warn;
+
+/// An aerodrome heading such as `EPBK — BIAŁYSTOK/KRYWLANY`.
+static HEADING: LazyLock<Regex> = LazyLock::new(|| {
+    Regex::new(r"\b(EP[A-Z]{2})\s*[-–—]\s*(\p{Lu}[\p{Lu}\p{Ll} /.'-]{1,60}?)\s*(?:\n|$|  )").expect("the pattern is valid")
+});
+
+/// An ARP in the compact `530608N 0231014E` form or with degree, minute and
+/// second marks, `53°06'08"N 023°10'14"E`.
+static ARP: LazyLock<Regex> = LazyLock::new(|| {
+    Regex::new(
+        r#"(?x)
+        (?P<lat>\d{6}(?:[.,]\d+)?|\d{1,2}°\s*\d{1,2}['′’]\s*\d{1,2}(?:[.,]\d+)?(?:"|''|″|”)?)\s*(?P<ns>[NS])
+        [\s,;]*
+        (?P<lon>\d{7}(?:[.,]\d+)?|\d{1,3}°\s*\d{1,2}['′’]\s*\d{1,2}(?:[.,]\d+)?(?:"|''|″|”)?)\s*(?P<ew>[EW])"#,
+    )
+    .expect("the pattern is valid")
+});
+
+static ELEVATION: LazyLock<Regex> = LazyLock::new(|| {
+    Regex::new(r"(?i)\b(?:elev(?:ation)?|wzniesienie|elewacja)\b\D{0,40}?(\d+(?:[.,]\d+)?)\s*(ft|m)\b")
+        .expect("the pattern is valid")
+});
+
+static FREQUENCY: LazyLock<Regex> = LazyLock::new(|| {
+    Regex::new(r"\b(TWR|AFIS|APP|GND|ATIS|RADIO|INFO|FIS|A/A)\b\D{0,40}?\b(1[1-3]\d[.,]\d{2,3})\b")
+        .expect("the pattern is valid")
+});
+
+/// Degrees from either ARP form.
+fn degrees(text: &str, negative: bool) -> Option<f32> {
+    let text = text.replace(',', ".");
+    let numbers: Vec<&str> = text.split(|c: char| !c.is_ascii_digit() && c != '.').filter(|n| !n.is_empty()).collect();
+    let (d, m, s) = match numbers[..] {
+        [compact] => {
+            let whole = compact.split('.').next()?;
+            let width = whole.len() - 4;
+            (&compact[..width], &compact[width..width + 2], &compact[width + 2..])
+        }
+        [d, m, s] => (d, m, s),
+        _ => return None,
+    };
+    let degrees = d.parse::<f32>().ok()? + m.parse::<f32>().ok()? / 60. + s.parse::<f32>().ok()? / 3600.;
+    Some(if negative { -degrees } else { degrees })
+}
+
+/// The document's text: PDFs have theirs extracted, HTML loses its markup.
+fn text(path: &str) -> Result<String, Box<dyn Error>> {
+    let input = input::open(path)?;
+    let bytes = &input.get_ref()[..];
+    if bytes.starts_with(b"%PDF") {
+        #[cfg(not(target_arch = "wasm32"))]
+        return Ok(pdf_extract::extract_text_from_mem(bytes)?);
+        #[cfg(target_arch = "wasm32")]
+        return Err(format!("{}: PDFs can't be read in the browser build", path).into());
+    }
+    Ok(strip_html(&String::from_utf8_lossy(bytes)))
+}
+
+/// `html` as text: tags become line breaks, comments, scripts and styles go,
+/// and the common entities are decoded.
+fn strip_html(html: &str) -> String {
+    static MARKUP: LazyLock<Regex> = LazyLock::new(|| {
+        Regex::new(r"(?is)<!--.*?-->|<(script|style)\b.*?</(?:script|style)>|<[^>]*>").expect("the pattern is valid")
+    });
+    static ENTITY: LazyLock<Regex> =
+        LazyLock::new(|| Regex::new(r"&(#\d+|#x[0-9a-fA-F]+|[a-z]+);").expect("the pattern is valid"));
+    let text = MARKUP.replace_all(html, "\n");
+    let text = ENTITY.replace_all(&text, |c: &regex::Captures| {
+        let entity = &c[1];
+        let code = match entity {
+            "nbsp" => Some(' '),
+            "amp" => Some('&'),
+            "lt" => Some('<'),
+            "gt" => Some('>'),
+            "quot" => Some('"'),
+            "apos" => Some('\''),
+            "deg" => Some('°'),
+            "mdash" => Some('—'),
+            "ndash" => Some('–'),
+            _ if entity.starts_with("#x") => u32::from_str_radix(&entity[2..], 16).ok().and_then(char::from_u32),
+            _ if entity.starts_with('#') => entity[1..].parse().ok().and_then(char::from_u32),
+            _ => None,
+        };
+        code.map_or_else(|| c[0].to_owned(), String::from)
+    });
+    // One space between words and no runs of blank lines; the headings end
+    // at a line break.
+    let lines: Vec<String> = text
+        .lines()
+        .map(|line| line.split_whitespace().collect::<Vec<_>>().join(" "))
+        .filter(|line| !line.is_empty())
+        .collect();
+    lines.join("\n")
+}
+
+/// The aerodromes found in `text`, one per heading that is followed by an
+/// ARP before the next heading. Aerodromes that fail validation are
+/// skipped with a warning.
+pub fn parse(text: &str, source: &str) -> Vec<Waypoint> {
+    let headings: Vec<_> = HEADING.captures_iter(text).collect();
+    let mut waypoints: Vec<Waypoint> = vec![];
+    for (i, heading) in headings.iter().enumerate() {
+        let whole = heading.get(0).expect("group 0 always matches");
+        let end = headings.get(i + 1).map_or(text.len(), |next| next.get(0).expect("group 0 always matches").start());
+        let section = &text[whole.end()..end];
+        let Some(arp) = ARP.captures(section) else {
+            continue;
+        };
+        let (Some(latitude), Some(longitude)) =
+            (degrees(&arp["lat"], &arp["ns"] == "S"), degrees(&arp["lon"], &arp["ew"] == "W"))
+        else {
+            continue;
+        };
+        let icao = &heading[1];
+        // Each aerodrome has several headed pages; the first with an ARP counts.
+        if waypoints.iter().any(|w| w.ident == icao) {
+            continue;
+        }
+        let elevation = ELEVATION.captures(section).and_then(|c| {
+            let value = c[1].replace(',', ".").parse::<f32>().ok()?;
+            Some(match c[2].eq_ignore_ascii_case("m") {
+                true => (value * FEET_PER_METRE).round(),
+                false => value,
+            })
+        });
+        let mut frequencies: Vec<String> = vec![];
+        for c in FREQUENCY.captures_iter(section) {
+            let frequency = format!("{} {} MHz", &c[1], c[2].replace(',', "."));
+            if !frequencies.contains(&frequency) {
+                frequencies.push(frequency);
+            }
+        }
+        let name = heading[2].trim();
+        let waypoint_type = if name.contains("HELI") || section.contains("HELIPORT") { "Helipad" } else { "Airport" };
+        let mut waypoint = Waypoint::builder(name)
+            .waypoint_type(waypoint_type)
+            .ident(icao)
+            .coordinates(latitude, longitude)
+            .elevation(elevation)
+            .region(region::POLAND)
+            .import_filename(source);
+        if !frequencies.is_empty() {
+            waypoint = waypoint.description(frequencies.join(", "));
+        }
+        match waypoint.build() {
+            Ok(waypoint) => waypoints.push(waypoint),
+            Err(e) => warn!("skipping {}: {}", icao, e),
+        }
+    }
+    waypoints
+}
+
+/// Reads the aerodromes of an AD 4 section saved as HTML or PDF.
+pub fn read_ad(path: &str) -> Result<Vec<Waypoint>, Box<dyn Error>> {
+    let waypoints = parse(&text(path)?, path);
+    if waypoints.is_empty() {
+        return Err(format!("{}: no aerodrome headings with an ARP found", path).into());
+    }
+    Ok(waypoints)
+}
+
+pub struct AipVfr;
+
+impl InputFormat for AipVfr {
+    fn name(&self) -> &'static str {
+        "aip-vfr"
+    }
+
+    fn description(&self) -> &'static str {
+        "AIP VFR Poland AD 4 sections as HTML or PDF, best effort"
+    }
+
+    fn fields(&self) -> Fields {
+        Fields { elevation: true, description: true, ..Fields::default() }
+    }
+
+    fn read(&self, path: &str, _: &ReadOptions) -> Result<Records, Box<dyn Error>> {
+        Ok(Box::new(read_ad(path)?.into_iter()))
+    }
+}
@@ -0,0 +1,117 @@
+//! `--ask`: puts records with no elevation, or with a position that only
+//! makes sense read another way, to the operator with suggestions instead of
+//! writing them as they are.
+
+use crate::dem::Dem;
+use crate::elevation::FEET_PER_METRE;
+use crate::{geo, preview, Waypoint};
+use std::error::Error;
+use std::io::{self, BufRead, Lines, StdinLock, Write};
+
+/// Degrees written as `DDMM.mm`, as some sources do without saying so.
+fn from_degrees_minutes(value: f64) -> f64 {
+    let degrees = (value / 100.).trunc();
+    degrees + (value - degrees * 100.) / 60.
+}
+
+/// The ways to read a record's position: as read first, then the others
+/// that land in Poland.
+fn readings(waypoint: &Waypoint) -> Vec<(&'static str, f64, f64)> {
+    let (lat, lon) = (waypoint.latitude as f64, waypoint.longitude as f64);
+    let mut readings = vec![("as read", lat, lon), ("latitude and longitude swapped", lon, lat)];
+    if lat.abs() >= 100. || lon.abs() >= 100. {
+        readings.push(("degrees and minutes, DDMM.mm", from_degrees_minutes(lat), from_degrees_minutes(lon)));
+    }
+    let mut readings: Vec<_> = readings
+        .into_iter()
+        .enumerate()
+        .filter(|(i, (_, lat, lon))| *i == 0 || geo::POLAND.contains(*lat, *lon))
+        .map(|(_, reading)| reading)
+        .collect();
+    readings.dedup_by(|a, b| a.1 == b.1 && a.2 == b.2);
+    readings
+}
+
+/// Prints `question` and reads the answer, or `None` once the input ends.
+fn answer(lines: &mut Lines<StdinLock>, question: &str) -> Result<Option<String>, Box<dyn Error>> {
+    eprint!("{}", question);
+    io::stderr().flush()?;
+    Ok(lines.next().transpose()?.map(|line| line.trim().to_owned()))
+}
+
+fn ask_position(lines: &mut Lines<StdinLock>, waypoint: &mut Waypoint) -> Result<(), Box<dyn Error>> {
+    let readings = readings(waypoint);
+    if readings.len() < 2 {
+        return Ok(());
+    }
+    let marks: Vec<(f64, f64, char)> = readings
+        .iter()
+        .zip('1'..)
+        .map(|(&(_, lat, lon), mark)| (lat, lon, mark))
+        .collect();
+    eprint!("{}", preview::render_points(&marks, 60));
+    eprintln!("{} ({}) is outside Poland as read:", waypoint.ident, waypoint.name);
+    for (i, (label, lat, lon)) in readings.iter().enumerate() {
+        eprintln!("  {}) {:.5}, {:.5}  {}", i + 1, lat, lon, label);
+    }
+    while let Some(choice) = answer(lines, "Which one? [1] ")? {
+        let i: usize = match choice.as_str() {
+            "" => 1,
+            n => n.parse().unwrap_or(0),
+        };
+        if let Some(&(_, lat, lon)) = i.checked_sub(1).and_then(|i| readings.get(i)) {
+            (waypoint.latitude, waypoint.longitude) = (lat as f32, lon as f32);
+            break;
+        }
+        eprintln!("choose 1 to {}", readings.len());
+    }
+    Ok(())
+}
+
+fn ask_elevation(lines: &mut Lines<StdinLock>, waypoint: &mut Waypoint, dem: Option<&mut Dem>) -> Result<(), Box<dyn Error>> {
+    let suggestion = match dem {
+        Some(dem) => dem
+            .elevation(waypoint.latitude as f64, waypoint.longitude as f64)?
+            .map(|metres| (metres * FEET_PER_METRE).round()),
+        None => None,
+    };
+    let question = match suggestion {
+        Some(feet) => format!(
+            "{} ({}) has no elevation; the terrain there is {} ft. Feet, Enter for {} or - for none: ",
+            waypoint.ident, waypoint.name, feet, feet
+        ),
+        None => format!("{} ({}) has no elevation. Feet, or Enter for none: ", waypoint.ident, waypoint.name),
+    };
+    while let Some(reply) = answer(lines, &question)? {
+        match reply.as_str() {
+            "" => waypoint.elevation = suggestion,
+            "-" => waypoint.elevation = None,
+            feet => match feet.parse::<f32>() {
+                Ok(feet) if feet.is_finite() => waypoint.elevation = Some(feet),
+                _ => {
+                    eprintln!("{:?} is not a number of feet", feet);
+                    continue;
+                }
+            },
+        }
+        break;
+    }
+    Ok(())
+}
+
+/// Asks about each record outside Poland that another reading of its
+/// position would put inside, and each record without an elevation,
+/// suggesting the terrain elevation from `dem`. Once the input ends the
+/// remaining records are left as they are.
+pub fn ask(waypoints: &mut [Waypoint], mut dem: Option<&mut Dem>) -> Result<(), Box<dyn Error>> {
+    let mut lines = io::stdin().lock().lines();
+    for waypoint in waypoints {
+        if !geo::POLAND.contains(waypoint.latitude as f64, waypoint.longitude as f64) {
+            ask_position(&mut lines, waypoint)?;
+        }
+        if waypoint.elevation.is_none() {
+            ask_elevation(&mut lines, waypoint, dem.as_deref_mut())?;
+        }
+    }
+    Ok(())
+}
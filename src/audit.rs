@@ -0,0 +1,130 @@
+//! The `--audit` log: a JSON line for every record as it was parsed and for
+//! every change a later step made to it, so each value in a published
+//! dataset can be traced back to its source.
+
+use crate::Waypoint;
+use serde::Serialize;
+use serde_json::Value;
+use std::error::Error;
+use std::fs::File;
+use std::io::{BufWriter, Write};
+
+/// One line of the log. `record` is the ident as the step found it, so a
+/// step that renames a record logs the new ident as its `Ident` change.
+#[derive(Debug, Serialize)]
+struct Entry<'a> {
+    record: &'a str,
+    step: &'a str,
+    /// The file, URL or rules the step took its data from.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    source: Option<&'a str>,
+    /// The whole record, for `parse`.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    value: Option<&'a Waypoint>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    field: Option<&'a str>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    old: Option<&'a Value>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    new: Option<&'a Value>,
+    /// Why the record was dropped, merged or flagged.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    decision: Option<&'a str>,
+}
+
+impl<'a> Entry<'a> {
+    fn new(record: &'a str, step: &'a str) -> Self {
+        Entry { record, step, source: None, value: None, field: None, old: None, new: None, decision: None }
+    }
+}
+
+/// Writes the log, or does nothing when no `--audit` path was given.
+#[derive(Default)]
+pub struct Audit {
+    writer: Option<BufWriter<File>>,
+}
+
+impl Audit {
+    pub fn create(path: Option<&str>) -> Result<Self, Box<dyn Error>> {
+        let writer = match path {
+            Some(path) => Some(BufWriter::new(File::create(path).map_err(|e| format!("{}: {}", path, e))?)),
+            None => None,
+        };
+        Ok(Audit { writer })
+    }
+
+    fn write(&mut self, entry: &Entry) -> Result<(), Box<dyn Error>> {
+        if let Some(writer) = &mut self.writer {
+            serde_json::to_writer(&mut *writer, entry)?;
+            writer.write_all(b"\n")?;
+        }
+        Ok(())
+    }
+
+    /// Logs each record as converted from the input.
+    pub fn parsed(&mut self, waypoints: &[Waypoint], input: &str) -> Result<(), Box<dyn Error>> {
+        for waypoint in waypoints {
+            let source = waypoint.import_filename.as_deref().unwrap_or(input);
+            self.write(&Entry { source: Some(source), value: Some(waypoint), ..Entry::new(&waypoint.ident, "parse") })?;
+        }
+        Ok(())
+    }
+
+    /// A copy of the records to compare with after a step, or `None` when
+    /// there is no log to write.
+    pub fn snapshot(&self, waypoints: &[Waypoint]) -> Option<Vec<Waypoint>> {
+        self.writer.is_some().then(|| waypoints.to_vec())
+    }
+
+    /// Logs every field `step` changed, comparing the records with the
+    /// `before` snapshot; the step must keep the records in place.
+    pub fn changed(
+        &mut self,
+        step: &str,
+        source: Option<&str>,
+        before: Option<Vec<Waypoint>>,
+        after: &[Waypoint],
+    ) -> Result<(), Box<dyn Error>> {
+        let Some(before) = before else {
+            return Ok(());
+        };
+        for (old, new) in before.iter().zip(after) {
+            if old == new {
+                continue;
+            }
+            let (Value::Object(old_fields), Value::Object(new_fields)) = (serde_json::to_value(old)?, serde_json::to_value(new)?)
+            else {
+                continue;
+            };
+            for (field, old_value) in &old_fields {
+                let new_value = &new_fields[field];
+                if old_value != new_value {
+                    let entry = Entry { source, field: Some(field), old: Some(old_value), new: Some(new_value), ..Entry::new(&old.ident, step) };
+                    self.write(&entry)?;
+                }
+            }
+        }
+        Ok(())
+    }
+
+    /// Logs the records `step` dropped from the run.
+    pub fn dropped(&mut self, step: &str, reason: &str, waypoints: &[Waypoint]) -> Result<(), Box<dyn Error>> {
+        for waypoint in waypoints {
+            self.write(&Entry { decision: Some(reason), ..Entry::new(&waypoint.ident, step) })?;
+        }
+        Ok(())
+    }
+
+    /// Logs a decision about one record, such as dedupe merging it into
+    /// another.
+    pub fn decided(&mut self, step: &str, record: &str, decision: &str) -> Result<(), Box<dyn Error>> {
+        self.write(&Entry { decision: Some(decision), ..Entry::new(record, step) })
+    }
+
+    pub fn finish(self) -> Result<(), Box<dyn Error>> {
+        if let Some(mut writer) = self.writer {
+            writer.flush()?;
+        }
+        Ok(())
+    }
+}
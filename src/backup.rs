@@ -0,0 +1,80 @@
+//! Timestamped copies of output files taken before a run overwrites them,
+//! and `restore` to put one back.
+
+use std::error::Error;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+const EXTENSION: &str = "bak";
+
+/// `userpoints.csv` → `userpoints.csv.20261014-120446.bak`; the timestamps
+/// sort in the order the backups were taken.
+fn backup_path(path: &Path, stamp: &str) -> PathBuf {
+    let mut name = path.file_name().unwrap_or_default().to_os_string();
+    name.push(format!(".{}.{}", stamp, EXTENSION));
+    path.with_file_name(name)
+}
+
+/// The backups of `path`, oldest first, with their timestamps.
+pub fn list(path: &Path) -> Result<Vec<(String, PathBuf)>, Box<dyn Error>> {
+    let name = path.file_name().ok_or_else(|| format!("{} is not a file", path.display()))?.to_string_lossy();
+    let prefix = format!("{}.", name);
+    let suffix = format!(".{}", EXTENSION);
+    let dir = match path.parent() {
+        Some(dir) if !dir.as_os_str().is_empty() => dir,
+        _ => Path::new("."),
+    };
+    let mut backups = vec![];
+    if !dir.is_dir() {
+        return Ok(backups);
+    }
+    for entry in fs::read_dir(dir)? {
+        let file = entry?.file_name().to_string_lossy().into_owned();
+        let Some(stamp) = file.strip_prefix(&prefix).and_then(|s| s.strip_suffix(&suffix)) else {
+            continue;
+        };
+        if stamp.len() == 15 && stamp.chars().all(|c| c.is_ascii_digit() || c == '-') {
+            backups.push((stamp.to_owned(), backup_path(path, stamp)));
+        }
+    }
+    backups.sort();
+    Ok(backups)
+}
+
+/// Copies `path`, when it exists, to a timestamped backup next to it and
+/// removes all but the `keep` newest backups. Keeping none disables it.
+pub fn keep(path: &str, keep: usize) -> Result<(), Box<dyn Error>> {
+    let path = Path::new(path);
+    if keep == 0 || !path.is_file() {
+        return Ok(());
+    }
+    let stamp = chrono::Local::now().format("%Y%m%d-%H%M%S").to_string();
+    let backup = backup_path(path, &stamp);
+    fs::copy(path, &backup).map_err(|e| format!("{}: {}", backup.display(), e))?;
+    let backups = list(path)?;
+    for (_, old) in backups.iter().take(backups.len().saturating_sub(keep)) {
+        fs::remove_file(old)?;
+    }
+    tracing::debug!(file = %path.display(), backup = %backup.display(), "backed up");
+    Ok(())
+}
+
+/// Puts the backup of `path` taken at `stamp`, or the newest one, back in
+/// its place. The file it replaces is backed up first, so a restore can be
+/// undone in turn.
+pub fn restore(path: &str, stamp: Option<&str>, backups: usize) -> Result<(), Box<dyn Error>> {
+    let available = list(Path::new(path))?;
+    let (stamp, backup) = match stamp {
+        Some(stamp) => available
+            .into_iter()
+            .find(|(s, _)| s == stamp)
+            .ok_or_else(|| format!("{} has no backup from {}", path, stamp))?,
+        None => available.into_iter().last().ok_or_else(|| format!("{} has no backups", path))?,
+    };
+    // Copied aside first: backing up the current file may prune this one.
+    let restored = fs::read(&backup)?;
+    keep(path, backups)?;
+    fs::write(path, restored).map_err(|e| format!("{}: {}", path, e))?;
+    eprintln!("restored {} from the backup of {}", path, stamp);
+    Ok(())
+}
@@ -0,0 +1,168 @@
+use crate::{Error, Position, Waypoint};
+
+pub const MAX_NAME_LENGTH: usize = 100;
+pub const MAX_IDENT_LENGTH: usize = 50;
+
+/// Builds a [`Waypoint`], checking its fields when [`build`](Self::build) is
+/// called. Only the name and position are required; the ident defaults to
+/// the name, cut to length and without the characters an ident can't have,
+/// and the type to `Airstrip`.
+#[derive(Debug, Clone)]
+pub struct WaypointBuilder {
+    waypoint_type: String,
+    name: String,
+    ident: Option<String>,
+    position: Option<(f32, f32)>,
+    elevation: Option<f32>,
+    magnetic_declination: Option<f32>,
+    tags: Option<String>,
+    description: Option<String>,
+    region: Option<String>,
+    visible_from: Option<i32>,
+    import_filename: Option<String>,
+}
+
+impl Waypoint {
+    pub fn builder(name: impl Into<String>) -> WaypointBuilder {
+        WaypointBuilder {
+            waypoint_type: "Airstrip".to_owned(),
+            name: name.into(),
+            ident: None,
+            position: None,
+            elevation: None,
+            magnetic_declination: None,
+            tags: None,
+            description: None,
+            region: None,
+            visible_from: None,
+            import_filename: None,
+        }
+    }
+}
+
+fn check_range(field: &'static str, value: f32, min: f32, max: f32) -> Result<(), Error> {
+    match (min..=max).contains(&value) {
+        true => Ok(()),
+        false => Err(Error::invalid(field, &value.to_string())),
+    }
+}
+
+/// `s` without surrounding whitespace, reusing its allocation when there is none.
+fn trimmed(s: String) -> String {
+    match s.trim().len() == s.len() {
+        true => s,
+        false => s.trim().to_owned(),
+    }
+}
+
+/// Whether an ident may contain `c`.
+fn ident_char(c: char) -> bool {
+    !c.is_control() && !",;\"".contains(c)
+}
+
+fn check_text(field: &'static str, value: &str, max: usize, allowed: impl Fn(char) -> bool) -> Result<(), Error> {
+    let length = value.chars().count();
+    match length > 0 && length <= max && value.chars().all(allowed) {
+        true => Ok(()),
+        false => Err(Error::invalid(field, value)),
+    }
+}
+
+impl WaypointBuilder {
+    pub fn waypoint_type(mut self, waypoint_type: impl Into<String>) -> Self {
+        self.waypoint_type = waypoint_type.into();
+        self
+    }
+
+    pub fn ident(mut self, ident: impl Into<String>) -> Self {
+        self.ident = Some(ident.into());
+        self
+    }
+
+    /// Latitude and longitude in decimal degrees.
+    pub fn coordinates(mut self, latitude: f32, longitude: f32) -> Self {
+        self.position = Some((latitude, longitude));
+        self
+    }
+
+    pub fn position(self, position: &Position) -> Self {
+        self.coordinates(position.lat.to_decimal_degrees(), position.lon.to_decimal_degrees())
+    }
+
+    /// Elevation in feet.
+    pub fn elevation(mut self, elevation: Option<f32>) -> Self {
+        self.elevation = elevation;
+        self
+    }
+
+    pub fn magnetic_declination(mut self, declination: f32) -> Self {
+        self.magnetic_declination = Some(declination);
+        self
+    }
+
+    pub fn tags(mut self, tags: impl Into<String>) -> Self {
+        self.tags = Some(tags.into());
+        self
+    }
+
+    pub fn description(mut self, description: impl Into<String>) -> Self {
+        self.description = Some(description.into());
+        self
+    }
+
+    pub fn region(mut self, region: impl Into<String>) -> Self {
+        self.region = Some(region.into());
+        self
+    }
+
+    pub fn visible_from(mut self, visible_from: i32) -> Self {
+        self.visible_from = Some(visible_from);
+        self
+    }
+
+    pub fn import_filename(mut self, import_filename: impl Into<String>) -> Self {
+        self.import_filename = Some(import_filename.into());
+        self
+    }
+
+    /// Checks that coordinates are on the globe, the elevation is plausible,
+    /// the trimmed name and ident are non-empty and within length, and the
+    /// ident has no commas, semicolons or quotes, which device importers
+    /// choke on.
+    pub fn build(self) -> Result<Waypoint, Error> {
+        let (latitude, longitude) = self.position.ok_or(Error::MissingAttribute("Position"))?;
+        check_range("latitude", latitude, -90., 90.)?;
+        check_range("longitude", longitude, -180., 180.)?;
+        if let Some(elevation) = self.elevation {
+            check_range("elevation", elevation, -1500., 30000.)?;
+        }
+        if let Some(declination) = self.magnetic_declination {
+            check_range("magnetic declination", declination, -180., 180.)?;
+        }
+        if let Some(visible_from) = self.visible_from {
+            check_range("visible from", visible_from as f32, 0., f32::MAX)?;
+        }
+        let name = trimmed(self.name);
+        check_text("name", &name, MAX_NAME_LENGTH, |c| !c.is_control())?;
+        let ident = match self.ident {
+            Some(ident) => trimmed(ident),
+            None => trimmed(name.chars().filter(|&c| ident_char(c)).take(MAX_IDENT_LENGTH).collect()),
+        };
+        check_text("ident", &ident, MAX_IDENT_LENGTH, ident_char)?;
+        Ok(Waypoint {
+            waypoint_type: self.waypoint_type,
+            name,
+            ident,
+            latitude,
+            longitude,
+            elevation: self.elevation,
+            magnetic_declination: self.magnetic_declination,
+            tags: self.tags,
+            description: self.description,
+            region: self.region,
+            visible_from: self.visible_from,
+            last_edit: None,
+            import_filename: self.import_filename,
+        })
+    }
+}
@@ -0,0 +1,77 @@
+use crate::pipeline::Outputs;
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use std::collections::HashMap;
+use std::error::Error;
+use std::{fs, path::Path};
+use tracing::info;
+
+const VERSION: u32 = 7;
+
+#[derive(Serialize, Deserialize, Default)]
+struct CacheFile {
+    version: u32,
+    entries: HashMap<String, Outputs>,
+}
+
+/// Per-record conversion results keyed by a SHA-256 of the record's source
+/// text and the conversion settings. Entries not used by a run are dropped
+/// when it saves, so the file tracks the current input.
+pub struct RecordCache {
+    path: String,
+    settings: String,
+    stored: HashMap<String, Outputs>,
+    used: HashMap<String, Outputs>,
+    hits: usize,
+    misses: usize,
+}
+
+impl RecordCache {
+    pub fn open(path: &str, settings: &str) -> Result<Self, Box<dyn Error>> {
+        let stored = if Path::new(path).exists() {
+            let file: CacheFile = serde_json::from_str(&fs::read_to_string(path)?)?;
+            if file.version == VERSION { file.entries } else { HashMap::new() }
+        } else {
+            HashMap::new()
+        };
+        Ok(RecordCache {
+            path: path.to_owned(),
+            settings: settings.to_owned(),
+            stored,
+            used: HashMap::new(),
+            hits: 0,
+            misses: 0,
+        })
+    }
+
+    fn key(&self, source: &str) -> String {
+        let mut hasher = Sha256::new();
+        hasher.update(self.settings.as_bytes());
+        hasher.update([0]);
+        hasher.update(source.as_bytes());
+        hasher.finalize().iter().map(|b| format!("{:02x}", b)).collect()
+    }
+
+    pub fn get(&mut self, source: &str) -> Option<Outputs> {
+        let key = self.key(source);
+        let hit = self.stored.remove(&key).or_else(|| self.used.get(&key).cloned())?;
+        self.used.insert(key, hit.clone());
+        self.hits += 1;
+        Some(hit)
+    }
+
+    pub fn insert(&mut self, source: &str, produced: &Outputs) {
+        self.used.insert(self.key(source), produced.clone());
+        self.misses += 1;
+    }
+
+    pub fn save(self) -> Result<(), Box<dyn Error>> {
+        info!("{} of {} records reused from {}", self.hits, self.hits + self.misses, self.path);
+        let file = CacheFile {
+            version: VERSION,
+            entries: self.used,
+        };
+        fs::write(&self.path, serde_json::to_string(&file)?)?;
+        Ok(())
+    }
+}
@@ -0,0 +1,51 @@
+use crate::geo;
+use crate::runway::RunwayEnd;
+use clap::ValueEnum;
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, ValueEnum)]
+pub enum PatternSide {
+    Left,
+    Right,
+}
+
+/// Shape of a standard rectangular traffic pattern around a runway.
+#[derive(Clone, Copy, Debug)]
+pub struct PatternGeometry {
+    /// Lateral distance of the downwind leg from the runway centerline (metres).
+    pub offset: f64,
+    pub side: PatternSide,
+}
+
+/// A named join point of the pattern flown for one landing direction.
+#[derive(Debug, PartialEq)]
+pub struct PatternPoint {
+    pub leg: &'static str,
+    pub latitude: f64,
+    pub longitude: f64,
+}
+
+impl PatternGeometry {
+    /// Downwind join abeam the runway midpoint and the base turn, which sits as
+    /// far behind the threshold as downwind is from the centerline.
+    pub fn points(&self, end: &RunwayEnd, lat: f64, lon: f64) -> [PatternPoint; 2] {
+        let lateral = match self.side {
+            PatternSide::Left => end.heading + 270.,
+            PatternSide::Right => end.heading + 90.,
+        } % 360.;
+        let (dw_lat, dw_lon) = geo::destination(lat, lon, lateral, self.offset);
+        let (base_lat, base_lon) = geo::destination(end.latitude, end.longitude, geo::reciprocal(end.heading), self.offset);
+        let (base_lat, base_lon) = geo::destination(base_lat, base_lon, lateral, self.offset);
+        [
+            PatternPoint {
+                leg: "downwind",
+                latitude: dw_lat,
+                longitude: dw_lon,
+            },
+            PatternPoint {
+                leg: "base",
+                latitude: base_lat,
+                longitude: base_lon,
+            },
+        ]
+    }
+}
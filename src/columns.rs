@@ -0,0 +1,162 @@
+use crate::elevation::ElevationUnit;
+use crate::Waypoint;
+use serde_json::{Map, Value};
+use std::fmt;
+use std::str::FromStr;
+
+/// Output column names in the default order, as written in the header.
+pub const ALL: [&str; 13] = [
+    "Type",
+    "Name",
+    "Ident",
+    "Latitude",
+    "Longitude",
+    "Elevation",
+    "Magnetic Declination",
+    "Tags",
+    "Description",
+    "Region",
+    "Visible From",
+    "Last Edit",
+    "Import Filename",
+];
+
+/// Which waypoint fields to write and in what order, parsed from a list such
+/// as `ident,name,lat,lon,elev`.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Columns(pub Vec<&'static str>);
+
+impl Default for Columns {
+    fn default() -> Self {
+        Columns(ALL.to_vec())
+    }
+}
+
+fn column(name: &str) -> Option<&'static str> {
+    Some(match name.trim().to_lowercase().replace(['_', '-'], " ").as_str() {
+        "type" => "Type",
+        "name" => "Name",
+        "ident" => "Ident",
+        "lat" | "latitude" => "Latitude",
+        "lon" | "longitude" => "Longitude",
+        "elev" | "elevation" => "Elevation",
+        "declination" | "magnetic declination" => "Magnetic Declination",
+        "tags" => "Tags",
+        "description" => "Description",
+        "region" => "Region",
+        "visible from" => "Visible From",
+        "last edit" => "Last Edit",
+        "import filename" => "Import Filename",
+        _ => return None,
+    })
+}
+
+impl FromStr for Columns {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let columns = s
+            .split(',')
+            .map(|c| column(c).ok_or_else(|| format!("unknown column {:?}", c)))
+            .collect::<Result<Vec<_>, _>>()?;
+        if columns.is_empty() {
+            return Err("no columns given".to_owned());
+        }
+        Ok(Columns(columns))
+    }
+}
+
+impl fmt::Display for Columns {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}", self.0.join(","))
+    }
+}
+
+/// Keeps the shortest decimal form of an `f32` rather than its exact `f64`
+/// widening, so 53.16556 doesn't become 53.16556167602539.
+fn number(x: f32) -> Value {
+    x.to_string()
+        .parse::<f64>()
+        .ok()
+        .and_then(serde_json::Number::from_f64)
+        .map_or(Value::Null, Value::Number)
+}
+
+fn text(s: &Option<String>) -> Value {
+    s.clone().map_or(Value::Null, Value::String)
+}
+
+fn value(waypoint: &Waypoint, column: &str) -> Value {
+    match column {
+        "Type" => Value::String(waypoint.waypoint_type.clone()),
+        "Name" => Value::String(waypoint.name.clone()),
+        "Ident" => Value::String(waypoint.ident.clone()),
+        "Latitude" => number(waypoint.latitude),
+        "Longitude" => number(waypoint.longitude),
+        "Elevation" => waypoint.elevation.map_or(Value::Null, number),
+        "Magnetic Declination" => waypoint.magnetic_declination.map_or(Value::Null, number),
+        "Tags" => text(&waypoint.tags),
+        "Description" => text(&waypoint.description),
+        "Region" => text(&waypoint.region),
+        "Visible From" => waypoint.visible_from.map_or(Value::Null, Value::from),
+        "Last Edit" => text(&waypoint.last_edit),
+        "Import Filename" => text(&waypoint.import_filename),
+        _ => Value::Null,
+    }
+}
+
+impl Columns {
+    /// The selected fields of `waypoint`, keyed by column name, in order.
+    pub fn select(&self, waypoint: &Waypoint) -> Map<String, Value> {
+        self.0.iter().map(|c| (c.to_string(), value(waypoint, c))).collect()
+    }
+
+    /// Like [`Columns::select`], with the elevation converted from feet to
+    /// `unit`. Only userpoints CSV and JSON offer other units; the export
+    /// formats keep feet.
+    pub fn select_in(&self, waypoint: &Waypoint, unit: ElevationUnit) -> Map<String, Value> {
+        let mut row = self.select(waypoint);
+        if let (Some(cell), Some(feet)) = (row.get_mut("Elevation"), waypoint.elevation) {
+            *cell = number(unit.convert_feet(feet));
+        }
+        row
+    }
+}
+
+/// How numbers are spelled in CSV cells; JSON always keeps plain
+/// dot-decimal numbers.
+#[derive(Clone, Copy, Debug, Default)]
+pub struct NumberFormat {
+    /// Write `53,16556` for Polish-locale spreadsheets.
+    pub decimal_comma: bool,
+    /// Round fractional numbers to at most this many decimal places.
+    pub precision: Option<u32>,
+}
+
+impl NumberFormat {
+    fn format(&self, n: serde_json::Number) -> String {
+        let n = match (self.precision, n.as_f64()) {
+            (Some(places), Some(x)) if n.is_f64() => {
+                let scale = 10f64.powi(places as i32);
+                serde_json::Number::from_f64((x * scale).round() / scale).unwrap_or(n)
+            }
+            _ => n,
+        };
+        match self.decimal_comma {
+            true => n.to_string().replace('.', ","),
+            false => n.to_string(),
+        }
+    }
+}
+
+/// A selected (and possibly mapped) row as CSV cells.
+pub fn cells(row: Map<String, Value>, format: &NumberFormat) -> Vec<String> {
+    row.into_iter()
+        .map(|(_, v)| match v {
+            Value::Null => String::new(),
+            Value::String(s) => s,
+            Value::Number(n) => format.format(n),
+            v => v.to_string(),
+        })
+        .collect()
+}
@@ -0,0 +1,98 @@
+use crate::{geo, Waypoint};
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::error::Error;
+use std::fs;
+use std::io::{self, BufRead, Write};
+use tracing::warn;
+
+/// Operator decisions, keyed by ident, naming the source to trust, e.g.
+///
+/// ```toml
+/// [position]
+/// Minikowo = "local"
+/// ```
+#[derive(Serialize, Deserialize, Debug, Default)]
+pub struct Decisions {
+    #[serde(default)]
+    pub position: BTreeMap<String, String>,
+}
+
+/// Settles merge conflicts that precedence alone shouldn't: positions from
+/// different sources further apart than `threshold` metres.
+#[derive(Debug, Default)]
+pub struct ConflictResolver {
+    pub threshold: f64,
+    pub interactive: bool,
+    pub decisions: Decisions,
+}
+
+impl ConflictResolver {
+    pub fn load_decisions(&mut self, path: &str) -> Result<(), Box<dyn Error>> {
+        self.decisions = toml::from_str(&fs::read_to_string(path)?)?;
+        Ok(())
+    }
+
+    pub fn save_decisions(&self, path: &str) -> Result<(), Box<dyn Error>> {
+        fs::write(path, toml::to_string(&self.decisions)?)?;
+        Ok(())
+    }
+
+    fn spread(candidates: &[(&str, &Waypoint)]) -> f64 {
+        let mut max: f64 = 0.;
+        for (i, (_, a)) in candidates.iter().enumerate() {
+            for (_, b) in &candidates[i + 1..] {
+                let d = geo::distance(a.latitude as f64, a.longitude as f64, b.latitude as f64, b.longitude as f64);
+                max = max.max(d);
+            }
+        }
+        max
+    }
+
+    /// Source to take the position of `ident` from, or `None` to leave the
+    /// choice to precedence. `candidates` are in precedence order.
+    pub fn position(&mut self, ident: &str, candidates: &[(&str, &Waypoint)]) -> Option<String> {
+        let spread = Self::spread(candidates);
+        if spread <= self.threshold {
+            return None;
+        }
+        if let Some(source) = self.decisions.position.get(ident) {
+            if candidates.iter().any(|(name, _)| name == source) {
+                return Some(source.clone());
+            }
+            warn!("{}: decided source {} has no record, using precedence", ident, source);
+            return None;
+        }
+        if !self.interactive {
+            warn!("{}: sources disagree on position by {:.0} m, using {}", ident, spread, candidates[0].0);
+            return None;
+        }
+        let choice = prompt(ident, spread, candidates).unwrap_or(0);
+        let source = candidates[choice].0.to_owned();
+        self.decisions.position.insert(ident.to_owned(), source.clone());
+        Some(source)
+    }
+}
+
+fn prompt(ident: &str, spread: f64, candidates: &[(&str, &Waypoint)]) -> io::Result<usize> {
+    let mut stderr = io::stderr();
+    writeln!(stderr, "{}: positions disagree by {:.0} m", ident, spread)?;
+    for (i, (source, w)) in candidates.iter().enumerate() {
+        writeln!(stderr, "  [{}] {:<12} {:.6} {:.6}  {}", i + 1, source, w.latitude, w.longitude, w.name)?;
+    }
+    loop {
+        write!(stderr, "Use which position? [1-{}, Enter for 1] ", candidates.len())?;
+        stderr.flush()?;
+        let mut line = String::new();
+        if io::stdin().lock().read_line(&mut line)? == 0 {
+            return Ok(0);
+        }
+        match line.trim() {
+            "" => return Ok(0),
+            s => match s.parse::<usize>() {
+                Ok(n) if (1..=candidates.len()).contains(&n) => return Ok(n - 1),
+                _ => writeln!(stderr, "Not a choice: {}", s)?,
+            },
+        }
+    }
+}
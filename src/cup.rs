@@ -0,0 +1,204 @@
+//! SeeYou CUP waypoint files, read by XCSoar, LK8000 and most other glider
+//! flight computers.
+
+use crate::columns::Columns;
+use crate::format::{Fields, InputFormat, Limits, OutputFormat, ReadOptions, Records, WriteOptions};
+use crate::{glider, input, region, Waypoint};
+use regex::Regex;
+use std::error::Error;
+use std::num::NonZeroUsize;
+use std::sync::LazyLock;
+
+const HEADER: &str = "name,code,country,lat,lon,elev,style,rwdir,rwlen,freq,desc";
+
+/// A frequency in the description, e.g. `123.500`.
+pub(crate) static FREQUENCY: LazyLock<Regex> =
+    LazyLock::new(|| Regex::new(r"\b1[1-3]\d\.\d{2,3}\b").expect("the pattern is valid"));
+
+/// The CUP waypoint style: 2 is a grass airfield, 4 a gliding site, 5 a
+/// paved airfield, 8 a mast and 1 anything else.
+fn style(waypoint: &Waypoint) -> u8 {
+    if glider::is_glider_site(waypoint) {
+        return 4;
+    }
+    match waypoint.waypoint_type.as_str() {
+        "Airport" => 5,
+        "Airstrip" => 2,
+        "Obstacle" => 8,
+        _ => 1,
+    }
+}
+
+/// `degrees` as CUP degrees and decimal minutes, e.g. `5209.933N`.
+fn coordinate(degrees: f32, width: usize, positive: char, negative: char) -> String {
+    let hemisphere = if degrees < 0. { negative } else { positive };
+    let degrees = degrees.abs() as f64;
+    let mut whole = degrees.trunc();
+    let mut minutes = ((degrees - whole) * 60. * 1000.).round() / 1000.;
+    if minutes >= 60. {
+        whole += 1.;
+        minutes -= 60.;
+    }
+    format!("{:0width$}{:06.3}{}", whole as u32, minutes, hemisphere)
+}
+
+/// Degrees from a CUP coordinate such as `5209.933N` or `01744.817E`.
+fn parse_coordinate(text: &str) -> Option<f32> {
+    let text = text.trim();
+    let (number, sign) = match (text.strip_suffix(['N', 'E', 'n', 'e']), text.strip_suffix(['S', 'W', 's', 'w'])) {
+        (Some(number), _) => (number, 1.),
+        (_, Some(number)) => (number, -1.),
+        _ => return None,
+    };
+    let point = number.find('.').unwrap_or(number.len());
+    let degrees: f64 = number.get(..point.checked_sub(2)?)?.parse().ok()?;
+    let minutes: f64 = number.get(point - 2..)?.parse().ok()?;
+    Some((sign * (degrees + minutes / 60.)) as f32)
+}
+
+/// A CUP elevation in feet, from `262ft`, `80m` or `80.0m`.
+fn parse_elevation(text: &str) -> Option<f32> {
+    let text = text.trim().to_lowercase();
+    if let Some(feet) = text.strip_suffix("ft") {
+        return feet.trim().parse().ok();
+    }
+    let metres: f32 = text.strip_suffix('m').unwrap_or(&text).trim().parse().ok()?;
+    Some((metres * crate::elevation::FEET_PER_METRE).round())
+}
+
+/// The waypoint type for a CUP style; gliding sites are airstrips with the
+/// glider tag.
+fn waypoint_type(style: &str) -> &'static str {
+    match style.trim() {
+        "2" | "3" | "4" => "Airstrip",
+        "5" => "Airport",
+        "8" => "Obstacle",
+        _ => "Waypoint",
+    }
+}
+
+/// `text` as a CUP string: quoted, with quotes inside doubled.
+fn quoted(text: &str) -> String {
+    format!("\"{}\"", text.replace('"', "\"\""))
+}
+
+pub struct Cup;
+
+impl OutputFormat for Cup {
+    fn name(&self) -> &'static str {
+        "cup"
+    }
+
+    fn description(&self) -> &'static str {
+        "SeeYou CUP waypoints for XCSoar and other glider computers"
+    }
+
+    fn extension(&self) -> &'static str {
+        "cup"
+    }
+
+    fn columns(&self) -> Columns {
+        Columns(vec!["Type", "Name", "Ident", "Latitude", "Longitude", "Elevation", "Tags", "Description"])
+    }
+
+    /// Older glider computers keep only 6 characters of the code. Names are
+    /// kept whole: devices that show fewer characters cut them on screen,
+    /// and 12 would leave many Polish names indistinguishable.
+    fn limits(&self) -> Limits {
+        Limits { ident: NonZeroUsize::new(6), name: None }
+    }
+
+    fn render(&self, waypoints: &[Waypoint], options: &WriteOptions) -> Result<Vec<u8>, Box<dyn Error>> {
+        let mut text = format!("{}\n", HEADER);
+        for waypoint in self.shorten(waypoints, options).iter() {
+            let description = waypoint.description.as_deref().unwrap_or_default();
+            let runway = glider::runway(waypoint)
+                .and_then(|r| r.trim_end_matches(['L', 'C', 'R']).parse::<u16>().ok())
+                .map(|r| format!("{:03}", r * 10))
+                .unwrap_or_default();
+            let fields = [
+                quoted(&waypoint.name),
+                quoted(&waypoint.ident),
+                // The country is an ISO code, which an ICAO region doesn't map to.
+                String::new(),
+                coordinate(waypoint.latitude, 2, 'N', 'S'),
+                coordinate(waypoint.longitude, 3, 'E', 'W'),
+                waypoint.elevation.map(|e| format!("{}ft", e.round())).unwrap_or_default(),
+                style(waypoint).to_string(),
+                runway,
+                String::new(),
+                FREQUENCY.find(description).map(|f| f.as_str().to_owned()).unwrap_or_default(),
+                quoted(description),
+            ];
+            text.push_str(&fields.join(","));
+            text.push('\n');
+        }
+        Ok(options.line_ending.apply(&text).into_owned().into_bytes())
+    }
+}
+
+impl InputFormat for Cup {
+    fn name(&self) -> &'static str {
+        "cup"
+    }
+
+    fn description(&self) -> &'static str {
+        "SeeYou CUP waypoints"
+    }
+
+    fn fields(&self) -> Fields {
+        Fields { elevation: true, tags: true, ..Fields::default() }
+    }
+
+    fn read(&self, path: &str, _: &ReadOptions) -> Result<Records, Box<dyn Error>> {
+        let mut reader = csv::ReaderBuilder::new().flexible(true).from_reader(input::open(path)?);
+        // Columns by header name, since SeeYou versions differ in order and
+        // newer ones add columns such as `rwwidth`.
+        let headers: Vec<String> = reader.headers()?.iter().map(|h| h.trim().to_lowercase()).collect();
+        let column = |name: &str| headers.iter().position(|h| h == name);
+        let (name, code, lat, lon) = (column("name"), column("code"), column("lat"), column("lon"));
+        let (elev, style, rwdir, desc) = (column("elev"), column("style"), column("rwdir"), column("desc"));
+        let (Some(name), Some(lat), Some(lon)) = (name, lat, lon) else {
+            return Err(format!("{}: not a CUP file, the header has no name, lat and lon columns", path).into());
+        };
+        let mut waypoints = vec![];
+        for (i, record) in reader.records().enumerate() {
+            let record = record?;
+            let field = |n: Option<usize>| n.and_then(|n| record.get(n)).unwrap_or_default();
+            // Tasks follow the waypoints in the same file.
+            if record.get(0).unwrap_or_default().starts_with("-----Related Tasks") {
+                break;
+            }
+            let position = (parse_coordinate(field(Some(lat))), parse_coordinate(field(Some(lon))));
+            let (Some(latitude), Some(longitude)) = position else {
+                let (lat, lon) = (field(Some(lat)), field(Some(lon)));
+                return Err(format!("{}: row {}: unreadable coordinates {:?} {:?}", path, i + 2, lat, lon).into());
+            };
+            let mut waypoint = Waypoint::builder(field(Some(name)))
+                .waypoint_type(waypoint_type(field(style)))
+                .coordinates(latitude, longitude)
+                .elevation(parse_elevation(field(elev)))
+                .import_filename(path);
+            if !field(code).is_empty() {
+                waypoint = waypoint.ident(field(code));
+            }
+            if let Some(region) = region::of_icao(field(code).trim()) {
+                waypoint = waypoint.region(region);
+            }
+            let mut waypoint = waypoint.build().map_err(|e| e.in_record(format!("{}: row {}", path, i + 2)))?;
+            if field(style).trim() == "4" {
+                waypoint.add_tag(glider::GLIDER_TAG);
+            }
+            if let Ok(direction) = field(rwdir).trim().parse::<u16>() {
+                let designator = match (direction + 5) / 10 % 36 {
+                    0 => 36,
+                    d => d,
+                };
+                glider::set_runway(&mut waypoint, &format!("{:02}", designator));
+            }
+            waypoint.description = Some(field(desc).to_owned()).filter(|d| !d.is_empty());
+            waypoints.push(waypoint);
+        }
+        Ok(Box::new(waypoints.into_iter()))
+    }
+}
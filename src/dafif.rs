@@ -0,0 +1,95 @@
+//! Airfields from DAFIF, the discontinued military flight information
+//! files, read from the tab-separated `ARPT.TXT`. Archived copies still
+//! list strips that have since disappeared from the current sources, so
+//! every record is tagged `unverified` until checked.
+
+use crate::format::{Fields, InputFormat, ReadOptions, Records};
+use crate::{input, merge, region, Coordinate, Error as DataError, Waypoint};
+use serde::Deserialize;
+use std::error::Error;
+
+#[derive(Deserialize)]
+#[serde(rename_all = "SCREAMING_SNAKE_CASE")]
+struct ArptRecord {
+    arpt_ident: String,
+    name: String,
+    icao: Option<String>,
+    wgs_lat: Option<String>,
+    wgs_dlat: Option<f32>,
+    wgs_long: Option<String>,
+    wgs_dlong: Option<f32>,
+    elev: Option<String>,
+    #[serde(rename = "TYPE")]
+    arpt_type: Option<String>,
+    cycle_date: Option<String>,
+}
+
+/// Degrees from the decimal column, or else from the `N52095600` form with
+/// hundredths of seconds and no decimal point.
+fn degrees(decimal: Option<f32>, text: Option<&str>) -> Result<f32, DataError> {
+    if let Some(decimal) = decimal {
+        return Ok(decimal);
+    }
+    let text = text.map(str::trim).unwrap_or_default();
+    if text.len() < 4 || !text.is_ascii() {
+        return Err(DataError::invalid("coordinate", text));
+    }
+    let (whole, hundredths) = text.split_at(text.len() - 2);
+    Coordinate::parse_decimal(&format!("{}.{}", whole, hundredths))
+}
+
+/// Reads DAFIF `ARPT.TXT`, which is always tab-separated. The ICAO code is
+/// the ident where there is one; the DAFIF ident and cycle go into the
+/// description and military fields are tagged `military`.
+pub fn read_arpt(path: &str) -> Result<Vec<Waypoint>, Box<dyn Error>> {
+    let mut reader = csv::ReaderBuilder::new().delimiter(b'\t').from_reader(input::open(path)?);
+    let mut waypoints = vec![];
+    for record in reader.deserialize() {
+        let record: ArptRecord = record?;
+        let icao = record.icao.as_deref().map(str::trim).filter(|i| region::of_icao(i).is_some());
+        let ident = icao.unwrap_or(record.arpt_ident.trim());
+        let latitude = degrees(record.wgs_dlat, record.wgs_lat.as_deref()).map_err(|e| e.in_record(ident))?;
+        let longitude = degrees(record.wgs_dlong, record.wgs_long.as_deref()).map_err(|e| e.in_record(ident))?;
+        let name = record.name.trim();
+        let helipad = name.ends_with("HELIPORT") || name.ends_with("HELIPAD");
+        let mut waypoint = Waypoint::builder(name)
+            .waypoint_type(if helipad { "Helipad" } else { "Airport" })
+            .ident(ident)
+            .coordinates(latitude, longitude)
+            .elevation(record.elev.and_then(|e| e.trim().parse().ok()))
+            .region(icao.and_then(region::of_icao).unwrap_or(region::POLAND))
+            .import_filename(path)
+            .build()
+            .map_err(|e| e.in_record(ident))?;
+        waypoint.add_tag(merge::UNVERIFIED);
+        if record.arpt_type.as_deref() == Some("C") {
+            waypoint.add_tag("military");
+        }
+        waypoint.description = Some(match record.cycle_date.as_deref().map(str::trim).filter(|c| !c.is_empty()) {
+            Some(cycle) => format!("DAFIF {} (cycle {})", record.arpt_ident.trim(), cycle),
+            None => format!("DAFIF {}", record.arpt_ident.trim()),
+        });
+        waypoints.push(waypoint);
+    }
+    Ok(waypoints)
+}
+
+pub struct DafifArpt;
+
+impl InputFormat for DafifArpt {
+    fn name(&self) -> &'static str {
+        "dafif-arpt"
+    }
+
+    fn description(&self) -> &'static str {
+        "DAFIF ARPT.TXT, tagged unverified"
+    }
+
+    fn fields(&self) -> Fields {
+        Fields { elevation: true, ..Fields::default() }
+    }
+
+    fn read(&self, path: &str, _: &ReadOptions) -> Result<Records, Box<dyn Error>> {
+        Ok(Box::new(read_arpt(path)?.into_iter()))
+    }
+}
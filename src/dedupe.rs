@@ -0,0 +1,60 @@
+use crate::matching::SiteMatcher;
+use crate::Waypoint;
+use clap::ValueEnum;
+
+pub const DUPLICATE_TAG: &str = "possible-duplicate";
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, ValueEnum)]
+pub enum DedupeMode {
+    /// Keep every record but tag the later ones and name the site they duplicate
+    Flag,
+    /// Keep the first record, filling its empty fields from the dropped ones
+    Merge,
+}
+
+fn fill_from(into: &mut Waypoint, from: Waypoint) {
+    into.elevation = into.elevation.or(from.elevation);
+    into.magnetic_declination = into.magnetic_declination.or(from.magnetic_declination);
+    into.tags = into.tags.take().or(from.tags);
+    into.description = into.description.take().or(from.description);
+    into.region = into.region.take().or(from.region);
+    into.visible_from = into.visible_from.or(from.visible_from);
+}
+
+/// Finds waypoints that `matcher` considers the same site as an earlier one,
+/// since merged sources often carry the same strip at slightly different
+/// coordinates or under a slightly different name.
+pub fn dedupe(waypoints: Vec<Waypoint>, matcher: &SiteMatcher, mode: DedupeMode) -> Vec<Waypoint> {
+    dedupe_with(waypoints, matcher, mode, |_, _| {})
+}
+
+/// [`dedupe`], calling `decided` with each duplicate and the earlier record
+/// it was found to duplicate, before it is merged or flagged.
+pub fn dedupe_with(
+    waypoints: Vec<Waypoint>,
+    matcher: &SiteMatcher,
+    mode: DedupeMode,
+    mut decided: impl FnMut(&Waypoint, &Waypoint),
+) -> Vec<Waypoint> {
+    let mut kept: Vec<Waypoint> = vec![];
+    for mut waypoint in waypoints {
+        let Some(original) = kept.iter().position(|k| matcher.same_site(k, &waypoint)) else {
+            kept.push(waypoint);
+            continue;
+        };
+        decided(&waypoint, &kept[original]);
+        match mode {
+            DedupeMode::Flag => {
+                waypoint.add_tag(DUPLICATE_TAG);
+                let note = format!("Same site as {}?", kept[original].ident);
+                waypoint.description = Some(match waypoint.description.take() {
+                    Some(d) => format!("{}; {}", d, note),
+                    None => note,
+                });
+                kept.push(waypoint);
+            }
+            DedupeMode::Merge => fill_from(&mut kept[original], waypoint),
+        }
+    }
+    kept
+}
@@ -0,0 +1,120 @@
+//! Terrain elevations from a directory of SRTM `.hgt` tiles, such as those
+//! from viewfinderpanoramas.org or the NASA SRTM 1 and 3 arc-second data.
+
+use crate::elevation::FEET_PER_METRE;
+use crate::enrich::{Enrichment, Lookup, LookupError};
+use crate::format::Fields;
+use crate::Waypoint;
+use std::collections::HashMap;
+use std::error::Error;
+use std::fs;
+use std::path::PathBuf;
+use std::sync::{Mutex, PoisonError};
+
+/// Where a tile has no data.
+const VOID: i16 = -32768;
+
+/// One tile of big-endian samples, north to south, west to east.
+struct Tile {
+    size: usize,
+    samples: Vec<i16>,
+}
+
+impl Tile {
+    fn read(path: &PathBuf) -> Result<Self, Box<dyn Error>> {
+        let bytes = fs::read(path)?;
+        let size = match bytes.len() {
+            2_884_802 => 1201,
+            25_934_402 => 3601,
+            n => return Err(format!("{}: {} bytes is not an SRTM 1 or 3 arc-second tile", path.display(), n).into()),
+        };
+        let samples = bytes.chunks_exact(2).map(|b| i16::from_be_bytes([b[0], b[1]])).collect();
+        Ok(Tile { size, samples })
+    }
+
+    fn sample(&self, row: usize, col: usize) -> Option<f32> {
+        let value = self.samples[row * self.size + col];
+        (value != VOID).then_some(value as f32)
+    }
+
+    /// The elevation at a fraction of the tile from its north-west corner,
+    /// interpolated between the four samples around it.
+    fn elevation(&self, south: f64, east: f64) -> Option<f32> {
+        let last = (self.size - 1) as f64;
+        let (y, x) = (south * last, east * last);
+        let (row, col) = ((y.floor() as usize).min(self.size - 2), (x.floor() as usize).min(self.size - 2));
+        let (dy, dx) = ((y - row as f64) as f32, (x - col as f64) as f32);
+        let top = self.sample(row, col)? * (1. - dx) + self.sample(row, col + 1)? * dx;
+        let bottom = self.sample(row + 1, col)? * (1. - dx) + self.sample(row + 1, col + 1)? * dx;
+        Some(top * (1. - dy) + bottom * dy)
+    }
+}
+
+/// The tiles in a directory, read as they are needed; missing tiles give no
+/// elevation rather than an error.
+pub struct Dem {
+    dir: PathBuf,
+    tiles: HashMap<(i32, i32), Option<Tile>>,
+}
+
+impl Dem {
+    pub fn open(dir: impl Into<PathBuf>) -> Result<Self, Box<dyn Error>> {
+        let dir = dir.into();
+        if !dir.is_dir() {
+            return Err(format!("{}: not a directory of .hgt tiles", dir.display()).into());
+        }
+        Ok(Dem { dir, tiles: HashMap::new() })
+    }
+
+    fn tile_path(&self, south: i32, west: i32) -> PathBuf {
+        let ns = if south < 0 { 'S' } else { 'N' };
+        let ew = if west < 0 { 'W' } else { 'E' };
+        let name = format!("{}{:02}{}{:03}", ns, south.abs(), ew, west.abs());
+        [format!("{}.hgt", name), format!("{}.hgt", name.to_lowercase())]
+            .into_iter()
+            .map(|file| self.dir.join(file))
+            .find(|path| path.is_file())
+            .unwrap_or_else(|| self.dir.join(format!("{}.hgt", name)))
+    }
+
+    /// The terrain elevation in metres, or `None` where no tile covers the
+    /// position or the tile has a void there.
+    pub fn elevation(&mut self, latitude: f64, longitude: f64) -> Result<Option<f32>, Box<dyn Error>> {
+        let (south, west) = (latitude.floor(), longitude.floor());
+        let key = (south as i32, west as i32);
+        if !self.tiles.contains_key(&key) {
+            let path = self.tile_path(key.0, key.1);
+            let tile = if path.is_file() { Some(Tile::read(&path)?) } else { None };
+            self.tiles.insert(key, tile);
+        }
+        let Some(tile) = &self.tiles[&key] else {
+            return Ok(None);
+        };
+        Ok(tile.elevation(1. - (latitude - south), longitude - west))
+    }
+}
+
+/// Fills in the elevation of records without one from the terrain; those
+/// with an elevation keep it.
+pub struct MissingElevations(Mutex<Dem>);
+
+impl MissingElevations {
+    pub fn new(dem: Dem) -> Self {
+        MissingElevations(Mutex::new(dem))
+    }
+}
+
+impl Lookup for MissingElevations {
+    fn provides(&self) -> Fields {
+        Fields { elevation: true, ..Fields::default() }
+    }
+
+    fn lookup(&self, waypoint: &Waypoint, _: Fields) -> Result<Enrichment, LookupError> {
+        if waypoint.elevation.is_some() {
+            return Ok(Enrichment::default());
+        }
+        let mut dem = self.0.lock().unwrap_or_else(PoisonError::into_inner);
+        let metres = dem.elevation(waypoint.latitude as f64, waypoint.longitude as f64).map_err(|e| e.to_string())?;
+        Ok(Enrichment { elevation: metres.map(|m| (m * FEET_PER_METRE).round()), ..Enrichment::default() })
+    }
+}
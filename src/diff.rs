@@ -0,0 +1,172 @@
+use crate::truncate::truncate;
+use crate::{geo, Waypoint};
+use clap::ValueEnum;
+use serde::Serialize;
+use std::fmt::Write;
+
+/// Values longer than this are cut short in the side-by-side view.
+const MAX_WIDTH: usize = 40;
+
+/// How `diff` reports the differences.
+#[derive(Debug, Clone, Copy, Default, PartialEq, ValueEnum)]
+pub enum DiffFormat {
+    /// One line per added, removed or changed airfield and per field
+    #[default]
+    Text,
+    /// Old and new values in aligned columns, coloured on a terminal
+    SideBySide,
+    /// The whole diff as a JSON object
+    Json,
+}
+
+/// One field that differs between two releases of the same airfield.
+#[derive(Debug, PartialEq, Serialize)]
+pub struct FieldChange {
+    pub field: &'static str,
+    pub old: String,
+    pub new: String,
+}
+
+#[derive(Debug, PartialEq, Serialize)]
+pub struct Changed {
+    pub ident: String,
+    /// Distance the position moved, if it moved more than the threshold (metres).
+    pub moved: Option<f64>,
+    /// Latitude and longitude in each release.
+    pub old_position: (f32, f32),
+    pub new_position: (f32, f32),
+    pub fields: Vec<FieldChange>,
+}
+
+#[derive(Debug, Default, Serialize)]
+pub struct DatasetDiff {
+    pub added: Vec<Waypoint>,
+    pub removed: Vec<Waypoint>,
+    pub changed: Vec<Changed>,
+}
+
+fn show<T: ToString>(value: &Option<T>) -> String {
+    value.as_ref().map(T::to_string).unwrap_or_default()
+}
+
+fn field_changes(old: &Waypoint, new: &Waypoint) -> Vec<FieldChange> {
+    let pairs = [
+        ("type", old.waypoint_type.clone(), new.waypoint_type.clone()),
+        ("name", old.name.clone(), new.name.clone()),
+        ("elevation", show(&old.elevation), show(&new.elevation)),
+        ("declination", show(&old.magnetic_declination), show(&new.magnetic_declination)),
+        ("tags", show(&old.tags), show(&new.tags)),
+        ("description", show(&old.description), show(&new.description)),
+        ("region", show(&old.region), show(&new.region)),
+        ("visible_from", show(&old.visible_from), show(&new.visible_from)),
+    ];
+    pairs
+        .into_iter()
+        .filter(|(_, o, n)| o != n)
+        .map(|(field, old, new)| FieldChange { field, old, new })
+        .collect()
+}
+
+/// Compares two datasets by ident. Position changes below `move_threshold`
+/// metres are treated as rounding noise.
+pub fn diff(old: &[Waypoint], new: &[Waypoint], move_threshold: f64) -> DatasetDiff {
+    let find = |set: &'_ [Waypoint], ident: &str| -> Option<usize> {
+        set.iter().position(|w| w.ident.eq_ignore_ascii_case(ident))
+    };
+    let mut result = DatasetDiff::default();
+    for o in old {
+        let Some(n) = find(new, &o.ident).map(|i| &new[i]) else {
+            result.removed.push(o.clone());
+            continue;
+        };
+        let distance = geo::distance(o.latitude as f64, o.longitude as f64, n.latitude as f64, n.longitude as f64);
+        let moved = (distance > move_threshold).then_some(distance);
+        let fields = field_changes(o, n);
+        if moved.is_some() || !fields.is_empty() {
+            result.changed.push(Changed {
+                ident: o.ident.clone(),
+                moved,
+                old_position: (o.latitude, o.longitude),
+                new_position: (n.latitude, n.longitude),
+                fields,
+            });
+        }
+    }
+    result.added = new.iter().filter(|n| find(old, &n.ident).is_none()).cloned().collect();
+    result
+}
+
+impl DatasetDiff {
+    pub fn is_empty(&self) -> bool {
+        self.added.is_empty() && self.removed.is_empty() && self.changed.is_empty()
+    }
+
+    fn counts(&self) -> String {
+        format!("{} added, {} removed, {} changed\n", self.added.len(), self.removed.len(), self.changed.len())
+    }
+
+    pub fn text(&self) -> String {
+        let mut text = String::new();
+        for w in &self.added {
+            writeln!(text, "+ {} ({}, {})", w.ident, w.latitude, w.longitude).unwrap();
+        }
+        for w in &self.removed {
+            writeln!(text, "- {} ({}, {})", w.ident, w.latitude, w.longitude).unwrap();
+        }
+        for c in &self.changed {
+            writeln!(text, "~ {}", c.ident).unwrap();
+            if let Some(moved) = c.moved {
+                writeln!(text, "    position moved {:.0} m", moved).unwrap();
+            }
+            for f in &c.fields {
+                writeln!(text, "    {}: {:?} -> {:?}", f.field, f.old, f.new).unwrap();
+            }
+        }
+        text.push_str(&self.counts());
+        text
+    }
+
+    /// Each changed airfield's old and new values in two aligned columns,
+    /// after the airfields added and removed. With `color`, old values are
+    /// red and new ones green.
+    pub fn side_by_side(&self, color: bool) -> String {
+        let paint = |code: &str, text: &str| if color { format!("\x1b[{}m{}\x1b[0m", code, text) } else { text.to_owned() };
+        let position = |(lat, lon): (f32, f32)| format!("{}, {}", lat, lon);
+        let rows: Vec<Vec<(&str, String, String)>> = self
+            .changed
+            .iter()
+            .map(|c| {
+                let mut rows = vec![];
+                if c.moved.is_some() {
+                    rows.push(("position", position(c.old_position), position(c.new_position)));
+                }
+                rows.extend(c.fields.iter().map(|f| (f.field, truncate(&f.old, MAX_WIDTH), truncate(&f.new, MAX_WIDTH))));
+                rows
+            })
+            .collect();
+        let field_width = rows.iter().flatten().map(|(field, _, _)| field.len()).max().unwrap_or(0);
+        let old_width = rows.iter().flatten().map(|(_, old, _)| old.chars().count()).max().unwrap_or(0);
+
+        let mut text = String::new();
+        for w in &self.added {
+            writeln!(text, "{}", paint("32", &format!("+ {:<8} {} ({}, {})", w.ident, w.name, w.latitude, w.longitude))).unwrap();
+        }
+        for w in &self.removed {
+            writeln!(text, "{}", paint("31", &format!("- {:<8} {} ({}, {})", w.ident, w.name, w.latitude, w.longitude))).unwrap();
+        }
+        for (c, rows) in self.changed.iter().zip(&rows) {
+            writeln!(text, "{}", paint("1", &format!("~ {}", c.ident))).unwrap();
+            for (field, old, new) in rows {
+                // Padded before painting, which would count the escape codes.
+                let old = format!("{:<width$}", old, width = old_width);
+                let mut line = format!("    {:<width$}  {} │ {}", field, paint("31", &old), paint("32", new), width = field_width);
+                if let (&"position", Some(moved)) = (field, c.moved) {
+                    write!(line, "  moved {:.0} m", moved).unwrap();
+                }
+                writeln!(text, "{}", line.trim_end()).unwrap();
+            }
+        }
+        text.push_str(&self.counts());
+        text
+    }
+}
@@ -0,0 +1,89 @@
+//! Aerodromes from EUROCONTROL European AIS Database (EAD) extracts, which
+//! come as AIXM 4.5 `Ahp` records. Used as the official reference when the
+//! list is reconciled each AIRAC cycle.
+
+use crate::elevation::FEET_PER_METRE;
+use crate::format::{Fields, InputFormat, ReadOptions, Records};
+use crate::xml::{Element, Elements};
+use crate::{input, region, Coordinate, Error as DataError, Waypoint};
+use std::error::Error;
+
+/// Parses an AIXM 4.5 `geoLat`/`geoLong`: `521000.00N`, `0205802.00E`, or
+/// decimal degrees such as `52.16667N`.
+pub fn parse_geo(text: &str) -> Result<f32, DataError> {
+    let text = text.trim();
+    let invalid = || DataError::invalid("coordinate", text);
+    let hemisphere = text.chars().last().filter(|c| "NSEW".contains(*c)).ok_or_else(invalid)?;
+    let value = &text[..text.len() - 1];
+    let whole = value.split('.').next().unwrap_or_default();
+    if whole.len() <= 3 {
+        let degrees = value.parse::<f32>().map_err(|_| invalid())?;
+        return Ok(if "SW".contains(hemisphere) { -degrees } else { degrees });
+    }
+    Coordinate::parse_decimal(&format!("{}{}", hemisphere, value))
+}
+
+fn feet(ahp: &Element) -> Option<f32> {
+    let value = ahp.descendant("valElev")?.text.trim().parse::<f32>().ok()?;
+    match ahp.descendant("uomDistVer").map(|u| u.text.trim()) {
+        Some("M") => Some((value * FEET_PER_METRE).round()),
+        _ => Some(value),
+    }
+}
+
+/// The userpoint type for an AIXM 4.5 `codeType`.
+fn userpoint_type(code: &str) -> &'static str {
+    match code {
+        "HP" => "Helipad",
+        "LS" => "Airstrip",
+        _ => "Airport",
+    }
+}
+
+/// Reads the `Ahp` records of an EAD AIXM 4.5 extract. The ICAO location
+/// indicator is the ident where there is one, the `codeId` otherwise; the
+/// region is that of the location indicator, `EP` without one.
+pub fn read_aixm(path: &str) -> Result<Vec<Waypoint>, Box<dyn Error>> {
+    let mut waypoints = vec![];
+    for ahp in Elements::new(input::open(path)?, "Ahp") {
+        let ahp = ahp?;
+        let text = |name: &str| ahp.descendant(name).map(|n| n.text.trim()).filter(|t| !t.is_empty());
+        let ident = text("codeIcao").or_else(|| text("codeId")).unwrap_or_default();
+        let (Some(latitude), Some(longitude)) = (text("geoLat"), text("geoLong")) else {
+            continue;
+        };
+        let latitude = parse_geo(latitude).map_err(|e| e.in_record(ident))?;
+        let longitude = parse_geo(longitude).map_err(|e| e.in_record(ident))?;
+        let waypoint = Waypoint::builder(text("txtName").unwrap_or(ident))
+            .waypoint_type(userpoint_type(text("codeType").unwrap_or("AD")))
+            .ident(ident)
+            .coordinates(latitude, longitude)
+            .elevation(feet(&ahp))
+            .region(text("codeIcao").and_then(region::of_icao).unwrap_or(region::POLAND))
+            .import_filename(path)
+            .build()
+            .map_err(|e| e.in_record(ident))?;
+        waypoints.push(waypoint);
+    }
+    Ok(waypoints)
+}
+
+pub struct EadAixm;
+
+impl InputFormat for EadAixm {
+    fn name(&self) -> &'static str {
+        "ead-aixm"
+    }
+
+    fn description(&self) -> &'static str {
+        "EUROCONTROL EAD extract as AIXM 4.5 Ahp records"
+    }
+
+    fn fields(&self) -> Fields {
+        Fields { elevation: true, ..Fields::default() }
+    }
+
+    fn read(&self, path: &str, _: &ReadOptions) -> Result<Records, Box<dyn Error>> {
+        Ok(Box::new(read_aixm(path)?.into_iter()))
+    }
+}
@@ -0,0 +1,54 @@
+use clap::ValueEnum;
+
+pub const FEET_PER_METRE: f32 = 3.28084;
+
+/// Unit of the elevations written to the output; waypoints carry feet.
+#[derive(Debug, Clone, Copy, Default, PartialEq, ValueEnum)]
+pub enum ElevationUnit {
+    #[default]
+    Ft,
+    M,
+}
+
+impl ElevationUnit {
+    /// `feet` in this unit; metres are rounded to the nearest whole metre.
+    pub fn convert_feet(self, feet: f32) -> f32 {
+        match self {
+            ElevationUnit::Ft => feet,
+            ElevationUnit::M => (feet / FEET_PER_METRE).round(),
+        }
+    }
+
+    /// `name` of an elevation column, labelled when it isn't feet:
+    /// `Elevation (m)`, or `Elev (m)` for `Elev (ft)`.
+    pub fn label(self, name: &str) -> String {
+        match self {
+            ElevationUnit::Ft => name.to_owned(),
+            ElevationUnit::M => {
+                let name = name.trim_end();
+                if ["(m)", "[m]"].iter().any(|unit| strip_unit(name, unit).is_some()) {
+                    return name.to_owned();
+                }
+                let bare = ["(ft)", "[ft]", " ft"].iter().find_map(|unit| strip_unit(name, unit)).unwrap_or(name);
+                format!("{} (m)", bare.trim_end())
+            }
+        }
+    }
+}
+
+/// Parses a source elevation such as `371`, `371 ft` or `113m` into feet.
+/// Values without a unit are taken as feet; converted ones are rounded to
+/// whole feet.
+pub fn parse_feet(s: &str) -> Option<f32> {
+    let s = s.trim();
+    if let Some(metres) = strip_unit(s, "m") {
+        return Some((metres.trim_end().parse::<f32>().ok()? * FEET_PER_METRE).round());
+    }
+    strip_unit(s, "ft").unwrap_or(s).trim_end().parse().ok()
+}
+
+/// `s` without a trailing `unit` in any case.
+fn strip_unit<'a>(s: &'a str, unit: &str) -> Option<&'a str> {
+    let split = s.len().checked_sub(unit.len())?;
+    (s.is_char_boundary(split) && s[split..].eq_ignore_ascii_case(unit)).then(|| &s[..split])
+}
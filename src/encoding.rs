@@ -0,0 +1,62 @@
+use clap::ValueEnum;
+use std::{error::Error, fs, fs::OpenOptions, io::Write};
+use tracing::warn;
+
+/// Character encoding of the generated CSV files.
+#[derive(Debug, Clone, Copy, Default, PartialEq, ValueEnum)]
+pub enum Encoding {
+    #[default]
+    #[value(name = "utf-8")]
+    Utf8,
+    /// UTF-8 with a byte order mark, which Excel needs to detect Polish
+    /// characters correctly.
+    #[value(name = "utf-8-bom")]
+    Utf8Bom,
+    /// The Central European code page expected by older avionics tools.
+    #[value(name = "windows-1250")]
+    Windows1250,
+}
+
+const BOM: &[u8] = b"\xEF\xBB\xBF";
+
+impl Encoding {
+    /// Encodes `text`; characters Windows-1250 cannot represent are replaced
+    /// with a logged warning.
+    pub fn encode(self, text: &str) -> Vec<u8> {
+        match self {
+            Encoding::Utf8 => text.as_bytes().to_vec(),
+            Encoding::Utf8Bom => [BOM, text.as_bytes()].concat(),
+            Encoding::Windows1250 => {
+                let (bytes, _, unmappable) = encoding_rs::WINDOWS_1250.encode(text);
+                if unmappable {
+                    warn!("some characters have no Windows-1250 equivalent and were replaced");
+                }
+                bytes.into_owned()
+            }
+        }
+    }
+
+    /// Decodes a file previously written in this encoding.
+    pub fn decode(self, bytes: &[u8]) -> String {
+        match self {
+            Encoding::Utf8 | Encoding::Utf8Bom => {
+                String::from_utf8_lossy(bytes.strip_prefix(BOM).unwrap_or(bytes)).into_owned()
+            }
+            Encoding::Windows1250 => encoding_rs::WINDOWS_1250.decode_without_bom_handling(bytes).0.into_owned(),
+        }
+    }
+
+    /// Writes `text` to `path`, or appends it without a second byte order mark.
+    pub fn write(self, path: &str, text: &str, append: bool) -> Result<(), Box<dyn Error>> {
+        if !append {
+            fs::write(path, self.encode(text))?;
+            return Ok(());
+        }
+        let bytes = match self {
+            Encoding::Utf8Bom => Encoding::Utf8.encode(text),
+            encoding => encoding.encode(text),
+        };
+        OpenOptions::new().append(true).open(path)?.write_all(&bytes)?;
+        Ok(())
+    }
+}
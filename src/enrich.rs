@@ -0,0 +1,198 @@
+//! Filling in fields from lookups such as declination models, elevation
+//! data or frequency databases. The crate's own is
+//! [`MissingElevations`](crate::dem::MissingElevations); implement
+//! [`Lookup`] for others, or `AsyncLookup` with the `async` feature for
+//! network services, and run it over the waypoints, wanting only the
+//! [`Fields`] the chosen outputs write, e.g. from
+//! [`OutputFormat::fields`](crate::format::OutputFormat::fields).
+
+use crate::format::Fields;
+use crate::Waypoint;
+use rayon::prelude::*;
+use std::error::Error;
+
+pub type LookupError = Box<dyn Error + Send + Sync>;
+
+/// Values found for one waypoint; `None` leaves the field as it was.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct Enrichment {
+    pub magnetic_declination: Option<f32>,
+    /// Feet.
+    pub elevation: Option<f32>,
+    pub frequency: Option<String>,
+}
+
+impl Enrichment {
+    /// Drops values for fields outside `fields`.
+    pub fn only(self, fields: Fields) -> Self {
+        Enrichment {
+            magnetic_declination: self.magnetic_declination.filter(|_| fields.magnetic_declination),
+            elevation: self.elevation.filter(|_| fields.elevation),
+            frequency: self.frequency.filter(|_| fields.description),
+        }
+    }
+
+    /// Frequencies have no userpoint column, so they are tagged onto the
+    /// description.
+    pub fn apply(self, waypoint: &mut Waypoint) {
+        if let Some(declination) = self.magnetic_declination {
+            waypoint.magnetic_declination = Some(declination);
+        }
+        if let Some(elevation) = self.elevation {
+            waypoint.elevation = Some(elevation);
+        }
+        if let Some(frequency) = self.frequency {
+            waypoint.description = Some(match waypoint.description.take() {
+                Some(d) if !d.is_empty() => format!("{}; {} MHz", d, frequency),
+                _ => format!("{} MHz", frequency),
+            });
+        }
+    }
+}
+
+pub trait Lookup: Sync {
+    /// The fields this lookup can fill.
+    fn provides(&self) -> Fields {
+        Fields::ALL
+    }
+
+    /// Looks up `wanted` fields, a non-empty subset of [`provides`](Self::provides).
+    fn lookup(&self, waypoint: &Waypoint, wanted: Fields) -> Result<Enrichment, LookupError>;
+}
+
+/// Applies `lookup` for the `wanted` fields to the waypoints on the rayon
+/// thread pool, and not at all when it provides none of them. Failed lookups
+/// leave the waypoint unchanged and are returned with its index, in order.
+pub fn enrich(waypoints: &mut [Waypoint], lookup: &impl Lookup, wanted: Fields) -> Vec<(usize, LookupError)> {
+    let wanted = lookup.provides().and(wanted);
+    if wanted.is_empty() {
+        return vec![];
+    }
+    waypoints
+        .par_iter_mut()
+        .enumerate()
+        .filter_map(|(i, waypoint)| match lookup.lookup(waypoint, wanted) {
+            Ok(enrichment) => {
+                enrichment.only(wanted).apply(waypoint);
+                None
+            }
+            Err(e) => Some((i, e)),
+        })
+        .collect()
+}
+
+#[cfg(feature = "async")]
+pub use concurrent::{enrich_async, AsyncLookup};
+
+#[cfg(feature = "async")]
+mod concurrent {
+    use super::{Enrichment, LookupError};
+    use crate::format::Fields;
+    use crate::Waypoint;
+    use futures_util::stream::{self, StreamExt};
+    use std::future::Future;
+
+    /// A [`Lookup`](super::Lookup) that waits on I/O instead of blocking a
+    /// thread, e.g. a request to an elevation or declination service.
+    pub trait AsyncLookup: Sync {
+        fn provides(&self) -> Fields {
+            Fields::ALL
+        }
+
+        fn lookup(&self, waypoint: &Waypoint, wanted: Fields) -> impl Future<Output = Result<Enrichment, LookupError>> + Send;
+    }
+
+    /// Runs up to `concurrency` lookups of the `wanted` fields at a time on
+    /// the caller's runtime and applies the results in input order once they
+    /// are all in. Failed lookups are returned with the waypoint's index.
+    pub async fn enrich_async(
+        waypoints: &mut [Waypoint],
+        lookup: &impl AsyncLookup,
+        wanted: Fields,
+        concurrency: usize,
+    ) -> Vec<(usize, LookupError)> {
+        let wanted = lookup.provides().and(wanted);
+        if wanted.is_empty() {
+            return vec![];
+        }
+        let mut results: Vec<_> = stream::iter(waypoints.iter().enumerate())
+            .map(|(i, waypoint)| async move { (i, lookup.lookup(waypoint, wanted).await) })
+            .buffer_unordered(concurrency.max(1))
+            .collect()
+            .await;
+        results.sort_by_key(|(i, _)| *i);
+        let mut failures = vec![];
+        for (i, result) in results {
+            match result {
+                Ok(enrichment) => enrichment.only(wanted).apply(&mut waypoints[i]),
+                Err(e) => failures.push((i, e)),
+            }
+        }
+        failures
+    }
+
+    #[cfg(test)]
+    mod tests {
+        use super::*;
+        use std::sync::atomic::{AtomicUsize, Ordering};
+        use std::time::Duration;
+
+        /// Elevations from the waypoint's position after a delay that makes
+        /// later waypoints finish first; fails for waypoints named `fail`.
+        #[derive(Default)]
+        struct Slow {
+            running: AtomicUsize,
+            most: AtomicUsize,
+        }
+
+        impl AsyncLookup for Slow {
+            fn provides(&self) -> Fields {
+                Fields { elevation: true, ..Fields::NONE }
+            }
+
+            async fn lookup(&self, waypoint: &Waypoint, _: Fields) -> Result<Enrichment, LookupError> {
+                let running = self.running.fetch_add(1, Ordering::SeqCst) + 1;
+                self.most.fetch_max(running, Ordering::SeqCst);
+                tokio::time::sleep(Duration::from_millis(50 - waypoint.latitude as u64)).await;
+                self.running.fetch_sub(1, Ordering::SeqCst);
+                if waypoint.name == "fail" {
+                    return Err("no data".into());
+                }
+                Ok(Enrichment { elevation: Some(waypoint.latitude * 100.), magnetic_declination: Some(5.), ..Default::default() })
+            }
+        }
+
+        fn waypoints() -> Vec<Waypoint> {
+            (0..8)
+                .map(|i| {
+                    let name = if i == 5 { "fail".to_owned() } else { format!("P{}", i) };
+                    Waypoint::builder(&name).coordinates(i as f32, 0.).build().unwrap()
+                })
+                .collect()
+        }
+
+        #[tokio::test]
+        async fn runs_lookups_concurrently_within_the_limit() {
+            let lookup = Slow::default();
+            let mut waypoints = waypoints();
+            let failures = enrich_async(&mut waypoints, &lookup, Fields::ALL, 3).await;
+            assert_eq!(lookup.most.load(Ordering::SeqCst), 3);
+            assert_eq!(failures.iter().map(|(i, _)| *i).collect::<Vec<_>>(), [5]);
+            for (i, waypoint) in waypoints.iter().enumerate().filter(|(i, _)| *i != 5) {
+                assert_eq!(waypoint.elevation, Some(i as f32 * 100.));
+                // Declination isn't provided, so it is left alone.
+                assert_eq!(waypoint.magnetic_declination, None);
+            }
+            assert_eq!(waypoints[5].elevation, None);
+        }
+
+        #[tokio::test]
+        async fn skips_lookups_providing_nothing_wanted() {
+            let lookup = Slow::default();
+            let mut waypoints = waypoints();
+            let wanted = Fields { description: true, ..Fields::NONE };
+            assert!(enrich_async(&mut waypoints, &lookup, wanted, 3).await.is_empty());
+            assert_eq!(lookup.most.load(Ordering::SeqCst), 0);
+        }
+    }
+}
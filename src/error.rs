@@ -0,0 +1,72 @@
+use thiserror::Error;
+
+/// Errors from reading source data, with enough context to find the
+/// offending record.
+#[derive(Debug, Error)]
+pub enum Error {
+    #[error("invalid {field} {value:?}")]
+    InvalidValue { field: &'static str, value: String },
+    #[error("missing {0} attribute")]
+    MissingAttribute(&'static str),
+    #[error("record {record}: {source}")]
+    Record {
+        record: String,
+        #[source]
+        source: Box<Error>,
+    },
+    #[error("{path}: {source}")]
+    File {
+        path: String,
+        #[source]
+        source: std::io::Error,
+    },
+    #[cfg(not(target_arch = "wasm32"))]
+    #[error("{url}: {source}{}", retried(*.attempts))]
+    Download {
+        url: String,
+        #[source]
+        source: Box<ureq::Error>,
+        attempts: u32,
+    },
+    #[error("{0} is not cached and network access is disabled")]
+    Offline(String),
+    #[error("document ends inside <{0}>")]
+    UnexpectedEnd(String),
+    #[error(transparent)]
+    Xml(#[from] quick_xml::Error),
+    #[error(transparent)]
+    Csv(#[from] csv::Error),
+    #[cfg(not(target_arch = "wasm32"))]
+    #[error("spill store: {0}")]
+    Database(#[from] rusqlite::Error),
+    #[error(transparent)]
+    Json(#[from] serde_json::Error),
+}
+
+#[cfg(not(target_arch = "wasm32"))]
+fn retried(attempts: u32) -> String {
+    match attempts {
+        0 | 1 => String::new(),
+        n => format!(" (gave up after {} attempts)", n),
+    }
+}
+
+impl Error {
+    pub fn invalid(field: &'static str, value: &str) -> Self {
+        Error::InvalidValue { field, value: value.to_owned() }
+    }
+
+    /// Attributes the error to `record`, e.g. an ident or airfield name.
+    pub fn in_record(self, record: impl Into<String>) -> Self {
+        Error::Record { record: record.into(), source: Box::new(self) }
+    }
+
+    #[cfg(not(target_arch = "wasm32"))]
+    pub fn download(url: &str, source: ureq::Error) -> Self {
+        Error::Download { url: url.to_owned(), source: Box::new(source), attempts: 1 }
+    }
+
+    pub fn file(path: &str) -> impl FnOnce(std::io::Error) -> Self + '_ {
+        move |source| Error::File { path: path.to_owned(), source }
+    }
+}
@@ -0,0 +1,139 @@
+//! C ABI for calling the converter from other languages; the declarations
+//! are in `include/polish_airports.h`. Functions return 0 on success and -1
+//! on failure. Strings handed out are owned by this library and must be
+//! released with [`polish_airports_free_string`].
+
+#[cfg(not(target_arch = "wasm32"))]
+use crate::{pipeline::Conversion, summary::Summary};
+use crate::{AirfieldReader, Coordinate, Position, Waypoint};
+use std::ffi::{c_char, c_int, CStr, CString};
+use std::slice;
+use std::str::FromStr;
+
+fn to_c_string(text: String) -> *mut c_char {
+    // Interior NULs cannot cross the boundary, so they are dropped.
+    CString::new(text.replace('\0', "")).unwrap_or_default().into_raw()
+}
+
+fn airfields_csv(xml: &[u8], delimiter: u8) -> Result<String, Box<dyn std::error::Error>> {
+    let waypoints = AirfieldReader::new(xml).collect::<Result<Vec<Waypoint>, _>>()?;
+    let mut writer = csv::WriterBuilder::new().delimiter(delimiter).from_writer(vec![]);
+    for waypoint in waypoints {
+        writer.serialize(waypoint)?;
+    }
+    Ok(String::from_utf8(writer.into_inner()?)?)
+}
+
+/// Converts the SkyDemon XML in `buf` to userpoints CSV with a header row.
+/// On success `*out` receives the CSV, otherwise the error message.
+///
+/// # Safety
+///
+/// `buf` must point to `len` readable bytes and `out` must be valid for a
+/// pointer write.
+#[no_mangle]
+pub unsafe extern "C" fn polish_airports_convert_skydemon_to_csv(
+    buf: *const u8,
+    len: usize,
+    delimiter: c_char,
+    out: *mut *mut c_char,
+) -> c_int {
+    if buf.is_null() || out.is_null() {
+        return -1;
+    }
+    let xml = slice::from_raw_parts(buf, len);
+    let (status, text) = match airfields_csv(xml, delimiter as u8) {
+        Ok(csv) => (0, csv),
+        Err(e) => (-1, e.to_string()),
+    };
+    *out = to_c_string(text);
+    status
+}
+
+/// Converts the SkyDemon XML file at `input` into the userpoints CSV file
+/// `output`, as the command line does without options; see
+/// [`crate::pipeline::Conversion`]. On failure `*error` receives the message,
+/// on success it is left untouched.
+///
+/// # Safety
+///
+/// `input` and `output` must be NUL-terminated strings and `error` must be
+/// valid for a pointer write.
+#[cfg(not(target_arch = "wasm32"))]
+#[no_mangle]
+pub unsafe extern "C" fn polish_airports_convert_file(
+    input: *const c_char,
+    output: *const c_char,
+    error: *mut *mut c_char,
+) -> c_int {
+    if input.is_null() || output.is_null() || error.is_null() {
+        return -1;
+    }
+    let paths = (CStr::from_ptr(input).to_str(), CStr::from_ptr(output).to_str());
+    let result = match paths {
+        (Ok(input), Ok(output)) => Conversion::new(input, output).run(&mut Summary::default()),
+        _ => Err("paths must be UTF-8".into()),
+    };
+    match result {
+        Ok(()) => 0,
+        Err(e) => {
+            *error = to_c_string(e.to_string());
+            -1
+        }
+    }
+}
+
+/// Parses one coordinate, in decimal degrees or the `N520101.50` form, into
+/// `*degrees`.
+///
+/// # Safety
+///
+/// `text` must be a NUL-terminated string and `degrees` valid for a write.
+#[no_mangle]
+pub unsafe extern "C" fn polish_airports_parse_coordinate(text: *const c_char, degrees: *mut f32) -> c_int {
+    if text.is_null() || degrees.is_null() {
+        return -1;
+    }
+    match CStr::from_ptr(text).to_str().map(Coordinate::parse_decimal) {
+        Ok(Ok(value)) => {
+            *degrees = value;
+            0
+        }
+        _ => -1,
+    }
+}
+
+/// Parses a SkyDemon position such as `N530956.01 E0174449.00` into decimal
+/// degrees.
+///
+/// # Safety
+///
+/// `text` must be a NUL-terminated string, and `latitude` and `longitude`
+/// valid for writes.
+#[no_mangle]
+pub unsafe extern "C" fn polish_airports_parse_position(text: *const c_char, latitude: *mut f32, longitude: *mut f32) -> c_int {
+    if text.is_null() || latitude.is_null() || longitude.is_null() {
+        return -1;
+    }
+    let position = CStr::from_ptr(text).to_str().ok().and_then(|s| Position::from_str(s).ok());
+    match position {
+        Some(position) => {
+            *latitude = position.lat.to_decimal_degrees();
+            *longitude = position.lon.to_decimal_degrees();
+            0
+        }
+        None => -1,
+    }
+}
+
+/// Releases a string returned by this library. Null is ignored.
+///
+/// # Safety
+///
+/// `text` must come from this library and not have been freed already.
+#[no_mangle]
+pub unsafe extern "C" fn polish_airports_free_string(text: *mut c_char) {
+    if !text.is_null() {
+        drop(CString::from_raw(text));
+    }
+}
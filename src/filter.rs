@@ -0,0 +1,410 @@
+use crate::Waypoint;
+use regex::{Regex, RegexBuilder};
+use std::str::FromStr;
+
+/// A parsed `--filter` expression, e.g.
+/// `elevation > 500 && type == 'Airstrip' && name =~ 'ląd'`.
+///
+/// Comparisons are `==`, `!=`, `<`, `<=`, `>`, `>=` and `=~` (case-insensitive
+/// regular expression), combined with `&&`, `||`, `!` and parentheses.
+/// A comparison against a field the record doesn't have is false. Parse
+/// errors give the byte offset they were found at.
+#[derive(Debug, Clone)]
+pub enum Filter {
+    And(Box<Filter>, Box<Filter>),
+    Or(Box<Filter>, Box<Filter>),
+    Not(Box<Filter>),
+    Compare(Field, Op, Value),
+    Matches(Field, Regex),
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Field {
+    Type,
+    Name,
+    Ident,
+    Latitude,
+    Longitude,
+    Elevation,
+    Tags,
+    Description,
+    Region,
+    /// The file the record was imported from.
+    Source,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Op {
+    Eq,
+    Ne,
+    Lt,
+    Le,
+    Gt,
+    Ge,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum Value {
+    Number(f64),
+    Text(String),
+}
+
+#[derive(Debug, Clone, PartialEq)]
+enum Token {
+    Ident(String),
+    Number(f64),
+    Text(String),
+    Op(&'static str),
+    Open,
+    Close,
+}
+
+/// The tokens of `s` with the byte offset each starts at.
+fn tokenize(s: &str) -> Result<Vec<(usize, Token)>, String> {
+    let mut tokens = vec![];
+    let mut chars = s.char_indices().peekable();
+    while let Some(&(i, c)) = chars.peek() {
+        if c.is_whitespace() {
+            chars.next();
+        } else if c == '(' || c == ')' {
+            tokens.push((i, if c == '(' { Token::Open } else { Token::Close }));
+            chars.next();
+        } else if c == '\'' || c == '"' {
+            chars.next();
+            let mut text = String::new();
+            loop {
+                match chars.next() {
+                    Some((_, q)) if q == c => break,
+                    Some((_, ch)) => text.push(ch),
+                    None => return Err(format!("unterminated string starting at {}", i)),
+                }
+            }
+            tokens.push((i, Token::Text(text)));
+        } else if c.is_ascii_digit() || c == '-' || c == '.' {
+            let mut number = String::new();
+            while let Some(&(_, d)) = chars.peek() {
+                if d.is_ascii_digit() || d == '.' || (number.is_empty() && d == '-') {
+                    number.push(d);
+                    chars.next();
+                } else {
+                    break;
+                }
+            }
+            let value = number.parse().map_err(|_| format!("invalid number {:?} at {}", number, i))?;
+            tokens.push((i, Token::Number(value)));
+        } else if c.is_alphabetic() || c == '_' {
+            let mut ident = String::new();
+            while let Some(&(_, d)) = chars.peek() {
+                if d.is_alphanumeric() || d == '_' {
+                    ident.push(d);
+                    chars.next();
+                } else {
+                    break;
+                }
+            }
+            tokens.push((i, Token::Ident(ident)));
+        } else {
+            let rest = &s[i..];
+            let op = ["&&", "||", "==", "!=", "<=", ">=", "=~", "<", ">", "!"]
+                .into_iter()
+                .find(|op| rest.starts_with(op))
+                .ok_or_else(|| format!("unexpected {:?} at {}", c, i))?;
+            for _ in 0..op.len() {
+                chars.next();
+            }
+            tokens.push((i, Token::Op(op)));
+        }
+    }
+    Ok(tokens)
+}
+
+impl FromStr for Field {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        Ok(match s {
+            "type" => Field::Type,
+            "name" => Field::Name,
+            "ident" => Field::Ident,
+            "lat" | "latitude" => Field::Latitude,
+            "lon" | "longitude" => Field::Longitude,
+            "elev" | "elevation" => Field::Elevation,
+            "tags" => Field::Tags,
+            "description" => Field::Description,
+            "region" => Field::Region,
+            "source" => Field::Source,
+            _ => return Err(format!("unknown field {:?}", s)),
+        })
+    }
+}
+
+struct Parser {
+    tokens: Vec<(usize, Token)>,
+    pos: usize,
+    /// Length of the expression, where errors at its end are reported.
+    end: usize,
+}
+
+impl Parser {
+    fn peek(&self) -> Option<&Token> {
+        self.tokens.get(self.pos).map(|(_, token)| token)
+    }
+
+    /// Byte offset of the next token.
+    fn at(&self) -> usize {
+        self.tokens.get(self.pos).map_or(self.end, |(at, _)| *at)
+    }
+
+    fn next(&mut self) -> Option<Token> {
+        let token = self.peek().cloned();
+        self.pos += 1;
+        token
+    }
+
+    fn or(&mut self) -> Result<Filter, String> {
+        let mut left = self.and()?;
+        while self.peek() == Some(&Token::Op("||")) {
+            self.next();
+            left = Filter::Or(Box::new(left), Box::new(self.and()?));
+        }
+        Ok(left)
+    }
+
+    fn and(&mut self) -> Result<Filter, String> {
+        let mut left = self.unary()?;
+        while self.peek() == Some(&Token::Op("&&")) {
+            self.next();
+            left = Filter::And(Box::new(left), Box::new(self.unary()?));
+        }
+        Ok(left)
+    }
+
+    fn unary(&mut self) -> Result<Filter, String> {
+        let at = self.at();
+        match self.next() {
+            Some(Token::Op("!")) => Ok(Filter::Not(Box::new(self.unary()?))),
+            Some(Token::Open) => {
+                let inner = self.or()?;
+                let at = self.at();
+                match self.next() {
+                    Some(Token::Close) => Ok(inner),
+                    _ => Err(format!("missing ) at {}", at)),
+                }
+            }
+            Some(Token::Ident(name)) => self.comparison(name.parse().map_err(|e| format!("{} at {}", e, at))?),
+            other => Err(format!("expected a field name at {}, got {:?}", at, other)),
+        }
+    }
+
+    fn comparison(&mut self, field: Field) -> Result<Filter, String> {
+        let at = self.at();
+        let op = match self.next() {
+            Some(Token::Op(op)) => op,
+            other => return Err(format!("expected an operator after {:?} at {}, got {:?}", field, at, other)),
+        };
+        let value_at = self.at();
+        let value = match self.next() {
+            Some(Token::Number(n)) => Value::Number(n),
+            Some(Token::Text(t)) => Value::Text(t),
+            other => return Err(format!("expected a number or string at {}, got {:?}", value_at, other)),
+        };
+        let op = match op {
+            "=~" => {
+                let Value::Text(pattern) = value else {
+                    return Err(format!("=~ needs a string pattern at {}", value_at));
+                };
+                let regex = RegexBuilder::new(&pattern)
+                    .case_insensitive(true)
+                    .build()
+                    .map_err(|e| e.to_string())?;
+                return Ok(Filter::Matches(field, regex));
+            }
+            "==" => Op::Eq,
+            "!=" => Op::Ne,
+            "<" => Op::Lt,
+            "<=" => Op::Le,
+            ">" => Op::Gt,
+            ">=" => Op::Ge,
+            op => return Err(format!("{} at {} is not a comparison", op, at)),
+        };
+        Ok(Filter::Compare(field, op, value))
+    }
+}
+
+impl FromStr for Filter {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let mut parser = Parser {
+            tokens: tokenize(s)?,
+            pos: 0,
+            end: s.len(),
+        };
+        let filter = parser.or()?;
+        match parser.peek() {
+            None => Ok(filter),
+            Some(token) => Err(format!("unexpected {:?} at {} after expression", token, parser.at())),
+        }
+    }
+}
+
+fn field_value(waypoint: &Waypoint, field: Field) -> Option<Value> {
+    let text = |s: &Option<String>| s.clone().map(Value::Text);
+    match field {
+        Field::Type => Some(Value::Text(waypoint.waypoint_type.clone())),
+        Field::Name => Some(Value::Text(waypoint.name.clone())),
+        Field::Ident => Some(Value::Text(waypoint.ident.clone())),
+        Field::Latitude => Some(Value::Number(waypoint.latitude as f64)),
+        Field::Longitude => Some(Value::Number(waypoint.longitude as f64)),
+        Field::Elevation => waypoint.elevation.map(|e| Value::Number(e as f64)),
+        Field::Tags => text(&waypoint.tags),
+        Field::Description => text(&waypoint.description),
+        Field::Region => text(&waypoint.region),
+        Field::Source => text(&waypoint.import_filename),
+    }
+}
+
+impl Filter {
+    pub fn matches(&self, waypoint: &Waypoint) -> bool {
+        match self {
+            Filter::And(a, b) => a.matches(waypoint) && b.matches(waypoint),
+            Filter::Or(a, b) => a.matches(waypoint) || b.matches(waypoint),
+            Filter::Not(f) => !f.matches(waypoint),
+            Filter::Matches(field, regex) => match field_value(waypoint, *field) {
+                Some(Value::Text(t)) => regex.is_match(&t),
+                Some(Value::Number(n)) => regex.is_match(&n.to_string()),
+                None => false,
+            },
+            Filter::Compare(field, op, value) => {
+                let Some(actual) = field_value(waypoint, *field) else {
+                    return false;
+                };
+                let ordering = match (&actual, value) {
+                    (Value::Number(a), Value::Number(b)) => a.partial_cmp(b),
+                    (Value::Text(a), Value::Text(b)) => Some(a.to_lowercase().cmp(&b.to_lowercase())),
+                    _ => None,
+                };
+                let Some(ordering) = ordering else {
+                    return false;
+                };
+                match op {
+                    Op::Eq => ordering.is_eq(),
+                    Op::Ne => ordering.is_ne(),
+                    Op::Lt => ordering.is_lt(),
+                    Op::Le => ordering.is_le(),
+                    Op::Gt => ordering.is_gt(),
+                    Op::Ge => ordering.is_ge(),
+                }
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn zamosc() -> Waypoint {
+        let mut waypoint = Waypoint::new("Airstrip", "Lądowisko Zamość", "EPZA", 50.70, 23.20, Some(750.));
+        waypoint.tags = Some("glider,night".to_owned());
+        waypoint
+    }
+
+    fn matches(expression: &str, waypoint: &Waypoint) -> bool {
+        expression.parse::<Filter>().unwrap().matches(waypoint)
+    }
+
+    fn error(expression: &str) -> String {
+        expression.parse::<Filter>().unwrap_err()
+    }
+
+    #[test]
+    fn and_binds_tighter_than_or() {
+        let waypoint = zamosc();
+        assert!(matches("ident == 'EPZA' || elevation > 1000 && type == 'Helipad'", &waypoint));
+        assert!(!matches("ident == 'XXXX' || elevation > 1000 && type == 'Airstrip'", &waypoint));
+        assert!(matches("elevation > 1000 && type == 'Helipad' || ident == 'EPZA'", &waypoint));
+    }
+
+    #[test]
+    fn not_applies_to_the_next_comparison_only() {
+        let waypoint = zamosc();
+        assert!(!matches("!type == 'Airstrip' && elevation > 500", &waypoint));
+        assert!(matches("!type == 'Helipad' && elevation > 500", &waypoint));
+        assert!(matches("!!type == 'Airstrip'", &waypoint));
+    }
+
+    #[test]
+    fn parentheses_group() {
+        let waypoint = zamosc();
+        assert!(!matches("(ident == 'EPZA' || elevation > 1000) && type == 'Helipad'", &waypoint));
+        assert!(matches("!(type == 'Helipad' || elevation < 500)", &waypoint));
+        assert!(matches("((elevation >= 750))", &waypoint));
+    }
+
+    #[test]
+    fn numbers_compare_numerically() {
+        let waypoint = zamosc();
+        assert!(matches("elevation == 750", &waypoint));
+        assert!(matches("elev <= 750.0 && elev >= 750", &waypoint));
+        assert!(matches("lat > 50.5 && lon < 23.5 && lon > -180", &waypoint));
+        assert!(!matches("elevation != 750", &waypoint));
+    }
+
+    #[test]
+    fn text_compares_without_case() {
+        let waypoint = zamosc();
+        assert!(matches("type == 'airstrip'", &waypoint));
+        assert!(matches(r#"ident == "epza""#, &waypoint));
+        assert!(matches("ident < 'EPZB' && ident > 'EPAA'", &waypoint));
+        assert!(matches("name =~ 'ZAMOŚĆ$'", &waypoint));
+        assert!(matches("tags =~ '(^|,)night(,|$)'", &waypoint));
+        assert!(!matches("name =~ '^zamość'", &waypoint));
+    }
+
+    #[test]
+    fn text_and_numbers_never_compare() {
+        let waypoint = zamosc();
+        assert!(!matches("elevation == '750'", &waypoint));
+        assert!(!matches("elevation != '750'", &waypoint));
+        assert!(!matches("ident == 750", &waypoint));
+        assert!(matches("elevation =~ '^750$'", &waypoint));
+    }
+
+    #[test]
+    fn missing_fields_are_false() {
+        let mut waypoint = zamosc();
+        waypoint.elevation = None;
+        waypoint.description = None;
+        assert!(!matches("elevation > 0", &waypoint));
+        assert!(!matches("elevation <= 0", &waypoint));
+        assert!(!matches("description =~ ''", &waypoint));
+        assert!(!matches("description != 'x'", &waypoint));
+        assert!(matches("!(elevation > 0)", &waypoint));
+    }
+
+    #[test]
+    fn tokenizer_errors_give_their_position() {
+        assert_eq!(error("name == 'Zamość"), "unterminated string starting at 8");
+        assert_eq!(error("elevation > 1.2.3"), r#"invalid number "1.2.3" at 12"#);
+        assert_eq!(error("elevation = 5"), "unexpected '=' at 10");
+        assert_eq!(error("type == 'Helipad' & elevation > 5"), "unexpected '&' at 18");
+    }
+
+    #[test]
+    fn parser_errors_give_their_position() {
+        assert_eq!(error("height > 5"), r#"unknown field "height" at 0"#);
+        assert_eq!(error("elevation > 5 && "), "expected a field name at 17, got None");
+        assert_eq!(error("(elevation > 5"), "missing ) at 14");
+        assert_eq!(error("elevation 5"), "expected an operator after Elevation at 10, got Some(Number(5.0))");
+        assert_eq!(error("elevation > type"), r#"expected a number or string at 12, got Some(Ident("type"))"#);
+        assert_eq!(error("elevation =~ 5"), "=~ needs a string pattern at 13");
+        assert_eq!(error("elevation && 5"), "&& at 10 is not a comparison");
+        assert_eq!(error("elevation > 5)"), "unexpected Close at 13 after expression");
+    }
+
+    #[test]
+    fn invalid_patterns_are_errors() {
+        assert!(error("name =~ '('").contains("regex parse error"));
+    }
+}
@@ -0,0 +1,358 @@
+//! Registry of the input and output formats. Each format lives in its own
+//! module and is listed here; the CLI builds its choices from these lists.
+
+use crate::columns::Columns;
+use crate::line_ending::LineEnding;
+use crate::truncate::shorten_unique;
+use crate::{aip, aip_vfr, cup, dafif, ead, fpl, geojson, gpx, hems, kml, nasr, navaid, obstacle, osm, reporting, ulc, verify, Waypoint};
+use std::borrow::Cow;
+use std::error::Error;
+use std::fmt::Write;
+use std::num::NonZeroUsize;
+
+pub type Records = Box<dyn Iterator<Item = Waypoint>>;
+
+/// Settings shared by the readers; each uses only what applies to it.
+#[derive(Debug, Clone)]
+pub struct ReadOptions {
+    pub delimiter: u8,
+    /// Idents already in use, which generated idents must avoid.
+    pub taken: Vec<String>,
+}
+
+impl Default for ReadOptions {
+    fn default() -> Self {
+        ReadOptions { delimiter: b',', taken: vec![] }
+    }
+}
+
+/// Settings shared by the writers; each uses only what applies to it.
+#[derive(Debug, Clone)]
+pub struct WriteOptions {
+    pub delimiter: u8,
+    pub line_ending: LineEnding,
+    /// Route or document title.
+    pub title: String,
+    /// Check the document against its format's schema before writing, for
+    /// formats that have one.
+    pub validate: bool,
+    /// Lengths to shorten idents and names to instead of the format's own
+    /// [`OutputFormat::limits`].
+    pub limits: Limits,
+}
+
+impl Default for WriteOptions {
+    fn default() -> Self {
+        WriteOptions {
+            delimiter: b',',
+            line_ending: LineEnding::default(),
+            title: String::new(),
+            validate: false,
+            limits: Limits::default(),
+        }
+    }
+}
+
+/// Longest idents and names a format's targets accept, in characters.
+/// Longer ones are shortened without two records ending up the same.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct Limits {
+    pub ident: Option<NonZeroUsize>,
+    pub name: Option<NonZeroUsize>,
+}
+
+impl Limits {
+    pub const fn new(ident: usize, name: usize) -> Self {
+        Limits { ident: NonZeroUsize::new(ident), name: NonZeroUsize::new(name) }
+    }
+
+    /// These limits, falling back to `other` for those not set.
+    pub fn or(self, other: Limits) -> Limits {
+        Limits { ident: self.ident.or(other.ident), name: self.name.or(other.name) }
+    }
+
+    /// The waypoints with idents and names shortened to the limits, borrowed
+    /// when none is too long.
+    pub fn apply<'a>(&self, waypoints: &'a [Waypoint]) -> Cow<'a, [Waypoint]> {
+        let fits = |value: &str, max: Option<NonZeroUsize>| max.is_none_or(|max| value.chars().count() <= max.get());
+        if waypoints.iter().all(|w| fits(&w.ident, self.ident) && fits(&w.name, self.name)) {
+            return Cow::Borrowed(waypoints);
+        }
+        let mut shortened = waypoints.to_vec();
+        if let Some(max) = self.ident {
+            let idents: Vec<String> = waypoints.iter().map(|w| w.ident.clone()).collect();
+            for (waypoint, ident) in shortened.iter_mut().zip(shorten_unique(&idents, max.get())) {
+                waypoint.ident = ident;
+            }
+        }
+        if let Some(max) = self.name {
+            let names: Vec<String> = waypoints.iter().map(|w| w.name.clone()).collect();
+            for (waypoint, name) in shortened.iter_mut().zip(shorten_unique(&names, max.get())) {
+                waypoint.name = name;
+            }
+        }
+        Cow::Owned(shortened)
+    }
+}
+
+/// What a format keeps of each record besides its name, ident and position,
+/// and so which fields enrichment is worth running for.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct Fields {
+    pub magnetic_declination: bool,
+    pub elevation: bool,
+    /// Radio frequencies and other notes, which records carry in their
+    /// description.
+    pub description: bool,
+    /// The runway in use and other flags.
+    pub tags: bool,
+}
+
+impl Fields {
+    pub const ALL: Fields = Fields { magnetic_declination: true, elevation: true, description: true, tags: true };
+    pub const NONE: Fields = Fields { magnetic_declination: false, elevation: false, description: false, tags: false };
+
+    /// The fields that show up in `columns`.
+    pub fn used_by(columns: &Columns) -> Self {
+        let has = |name| columns.0.contains(&name);
+        Fields {
+            magnetic_declination: has("Magnetic Declination"),
+            elevation: has("Elevation"),
+            description: has("Description"),
+            tags: has("Tags"),
+        }
+    }
+
+    /// Fields in either set, e.g. over all requested outputs.
+    pub fn or(self, other: Fields) -> Self {
+        Fields {
+            magnetic_declination: self.magnetic_declination || other.magnetic_declination,
+            elevation: self.elevation || other.elevation,
+            description: self.description || other.description,
+            tags: self.tags || other.tags,
+        }
+    }
+
+    pub fn and(self, other: Fields) -> Self {
+        Fields {
+            magnetic_declination: self.magnetic_declination && other.magnetic_declination,
+            elevation: self.elevation && other.elevation,
+            description: self.description && other.description,
+            tags: self.tags && other.tags,
+        }
+    }
+
+    pub fn is_empty(self) -> bool {
+        self == Fields::NONE
+    }
+}
+
+pub trait InputFormat: Sync {
+    fn name(&self) -> &'static str;
+    fn description(&self) -> &'static str;
+    /// What the reader takes from the source.
+    fn fields(&self) -> Fields;
+    fn read(&self, path: &str, options: &ReadOptions) -> Result<Records, Box<dyn Error>>;
+}
+
+pub trait OutputFormat: Sync {
+    fn name(&self) -> &'static str;
+    fn description(&self) -> &'static str;
+    fn extension(&self) -> &'static str;
+
+    /// The waypoint fields the format writes, so enrichment can skip the rest.
+    fn columns(&self) -> Columns {
+        Columns::default()
+    }
+
+    /// Ident and name lengths the format's targets cut off at; none by
+    /// default.
+    fn limits(&self) -> Limits {
+        Limits::default()
+    }
+
+    /// The waypoints shortened to the limits the options or, failing that,
+    /// the format set. Each writer renders these.
+    fn shorten<'a>(&self, waypoints: &'a [Waypoint], options: &WriteOptions) -> Cow<'a, [Waypoint]> {
+        options.limits.or(self.limits()).apply(waypoints)
+    }
+
+    /// What the writer keeps, going by its columns.
+    fn fields(&self) -> Fields {
+        Fields::used_by(&self.columns())
+    }
+
+    /// The whole document in memory, for callers without a filesystem.
+    fn render(&self, waypoints: &[Waypoint], options: &WriteOptions) -> Result<Vec<u8>, Box<dyn Error>>;
+
+    fn write(&self, path: &str, waypoints: &[Waypoint], options: &WriteOptions) -> Result<(), Box<dyn Error>> {
+        std::fs::write(path, self.render(waypoints, options)?)?;
+        Ok(())
+    }
+}
+
+pub struct Skydemon;
+
+impl InputFormat for Skydemon {
+    fn name(&self) -> &'static str {
+        "skydemon"
+    }
+
+    fn description(&self) -> &'static str {
+        "SkyDemon airfields XML"
+    }
+
+    fn fields(&self) -> Fields {
+        Fields { elevation: true, ..Fields::default() }
+    }
+
+    fn read(&self, path: &str, _: &ReadOptions) -> Result<Records, Box<dyn Error>> {
+        Ok(Box::new(crate::read_airfields(path)?.into_iter()))
+    }
+}
+
+pub struct Userpoints;
+
+impl InputFormat for Userpoints {
+    fn name(&self) -> &'static str {
+        "userpoints-csv"
+    }
+
+    fn description(&self) -> &'static str {
+        "Little Navmap userpoints CSV"
+    }
+
+    fn fields(&self) -> Fields {
+        Fields::ALL
+    }
+
+    fn read(&self, path: &str, options: &ReadOptions) -> Result<Records, Box<dyn Error>> {
+        Ok(Box::new(crate::read_dataset(path, options.delimiter)?.into_iter()))
+    }
+}
+
+impl OutputFormat for Userpoints {
+    fn name(&self) -> &'static str {
+        "userpoints-csv"
+    }
+
+    fn description(&self) -> &'static str {
+        "Little Navmap userpoints CSV"
+    }
+
+    fn extension(&self) -> &'static str {
+        "csv"
+    }
+
+    fn render(&self, waypoints: &[Waypoint], options: &WriteOptions) -> Result<Vec<u8>, Box<dyn Error>> {
+        let mut writer = csv::WriterBuilder::new()
+            .delimiter(options.delimiter)
+            .terminator(options.line_ending.terminator())
+            .from_writer(vec![]);
+        for waypoint in self.shorten(waypoints, options).iter() {
+            writer.serialize(waypoint)?;
+        }
+        Ok(writer.into_inner()?)
+    }
+}
+
+pub struct Json;
+
+impl InputFormat for Json {
+    fn name(&self) -> &'static str {
+        "json"
+    }
+
+    fn description(&self) -> &'static str {
+        "JSON array of userpoint records"
+    }
+
+    fn fields(&self) -> Fields {
+        Fields::ALL
+    }
+
+    fn read(&self, path: &str, _: &ReadOptions) -> Result<Records, Box<dyn Error>> {
+        let waypoints: Vec<Waypoint> = serde_json::from_slice(&crate::input::open(path)?.get_ref()[..])?;
+        Ok(Box::new(waypoints.into_iter()))
+    }
+}
+
+impl OutputFormat for Json {
+    fn name(&self) -> &'static str {
+        "json"
+    }
+
+    fn description(&self) -> &'static str {
+        "JSON array of userpoint records"
+    }
+
+    fn extension(&self) -> &'static str {
+        "json"
+    }
+
+    fn render(&self, waypoints: &[Waypoint], options: &WriteOptions) -> Result<Vec<u8>, Box<dyn Error>> {
+        let columns = Columns::default();
+        let records: Vec<_> = self.shorten(waypoints, options).iter().map(|w| columns.select(w)).collect();
+        let text = serde_json::to_string_pretty(&records)?;
+        Ok(options.line_ending.apply(&text).into_owned().into_bytes())
+    }
+}
+
+static INPUTS: [&dyn InputFormat; 17] = [
+    &Skydemon,
+    &aip::AipAixm,
+    &aip_vfr::AipVfr,
+    &ead::EadAixm,
+    &ulc::UlcRegistry,
+    &hems::HemsSites,
+    &obstacle::EtodCsv,
+    &obstacle::EtodAixm,
+    &navaid::NavaidCsv,
+    &reporting::ReportingCsv,
+    &Userpoints,
+    &Json,
+    &cup::Cup,
+    &verify::OurAirports,
+    &nasr::NasrApt,
+    &dafif::DafifArpt,
+    &osm::OsmOverpass,
+];
+
+static OUTPUTS: [&dyn OutputFormat; 7] =
+    [&Userpoints, &Json, &geojson::GeoJson, &kml::Kml, &gpx::Gpx, &fpl::Fpl, &cup::Cup];
+
+pub fn inputs() -> &'static [&'static dyn InputFormat] {
+    &INPUTS
+}
+
+pub fn outputs() -> &'static [&'static dyn OutputFormat] {
+    &OUTPUTS
+}
+
+pub fn input(name: &str) -> Option<&'static dyn InputFormat> {
+    INPUTS.iter().copied().find(|f| f.name() == name)
+}
+
+pub fn output(name: &str) -> Option<&'static dyn OutputFormat> {
+    OUTPUTS.iter().copied().find(|f| f.name() == name)
+}
+
+/// A table of every input and output format and which of the [`Fields`]
+/// each keeps, for `list-formats`.
+pub fn capabilities() -> String {
+    let mark = |kept: bool| if kept { "yes" } else { "-" };
+    let width = INPUTS.iter().map(|f| f.name().len()).chain(OUTPUTS.iter().map(|f| f.name().len())).max().unwrap_or(0);
+    let mut text = String::new();
+    let mut table = |title: &str, rows: Vec<(&str, &str, Fields)>| {
+        writeln!(text, "{:<width$}  elevation  description  tags", title, width = width).unwrap();
+        for (name, description, fields) in rows {
+            let row = format!("{:<9}  {:<11}  {:<4}", mark(fields.elevation), mark(fields.description), mark(fields.tags));
+            writeln!(text, "{:<width$}  {}  {}", name, row, description, width = width).unwrap();
+        }
+        text.push('\n');
+    };
+    table("Input", INPUTS.iter().map(|f| (f.name(), f.description(), f.fields())).collect());
+    table("Output", OUTPUTS.iter().map(|f| (f.name(), f.description(), f.fields())).collect());
+    text.push_str("Frequencies and other notes are kept in the description, the runway in use in the tags.\n");
+    text
+}
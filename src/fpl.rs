@@ -0,0 +1,148 @@
+use crate::columns::Columns;
+use crate::format::{Limits, OutputFormat, WriteOptions};
+use crate::line_ending::LineEnding;
+use crate::matching::to_ascii;
+use crate::truncate::{shorten_unique, truncate};
+use crate::xml::escape;
+use crate::Waypoint;
+use std::collections::{HashMap, HashSet};
+use std::error::Error;
+use std::io::{self, Write};
+
+const WAYPOINT_TYPE: &str = "USER WAYPOINT";
+const IDENTIFIER_LENGTH: usize = 6;
+const COMMENT_LENGTH: usize = 25;
+
+/// Garmin user waypoint identifiers are limited to six upper-case
+/// alphanumerics; longer idents are shortened without colliding.
+pub fn identifiers(waypoints: &[Waypoint]) -> Vec<String> {
+    identifiers_within(waypoints, IDENTIFIER_LENGTH)
+}
+
+/// Identifiers of at most `max` characters. Idents are sanitized before they
+/// are shortened, and two points whose idents sanitize alike, such as `EP-AB`
+/// and `EPAB`, get numbered apart instead of the second being left out of
+/// the waypoint table. A point visited twice keeps one identifier.
+fn identifiers_within(waypoints: &[Waypoint], max: usize) -> Vec<String> {
+    let sanitized: Vec<String> = waypoints
+        .iter()
+        .map(|w| {
+            to_ascii(&w.ident)
+                .chars()
+                .filter(char::is_ascii_alphanumeric)
+                .map(|c| c.to_ascii_uppercase())
+                .collect()
+        })
+        .collect();
+    let mut ids = shorten_unique(&sanitized, max);
+    let mut taken: HashSet<String> = ids.iter().cloned().collect();
+    let mut used: HashSet<String> = HashSet::new();
+    let mut points: HashMap<(&str, u32, u32), String> = HashMap::new();
+    for (i, waypoint) in waypoints.iter().enumerate() {
+        let point = (waypoint.ident.as_str(), waypoint.latitude.to_bits(), waypoint.longitude.to_bits());
+        if let Some(id) = points.get(&point) {
+            ids[i] = id.clone();
+            continue;
+        }
+        let mut n = 2;
+        while used.contains(&ids[i]) {
+            let suffix = n.to_string();
+            let candidate = format!("{}{}", truncate(&sanitized[i], max.saturating_sub(suffix.len())), suffix);
+            if !taken.contains(&candidate) {
+                taken.insert(candidate.clone());
+                ids[i] = candidate;
+            }
+            n += 1;
+        }
+        used.insert(ids[i].clone());
+        points.insert(point, ids[i].clone());
+    }
+    ids
+}
+
+/// Writes a Garmin FlightPlan v1 document visiting the waypoints in order.
+pub fn write_plan(path: &str, name: &str, waypoints: &[Waypoint], ending: LineEnding) -> io::Result<()> {
+    let mut out = ending.create(path)?;
+    plan(&mut out, name, waypoints, IDENTIFIER_LENGTH)?;
+    out.flush()
+}
+
+fn plan(out: &mut impl Write, name: &str, waypoints: &[Waypoint], ident_length: usize) -> io::Result<()> {
+    writeln!(out, r#"<?xml version="1.0" encoding="utf-8"?>"#)?;
+    writeln!(
+        out,
+        r#"<flight-plan xmlns="http://www8.garmin.com/xmlschemas/FlightPlan/v1">"#
+    )?;
+    writeln!(out, "<waypoint-table>")?;
+    let ids = identifiers_within(waypoints, ident_length);
+    let mut written: Vec<&String> = vec![];
+    for (waypoint, id) in waypoints.iter().zip(&ids) {
+        if written.contains(&id) {
+            continue;
+        }
+        writeln!(out, "<waypoint>")?;
+        writeln!(out, "<identifier>{}</identifier>", escape(id))?;
+        writeln!(out, "<type>{}</type>", WAYPOINT_TYPE)?;
+        writeln!(out, "<country-code>{}</country-code>", country_code(waypoint))?;
+        writeln!(out, "<lat>{}</lat>", waypoint.latitude)?;
+        writeln!(out, "<lon>{}</lon>", waypoint.longitude)?;
+        writeln!(out, "<comment>{}</comment>", escape(&truncate(&waypoint.name, COMMENT_LENGTH)))?;
+        writeln!(out, "</waypoint>")?;
+        written.push(id);
+    }
+    writeln!(out, "</waypoint-table>")?;
+    writeln!(out, "<route>")?;
+    writeln!(out, "<route-name>{}</route-name>", escape(name))?;
+    writeln!(out, "<flight-plan-index>1</flight-plan-index>")?;
+    for (waypoint, id) in waypoints.iter().zip(&ids) {
+        writeln!(out, "<route-point>")?;
+        writeln!(out, "<waypoint-identifier>{}</waypoint-identifier>", escape(id))?;
+        writeln!(out, "<waypoint-type>{}</waypoint-type>", WAYPOINT_TYPE)?;
+        writeln!(
+            out,
+            "<waypoint-country-code>{}</waypoint-country-code>",
+            country_code(waypoint)
+        )?;
+        writeln!(out, "</route-point>")?;
+    }
+    writeln!(out, "</route>")?;
+    writeln!(out, "</flight-plan>")
+}
+
+fn country_code(waypoint: &Waypoint) -> &str {
+    waypoint.region.as_deref().unwrap_or("")
+}
+
+pub struct Fpl;
+
+impl OutputFormat for Fpl {
+    fn name(&self) -> &'static str {
+        "fpl"
+    }
+
+    fn description(&self) -> &'static str {
+        "Garmin FPL flight plan visiting the waypoints in order"
+    }
+
+    fn extension(&self) -> &'static str {
+        "fpl"
+    }
+
+    fn columns(&self) -> Columns {
+        Columns(vec!["Ident", "Name", "Latitude", "Longitude", "Region"])
+    }
+
+    fn limits(&self) -> Limits {
+        Limits::new(IDENTIFIER_LENGTH, COMMENT_LENGTH)
+    }
+
+    fn render(&self, waypoints: &[Waypoint], options: &WriteOptions) -> Result<Vec<u8>, Box<dyn Error>> {
+        let mut out = options.line_ending.wrap(vec![]);
+        // Idents are shortened after they are sanitized, by the plan itself.
+        let limits = options.limits.or(self.limits());
+        let waypoints = Limits { ident: None, ..limits }.apply(waypoints);
+        let ident_length = limits.ident.map_or(IDENTIFIER_LENGTH, |max| max.get().min(IDENTIFIER_LENGTH));
+        plan(&mut out, &truncate(&options.title, COMMENT_LENGTH), &waypoints, ident_length)?;
+        Ok(out.into_inner())
+    }
+}
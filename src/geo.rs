@@ -0,0 +1,74 @@
+const EARTH_RADIUS_M: f64 = 6_371_008.8;
+pub const METRES_PER_NM: f64 = 1852.;
+
+/// A latitude and longitude range in decimal degrees.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Bounds {
+    pub min_latitude: f64,
+    pub max_latitude: f64,
+    pub min_longitude: f64,
+    pub max_longitude: f64,
+}
+
+impl Bounds {
+    pub fn contains(&self, lat: f64, lon: f64) -> bool {
+        (self.min_latitude..=self.max_latitude).contains(&lat) && (self.min_longitude..=self.max_longitude).contains(&lon)
+    }
+}
+
+/// Poland, 49.00-54.84 N and 14.12-24.15 E, widened by about half a degree
+/// so airfields on the border and points offshore still fit.
+pub const POLAND: Bounds = Bounds { min_latitude: 48.5, max_latitude: 55.4, min_longitude: 13.4, max_longitude: 24.9 };
+
+/// Point reached by travelling `distance` metres from `lat`/`lon` along the
+/// great circle with initial true `bearing` (all angles in decimal degrees).
+pub fn destination(lat: f64, lon: f64, bearing: f64, distance: f64) -> (f64, f64) {
+    let delta = distance / EARTH_RADIUS_M;
+    let theta = bearing.to_radians();
+    let phi1 = lat.to_radians();
+    let lambda1 = lon.to_radians();
+    let phi2 = (phi1.sin() * delta.cos() + phi1.cos() * delta.sin() * theta.cos()).asin();
+    let lambda2 = lambda1
+        + (theta.sin() * delta.sin() * phi1.cos()).atan2(delta.cos() - phi1.sin() * phi2.sin());
+    (phi2.to_degrees(), (lambda2.to_degrees() + 540.) % 360. - 180.)
+}
+
+pub fn reciprocal(bearing: f64) -> f64 {
+    (bearing + 180.) % 360.
+}
+
+/// Great-circle distance in metres between two points in decimal degrees.
+pub fn distance(lat1: f64, lon1: f64, lat2: f64, lon2: f64) -> f64 {
+    let phi1 = lat1.to_radians();
+    let phi2 = lat2.to_radians();
+    let dphi = (lat2 - lat1).to_radians();
+    let dlambda = (lon2 - lon1).to_radians();
+    let a = (dphi / 2.).sin().powi(2) + phi1.cos() * phi2.cos() * (dlambda / 2.).sin().powi(2);
+    2. * EARTH_RADIUS_M * a.sqrt().asin()
+}
+
+fn bearing(lat1: f64, lon1: f64, lat2: f64, lon2: f64) -> f64 {
+    let phi1 = lat1.to_radians();
+    let phi2 = lat2.to_radians();
+    let dlambda = (lon2 - lon1).to_radians();
+    let y = dlambda.sin() * phi2.cos();
+    let x = phi1.cos() * phi2.sin() - phi1.sin() * phi2.cos() * dlambda.cos();
+    y.atan2(x).to_degrees()
+}
+
+/// Distance in metres from a point to the great-circle segment between two
+/// others, i.e. the cross-track distance where the point lies abeam the
+/// segment and the distance to the nearer end otherwise.
+pub fn distance_to_segment(lat: f64, lon: f64, from: (f64, f64), to: (f64, f64)) -> f64 {
+    let d13 = distance(from.0, from.1, lat, lon) / EARTH_RADIUS_M;
+    let theta13 = bearing(from.0, from.1, lat, lon).to_radians();
+    let theta12 = bearing(from.0, from.1, to.0, to.1).to_radians();
+    let cross = (d13.sin() * (theta13 - theta12).sin()).asin();
+    let along = (d13.cos() / cross.cos()).clamp(-1., 1.).acos();
+    let length = distance(from.0, from.1, to.0, to.1) / EARTH_RADIUS_M;
+    if (theta13 - theta12).cos() < 0. || along > length {
+        distance(from.0, from.1, lat, lon).min(distance(to.0, to.1, lat, lon))
+    } else {
+        cross.abs() * EARTH_RADIUS_M
+    }
+}
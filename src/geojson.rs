@@ -0,0 +1,150 @@
+//! GeoJSON (RFC 7946) for web maps: a FeatureCollection of points whose
+//! properties are the userpoint fields.
+
+use crate::columns::Columns;
+use crate::format::{OutputFormat, WriteOptions};
+use crate::Waypoint;
+use serde_json::{json, Map, Value};
+use std::error::Error;
+
+/// The properties each feature carries, with the JSON types they may have.
+const PROPERTIES: [(&str, &[&str]); 11] = [
+    ("Type", &["string"]),
+    ("Name", &["string"]),
+    ("Ident", &["string"]),
+    ("Elevation", &["number", "null"]),
+    ("Magnetic Declination", &["number", "null"]),
+    ("Tags", &["string", "null"]),
+    ("Description", &["string", "null"]),
+    ("Region", &["string", "null"]),
+    ("Visible From", &["number", "null"]),
+    ("Last Edit", &["string", "null"]),
+    ("Import Filename", &["string", "null"]),
+];
+
+fn json_type(value: &Value) -> &'static str {
+    match value {
+        Value::Null => "null",
+        Value::Bool(_) => "boolean",
+        Value::Number(_) => "number",
+        Value::String(_) => "string",
+        Value::Array(_) => "array",
+        Value::Object(_) => "object",
+    }
+}
+
+/// The FeatureCollection for `waypoints`.
+pub fn collection(waypoints: &[Waypoint]) -> Value {
+    let columns = Columns(PROPERTIES.iter().map(|(name, _)| *name).collect());
+    let location = Columns(vec!["Longitude", "Latitude"]);
+    let features: Vec<Value> = waypoints
+        .iter()
+        .map(|w| {
+            let position: Vec<Value> = location.select(w).into_iter().map(|(_, v)| v).collect();
+            json!({
+                "type": "Feature",
+                "geometry": { "type": "Point", "coordinates": position },
+                "properties": columns.select(w),
+            })
+        })
+        .collect();
+    json!({ "type": "FeatureCollection", "features": features })
+}
+
+fn check_point(geometry: &Value, at: &str, problems: &mut Vec<String>) {
+    let Some(geometry) = geometry.as_object() else {
+        problems.push(format!("{}: geometry is not an object", at));
+        return;
+    };
+    if geometry.get("type").and_then(Value::as_str) != Some("Point") {
+        problems.push(format!("{}: geometry type is not Point", at));
+    }
+    let Some(position) = geometry.get("coordinates").and_then(Value::as_array) else {
+        problems.push(format!("{}: geometry has no coordinates array", at));
+        return;
+    };
+    let numbers: Vec<f64> = position.iter().filter_map(Value::as_f64).collect();
+    if numbers.len() != position.len() || !(2..=3).contains(&numbers.len()) {
+        problems.push(format!("{}: a position is two or three numbers, got {}", at, Value::Array(position.clone())));
+        return;
+    }
+    // RFC 7946 positions are longitude first.
+    if !(-180.0..=180.0).contains(&numbers[0]) || !(-90.0..=90.0).contains(&numbers[1]) {
+        problems.push(format!("{}: position {}, {} is out of range", at, numbers[0], numbers[1]));
+    }
+}
+
+fn check_properties(properties: &Map<String, Value>, at: &str, problems: &mut Vec<String>) {
+    for (name, types) in PROPERTIES {
+        let value = properties.get(name).unwrap_or(&Value::Null);
+        if !types.contains(&json_type(value)) {
+            problems.push(format!("{}: property {:?} is {}, expected {}", at, name, json_type(value), types.join(" or ")));
+        }
+    }
+    for name in properties.keys() {
+        if !PROPERTIES.iter().any(|(p, _)| p == name) {
+            problems.push(format!("{}: unexpected property {:?}", at, name));
+        }
+    }
+    if properties.get("Ident").and_then(Value::as_str).is_some_and(str::is_empty) {
+        problems.push(format!("{}: empty Ident", at));
+    }
+}
+
+/// Checks a document against the RFC 7946 FeatureCollection structure for
+/// points and against the properties this crate writes; returns every
+/// problem found.
+pub fn validate(document: &Value) -> Result<(), Vec<String>> {
+    let mut problems = vec![];
+    if document.get("type").and_then(Value::as_str) != Some("FeatureCollection") {
+        problems.push("the document is not a FeatureCollection".to_owned());
+    }
+    match document.get("features").and_then(Value::as_array) {
+        None => problems.push("the document has no features array".to_owned()),
+        Some(features) => {
+            for (i, feature) in features.iter().enumerate() {
+                let at = format!("feature {}", i);
+                if feature.get("type").and_then(Value::as_str) != Some("Feature") {
+                    problems.push(format!("{}: type is not Feature", at));
+                }
+                match feature.get("geometry") {
+                    Some(geometry) => check_point(geometry, &at, &mut problems),
+                    None => problems.push(format!("{}: no geometry", at)),
+                }
+                match feature.get("properties") {
+                    Some(Value::Object(properties)) => check_properties(properties, &at, &mut problems),
+                    _ => problems.push(format!("{}: properties is not an object", at)),
+                }
+            }
+        }
+    }
+    match problems.is_empty() {
+        true => Ok(()),
+        false => Err(problems),
+    }
+}
+
+pub struct GeoJson;
+
+impl OutputFormat for GeoJson {
+    fn name(&self) -> &'static str {
+        "geojson"
+    }
+
+    fn description(&self) -> &'static str {
+        "GeoJSON FeatureCollection of points for web maps"
+    }
+
+    fn extension(&self) -> &'static str {
+        "geojson"
+    }
+
+    fn render(&self, waypoints: &[Waypoint], options: &WriteOptions) -> Result<Vec<u8>, Box<dyn Error>> {
+        let document = collection(&self.shorten(waypoints, options));
+        if options.validate {
+            validate(&document).map_err(|problems| format!("invalid GeoJSON: {}", problems.join("; ")))?;
+        }
+        let text = serde_json::to_string_pretty(&document)?;
+        Ok(options.line_ending.apply(&text).into_owned().into_bytes())
+    }
+}
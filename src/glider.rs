@@ -0,0 +1,141 @@
+//! Glider operations at a site: whether it has winch launches or aerotows
+//! and the runway normally in use. They are kept as tags, so they survive
+//! merging and the userpoints CSV: `glider`, `winch`, `aerotow` and
+//! `rwy-07` for runway 07.
+
+use crate::lang::Lang;
+use crate::{input, Waypoint};
+use serde::Deserialize;
+use std::collections::HashMap;
+use std::error::Error;
+
+pub const GLIDER_TAG: &str = "glider";
+pub const WINCH_TAG: &str = "winch";
+pub const AEROTOW_TAG: &str = "aerotow";
+const RUNWAY_TAG: &str = "rwy-";
+
+const GLIDER_WORDS: [&str; 4] = ["szybow", "glider", "gliding", "segelflug"];
+const WINCH_WORDS: [&str; 3] = ["wyciągark", "wyciagark", "winch"];
+const AEROTOW_WORDS: [&str; 3] = ["holowani", "aerotow", "aero-tow"];
+
+pub fn is_glider_site(waypoint: &Waypoint) -> bool {
+    [GLIDER_TAG, WINCH_TAG, AEROTOW_TAG].iter().any(|t| waypoint.has_tag(t))
+}
+
+/// The designator of the runway gliders normally use, e.g. `07`.
+pub fn runway(waypoint: &Waypoint) -> Option<&str> {
+    waypoint.tags.as_deref()?.split(',').find_map(|t| t.strip_prefix(RUNWAY_TAG))
+}
+
+/// Records `designator` as the runway normally in use; a designator pair
+/// such as `07/25` keeps the first.
+pub fn set_runway(waypoint: &mut Waypoint, designator: &str) {
+    let designator = designator.split('/').next().unwrap_or_default().trim();
+    if designator.is_empty() {
+        return;
+    }
+    if let Some(old) = runway(waypoint).map(|r| format!("{}{}", RUNWAY_TAG, r)) {
+        waypoint.remove_tag(&old);
+    }
+    waypoint.add_tag(&format!("{}{}", RUNWAY_TAG, designator.to_uppercase()));
+}
+
+/// Tags glider sites and their launch methods from words in the name and
+/// description, in Polish, English or German.
+pub fn detect(waypoint: &mut Waypoint) {
+    let text = format!("{} {}", waypoint.name, waypoint.description.as_deref().unwrap_or_default()).to_lowercase();
+    let mentions = |words: &[&str]| words.iter().any(|w| text.contains(w));
+    if mentions(&WINCH_WORDS) {
+        waypoint.add_tag(WINCH_TAG);
+    }
+    if mentions(&AEROTOW_WORDS) {
+        waypoint.add_tag(AEROTOW_TAG);
+    }
+    if mentions(&GLIDER_WORDS) || mentions(&WINCH_WORDS) || mentions(&AEROTOW_WORDS) {
+        waypoint.add_tag(GLIDER_TAG);
+    }
+}
+
+/// What a supplemental CSV row says about one site; empty cells leave the
+/// site as the source has it.
+#[derive(Debug, Default, Deserialize)]
+pub struct Supplement {
+    #[serde(rename = "Ident")]
+    pub ident: String,
+    #[serde(rename = "Winch", default, deserialize_with = "yes_no")]
+    pub winch: Option<bool>,
+    #[serde(rename = "Aerotow", default, deserialize_with = "yes_no")]
+    pub aerotow: Option<bool>,
+    #[serde(rename = "Runway", default)]
+    pub runway: Option<String>,
+}
+
+fn yes_no<'de, D: serde::Deserializer<'de>>(deserializer: D) -> Result<Option<bool>, D::Error> {
+    let value = Option::<String>::deserialize(deserializer)?.unwrap_or_default();
+    match value.trim().to_lowercase().as_str() {
+        "" => Ok(None),
+        "y" | "yes" | "t" | "tak" | "true" | "1" => Ok(Some(true)),
+        "n" | "no" | "nie" | "false" | "0" => Ok(Some(false)),
+        other => Err(serde::de::Error::custom(format!("expected yes or no, got {:?}", other))),
+    }
+}
+
+/// Reads a CSV with `Ident,Winch,Aerotow,Runway` columns, keyed by ident.
+pub fn read_supplement(path: &str, delimiter: u8) -> Result<HashMap<String, Supplement>, Box<dyn Error>> {
+    let mut reader = csv::ReaderBuilder::new().delimiter(delimiter).from_reader(input::open(path)?);
+    let mut supplements = HashMap::new();
+    for record in reader.deserialize() {
+        let supplement: Supplement = record?;
+        supplements.insert(supplement.ident.trim().to_owned(), supplement);
+    }
+    Ok(supplements)
+}
+
+/// Applies the supplement for the waypoint's ident, if there is one.
+pub fn supplement(waypoint: &mut Waypoint, supplements: &HashMap<String, Supplement>) {
+    let Some(supplement) = supplements.get(&waypoint.ident) else {
+        return;
+    };
+    for (tag, value) in [(WINCH_TAG, supplement.winch), (AEROTOW_TAG, supplement.aerotow)] {
+        match value {
+            Some(true) => waypoint.add_tag(tag),
+            Some(false) => waypoint.remove_tag(tag),
+            None => {}
+        }
+    }
+    if let Some(runway) = &supplement.runway {
+        set_runway(waypoint, runway);
+    }
+    if supplement.winch == Some(true) || supplement.aerotow == Some(true) {
+        waypoint.add_tag(GLIDER_TAG);
+    }
+}
+
+/// Appends the launch methods and runway to the description of glider
+/// sites, e.g. `winch launch, aerotow, RWY 07`.
+pub fn describe(waypoint: &mut Waypoint, lang: Lang) {
+    if !is_glider_site(waypoint) {
+        return;
+    }
+    let (winch, aerotow, runway) = (waypoint.has_tag(WINCH_TAG), waypoint.has_tag(AEROTOW_TAG), runway(waypoint));
+    if !winch && !aerotow && runway.is_none() {
+        return;
+    }
+    let note = lang.note(|lang| {
+        let mut parts = vec![];
+        if winch {
+            parts.push(lang.text("winch launch".to_owned(), "wyciągarka".to_owned()));
+        }
+        if aerotow {
+            parts.push(lang.text("aerotow".to_owned(), "hol samolotowy".to_owned()));
+        }
+        if let Some(runway) = &runway {
+            parts.push(lang.runway(runway));
+        }
+        parts.join(", ")
+    });
+    waypoint.description = Some(match waypoint.description.take() {
+        Some(d) if !d.is_empty() => format!("{}; {}", d, note),
+        _ => note,
+    });
+}
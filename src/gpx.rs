@@ -0,0 +1,81 @@
+use crate::columns::Columns;
+use crate::elevation::FEET_PER_METRE;
+use crate::format::{OutputFormat, WriteOptions};
+use crate::line_ending::LineEnding;
+use crate::xml::escape;
+use crate::Waypoint;
+use std::error::Error;
+use std::io::{self, Write};
+
+/// Writes the waypoints as both a GPX route and an equivalent track, since
+/// some GPS units only import one or the other.
+pub fn write_route(path: &str, name: &str, waypoints: &[Waypoint], ending: LineEnding) -> io::Result<()> {
+    let mut out = ending.create(path)?;
+    route(&mut out, name, waypoints)?;
+    out.flush()
+}
+
+fn route(out: &mut impl Write, name: &str, waypoints: &[Waypoint]) -> io::Result<()> {
+    writeln!(out, r#"<?xml version="1.0" encoding="UTF-8"?>"#)?;
+    writeln!(
+        out,
+        r#"<gpx version="1.1" creator="polish-airports" xmlns="http://www.topografix.com/GPX/1/1">"#
+    )?;
+    writeln!(out, "<rte>")?;
+    writeln!(out, "<name>{}</name>", escape(name))?;
+    for waypoint in waypoints {
+        point(out, "rtept", waypoint)?;
+    }
+    writeln!(out, "</rte>")?;
+    writeln!(out, "<trk>")?;
+    writeln!(out, "<name>{}</name>", escape(name))?;
+    writeln!(out, "<trkseg>")?;
+    for waypoint in waypoints {
+        point(out, "trkpt", waypoint)?;
+    }
+    writeln!(out, "</trkseg>")?;
+    writeln!(out, "</trk>")?;
+    writeln!(out, "</gpx>")
+}
+
+fn point(out: &mut impl Write, tag: &str, waypoint: &Waypoint) -> io::Result<()> {
+    writeln!(
+        out,
+        r#"<{} lat="{}" lon="{}">"#,
+        tag, waypoint.latitude, waypoint.longitude
+    )?;
+    if let Some(elevation) = waypoint.elevation {
+        // GPX elevations are metres, userpoints carry feet; a tenth of a
+        // metre is finer than whole feet already.
+        writeln!(out, "<ele>{:.1}</ele>", elevation / FEET_PER_METRE)?;
+    }
+    writeln!(out, "<name>{}</name>", escape(&waypoint.ident))?;
+    writeln!(out, "<desc>{}</desc>", escape(&waypoint.name))?;
+    writeln!(out, "</{}>", tag)
+}
+
+pub struct Gpx;
+
+impl OutputFormat for Gpx {
+    fn name(&self) -> &'static str {
+        "gpx"
+    }
+
+    fn description(&self) -> &'static str {
+        "GPX route and track visiting the waypoints in order"
+    }
+
+    fn extension(&self) -> &'static str {
+        "gpx"
+    }
+
+    fn columns(&self) -> Columns {
+        Columns(vec!["Ident", "Name", "Latitude", "Longitude", "Elevation"])
+    }
+
+    fn render(&self, waypoints: &[Waypoint], options: &WriteOptions) -> Result<Vec<u8>, Box<dyn Error>> {
+        let mut out = options.line_ending.wrap(vec![]);
+        route(&mut out, &options.title, &self.shorten(waypoints, options))?;
+        Ok(out.into_inner())
+    }
+}
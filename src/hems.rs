@@ -0,0 +1,135 @@
+//! Hospital landing sites used by the air ambulance (HEMS). They get their
+//! own waypoint type, so a moving map can show them with their own icon
+//! instead of as airstrips, and a `night` tag where they are lit for night
+//! operations.
+
+use crate::elevation::FEET_PER_METRE;
+use crate::format::{Fields, InputFormat, ReadOptions, Records};
+use crate::ulc::{decimal_point, position, rows, tidy_name};
+use crate::{region, Waypoint};
+use std::error::Error;
+
+/// The waypoint type of hospital landing sites. Little Navmap shows types
+/// it has no icon for with its own `userpoint_HEMS.svg` when one is in the
+/// user icon directory.
+pub const HEMS_TYPE: &str = "HEMS";
+
+/// The tag of sites available at night.
+pub const NIGHT_TAG: &str = "night";
+
+/// Where each field is in a site list row.
+struct Layout {
+    name: usize,
+    hospital: Option<usize>,
+    icao: Option<usize>,
+    position: Option<usize>,
+    latitude: Option<usize>,
+    longitude: Option<usize>,
+    elevation: Option<(usize, bool)>,
+    night: Option<usize>,
+    remarks: Option<usize>,
+}
+
+impl Layout {
+    fn from_header(row: &[String]) -> Option<Self> {
+        let headers: Vec<String> = row.iter().map(|h| h.to_lowercase()).collect();
+        let find = |keys: &[&str]| headers.iter().position(|h| keys.iter().any(|k| h.contains(k)));
+        let layout = Layout {
+            name: find(&["nazwa", "name", "lądowisko"])?,
+            hospital: find(&["szpital", "hospital"]),
+            icao: find(&["icao", "kod"]),
+            position: find(&["współrzędne", "wspolrzedne", "coordinates", "position"]),
+            latitude: find(&["szerokość", "szerokosc", "latitude"]),
+            longitude: find(&["długość", "dlugosc", "longitude"]),
+            elevation: find(&["elewacja", "wysokość", "wysokosc", "elevation"])
+                .map(|i| (i, headers[i].contains("ft") || headers[i].contains("feet"))),
+            night: find(&["noc", "night", "pora"]),
+            remarks: find(&["uwagi", "remarks", "notes"]),
+        };
+        (layout.position.is_some() || (layout.latitude.is_some() && layout.longitude.is_some())).then_some(layout)
+    }
+}
+
+/// Whether a night column says the site is usable at night; `None` when it
+/// doesn't say.
+fn night(value: &str) -> Option<bool> {
+    let value = value.trim().to_lowercase();
+    match value.as_str() {
+        "" => None,
+        "nie" | "no" | "n" | "false" | "0" | "-" | "dzień" | "dzien" | "day" => Some(false),
+        "tak" | "yes" | "y" | "t" | "true" | "1" | "h24" | "24h" => Some(true),
+        _ if value.contains("noc") || value.contains("night") || value.contains("całodob") => Some(true),
+        _ => None,
+    }
+}
+
+/// Reads a list of HEMS landing sites from a spreadsheet or CSV with a
+/// header row naming the site and its position, in Polish or English.
+/// Sites with an ICAO code use it as the ident; the hospital and remarks go
+/// into the description.
+pub fn read_sites(path: &str, delimiter: u8) -> Result<Vec<Waypoint>, Box<dyn Error>> {
+    let rows = rows(path, delimiter)?;
+    let (header, layout) = rows
+        .iter()
+        .enumerate()
+        .find_map(|(i, row)| Some((i, Layout::from_header(row)?)))
+        .ok_or_else(|| format!("{}: no header row with the site name and coordinates", path))?;
+    let mut waypoints = vec![];
+    for (i, row) in rows.iter().enumerate().skip(header + 1) {
+        let cell = |column: Option<usize>| column.and_then(|c| row.get(c)).cloned().unwrap_or_default();
+        let name = tidy_name(&cell(Some(layout.name)));
+        if name.is_empty() {
+            continue;
+        }
+        let (latitude, longitude) = position(&cell(layout.position), &cell(layout.latitude), &cell(layout.longitude))
+            .ok_or_else(|| format!("{}: row {} ({}): unreadable coordinates", path, i + 1, name))?;
+        let elevation = layout.elevation.and_then(|(column, feet)| {
+            let value = decimal_point(&cell(Some(column)));
+            let value = value.trim_end_matches(|c: char| c.is_alphabetic() || c == '.' || c.is_whitespace());
+            let value = value.parse::<f32>().ok()?;
+            Some(if feet { value } else { (value * FEET_PER_METRE).round() })
+        });
+        let icao = cell(layout.icao).trim().to_uppercase();
+        let icao = region::of_icao(&icao).is_some().then_some(icao);
+        let mut waypoint = Waypoint::builder(&name)
+            .waypoint_type(HEMS_TYPE)
+            .coordinates(latitude, longitude)
+            .elevation(elevation)
+            .region(region::POLAND)
+            .import_filename(path);
+        if let Some(icao) = icao {
+            waypoint = waypoint.ident(icao);
+        }
+        let mut waypoint = waypoint.build().map_err(|e| e.in_record(format!("{}: row {}", path, i + 1)))?;
+        if night(&cell(layout.night)) == Some(true) {
+            waypoint.add_tag(NIGHT_TAG);
+        }
+        let description: Vec<String> = [cell(layout.hospital), cell(layout.remarks)]
+            .into_iter()
+            .filter(|text| !text.is_empty())
+            .collect();
+        waypoint.description = (!description.is_empty()).then(|| description.join("; "));
+        waypoints.push(waypoint);
+    }
+    Ok(waypoints)
+}
+
+pub struct HemsSites;
+
+impl InputFormat for HemsSites {
+    fn name(&self) -> &'static str {
+        "hems"
+    }
+
+    fn description(&self) -> &'static str {
+        "Hospital (HEMS) landing sites as a spreadsheet or CSV"
+    }
+
+    fn fields(&self) -> Fields {
+        Fields { elevation: true, ..Fields::default() }
+    }
+
+    fn read(&self, path: &str, options: &ReadOptions) -> Result<Records, Box<dyn Error>> {
+        Ok(Box::new(read_sites(path, options.delimiter)?.into_iter()))
+    }
+}
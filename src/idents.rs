@@ -0,0 +1,116 @@
+use crate::Waypoint;
+use clap::ValueEnum;
+use regex::Regex;
+use std::collections::{HashMap, HashSet};
+use std::sync::LazyLock;
+
+/// The tag of records whose ident looks like a Polish ICAO code but isn't
+/// one, such as the `EP12` style codes some clubs give their strips.
+pub const PSEUDO_ICAO_TAG: &str = "pseudo-icao";
+
+static POLISH_ICAO: LazyLock<Regex> = LazyLock::new(|| Regex::new("^EP[A-Z]{2}$").expect("the pattern is valid"));
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, ValueEnum)]
+pub enum DuplicatePolicy {
+    /// Warn about each duplicated ident and write the data unchanged
+    Report,
+    /// Append 2, 3, ... to the repeats so every ident is unique
+    Suffix,
+    /// Refuse to write output containing duplicated idents
+    Fail,
+}
+
+/// Idents (compared case-insensitively) that occur more than once, with their
+/// number of occurrences, in order of first appearance.
+pub fn duplicates(waypoints: &[Waypoint]) -> Vec<(String, usize)> {
+    let mut counts: Vec<(String, usize)> = vec![];
+    let mut index: HashMap<String, usize> = HashMap::new();
+    for waypoint in waypoints {
+        let key = waypoint.ident.to_uppercase();
+        match index.get(&key) {
+            Some(&i) => counts[i].1 += 1,
+            None => {
+                index.insert(key, counts.len());
+                counts.push((waypoint.ident.clone(), 1));
+            }
+        }
+    }
+    counts.retain(|(_, n)| *n > 1);
+    counts
+}
+
+/// Renames repeated idents by appending the lowest free number.
+pub fn make_unique(waypoints: &mut [Waypoint]) {
+    let mut taken: HashSet<String> = waypoints.iter().map(|w| w.ident.to_uppercase()).collect();
+    let mut seen: HashSet<String> = HashSet::new();
+    for waypoint in waypoints.iter_mut() {
+        let key = waypoint.ident.to_uppercase();
+        if !seen.contains(&key) {
+            seen.insert(key);
+            continue;
+        }
+        let mut n = 2;
+        while taken.contains(&format!("{}{}", key, n)) {
+            n += 1;
+        }
+        waypoint.ident = format!("{}{}", waypoint.ident, n);
+        taken.insert(waypoint.ident.to_uppercase());
+    }
+}
+
+/// Applies `policy` to the output set and returns the duplicated idents
+/// for the caller to report, as [`duplicates`] does.
+pub fn check(waypoints: &mut [Waypoint], policy: DuplicatePolicy) -> Result<Vec<(String, usize)>, String> {
+    let duplicates = duplicates(waypoints);
+    if duplicates.is_empty() {
+        return Ok(duplicates);
+    }
+    match policy {
+        DuplicatePolicy::Report => Ok(duplicates),
+        DuplicatePolicy::Suffix => {
+            make_unique(waypoints);
+            Ok(duplicates)
+        }
+        DuplicatePolicy::Fail => {
+            let message = duplicates.iter().map(|(ident, count)| format!("{} used by {} waypoints", ident, count));
+            Err(format!("{} duplicated idents: {}", duplicates.len(), message.collect::<Vec<_>>().join(", ")))
+        }
+    }
+}
+
+/// What an ident turned out to be when checked as an ICAO code.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum IcaoIdent {
+    /// A well-formed Polish ICAO code.
+    Icao,
+    /// Four characters starting with EP that aren't a valid code.
+    Pseudo,
+    /// Not claiming to be an ICAO code at all, e.g. a name.
+    Other,
+}
+
+pub fn is_polish_icao(code: &str) -> bool {
+    POLISH_ICAO.is_match(code)
+}
+
+/// Trims and upper-cases idents that claim to be Polish ICAO codes, four
+/// letters or digits starting with EP, and tags those that still aren't
+/// valid as pseudo-idents. Other idents are left alone, as are those that
+/// are just the name, like `Epka`.
+pub fn normalize_icao(waypoint: &mut Waypoint) -> IcaoIdent {
+    let code = waypoint.ident.trim().to_uppercase();
+    if waypoint.ident.trim() == waypoint.name.trim() && code != waypoint.name.trim() {
+        return IcaoIdent::Other;
+    }
+    if code.len() != 4 || !code.starts_with("EP") || !code.chars().all(|c| c.is_ascii_alphanumeric()) {
+        return IcaoIdent::Other;
+    }
+    waypoint.ident = code;
+    if is_polish_icao(&waypoint.ident) {
+        waypoint.remove_tag(PSEUDO_ICAO_TAG);
+        IcaoIdent::Icao
+    } else {
+        waypoint.add_tag(PSEUDO_ICAO_TAG);
+        IcaoIdent::Pseudo
+    }
+}
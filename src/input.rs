@@ -0,0 +1,51 @@
+use crate::Error;
+use memmap2::Mmap;
+use std::fs::File;
+use std::io::{Cursor, Read};
+use std::ops::Deref;
+
+/// A read-only input file, mapped into memory when it is a regular file.
+/// The streaming readers page a mapped file in as they go instead of
+/// copying it, so inputs larger than RAM work.
+pub type Input = Cursor<Bytes>;
+
+/// The contents of an input file, mapped or read.
+pub enum Bytes {
+    Mapped(Mmap),
+    /// Pipes, devices and files that can't be mapped, read up front.
+    Read(Vec<u8>),
+}
+
+impl Deref for Bytes {
+    type Target = [u8];
+
+    fn deref(&self) -> &[u8] {
+        match self {
+            Bytes::Mapped(map) => map,
+            Bytes::Read(bytes) => bytes,
+        }
+    }
+}
+
+impl AsRef<[u8]> for Bytes {
+    fn as_ref(&self) -> &[u8] {
+        self
+    }
+}
+
+pub fn open(path: &str) -> Result<Input, Error> {
+    let mut file = File::open(path).map_err(Error::file(path))?;
+    if file.metadata().is_ok_and(|m| m.is_file()) {
+        // SAFETY: the map is only read, but its contents are those of the
+        // file at the time of each access. Mapping assumes nothing modifies
+        // the input during a run: a write shows through mid-parse and a
+        // truncation raises SIGBUS, killing the process. We accept that for
+        // input files, which are not written while being converted.
+        if let Ok(map) = unsafe { Mmap::map(&file) } {
+            return Ok(Cursor::new(Bytes::Mapped(map)));
+        }
+    }
+    let mut bytes = vec![];
+    file.read_to_end(&mut bytes).map_err(Error::file(path))?;
+    Ok(Cursor::new(Bytes::Read(bytes)))
+}
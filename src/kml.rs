@@ -0,0 +1,90 @@
+use crate::columns::Columns;
+use crate::format::{OutputFormat, WriteOptions};
+use crate::line_ending::{LineEnding, TextFile};
+use crate::runway::RunwayEnd;
+use crate::xml::escape;
+use crate::Waypoint;
+use std::error::Error;
+use std::io::{self, Write};
+
+/// Google Earth review layer: one placemark per waypoint, optionally with
+/// each runway drawn as a line between its thresholds.
+pub struct KmlWriter<W = TextFile> {
+    out: W,
+}
+
+impl KmlWriter {
+    pub fn create(path: &str, ending: LineEnding) -> io::Result<Self> {
+        KmlWriter::new(ending.create(path)?)
+    }
+}
+
+impl<W: Write> KmlWriter<W> {
+    pub fn new(mut out: W) -> io::Result<Self> {
+        writeln!(out, r#"<?xml version="1.0" encoding="UTF-8"?>"#)?;
+        writeln!(out, r#"<kml xmlns="http://www.opengis.net/kml/2.2">"#)?;
+        writeln!(out, "<Document>")?;
+        Ok(KmlWriter { out })
+    }
+
+    pub fn point(&mut self, waypoint: &Waypoint) -> io::Result<()> {
+        writeln!(self.out, "<Placemark>")?;
+        writeln!(self.out, "<name>{}</name>", escape(&waypoint.name))?;
+        if let Some(description) = &waypoint.description {
+            writeln!(self.out, "<description>{}</description>", escape(description))?;
+        }
+        writeln!(
+            self.out,
+            "<Point><coordinates>{},{}</coordinates></Point>",
+            waypoint.longitude, waypoint.latitude
+        )?;
+        writeln!(self.out, "</Placemark>")
+    }
+
+    pub fn runway(&mut self, name: &str, from: &RunwayEnd, to: &RunwayEnd) -> io::Result<()> {
+        writeln!(self.out, "<Placemark>")?;
+        writeln!(self.out, "<name>{}</name>", escape(name))?;
+        writeln!(
+            self.out,
+            "<LineString><tessellate>1</tessellate><coordinates>{},{} {},{}</coordinates></LineString>",
+            from.longitude, from.latitude, to.longitude, to.latitude
+        )?;
+        writeln!(self.out, "</Placemark>")
+    }
+
+    /// Closes the document and returns the underlying writer.
+    pub fn finish(mut self) -> io::Result<W> {
+        writeln!(self.out, "</Document>")?;
+        writeln!(self.out, "</kml>")?;
+        self.out.flush()?;
+        Ok(self.out)
+    }
+}
+
+pub struct Kml;
+
+impl OutputFormat for Kml {
+    fn name(&self) -> &'static str {
+        "kml"
+    }
+
+    fn description(&self) -> &'static str {
+        "Google Earth KML placemarks"
+    }
+
+    fn extension(&self) -> &'static str {
+        "kml"
+    }
+
+    fn columns(&self) -> Columns {
+        Columns(vec!["Name", "Description", "Latitude", "Longitude"])
+    }
+
+    fn render(&self, waypoints: &[Waypoint], options: &WriteOptions) -> Result<Vec<u8>, Box<dyn Error>> {
+        let mut kml = KmlWriter::new(options.line_ending.wrap(vec![]))?;
+        for waypoint in self.shorten(waypoints, options).iter() {
+            kml.point(waypoint)?;
+        }
+        Ok(kml.finish()?.into_inner())
+    }
+}
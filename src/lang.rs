@@ -0,0 +1,62 @@
+use clap::ValueEnum;
+
+/// Language of the description text the converter generates: surfaces,
+/// glider, METAR and NOTAM notes. Source text is never translated.
+#[derive(Debug, Clone, Copy, Default, PartialEq, ValueEnum)]
+pub enum Lang {
+    #[default]
+    En,
+    Pl,
+    /// Polish, then English after a ` / `, e.g. `trawa / grass`
+    Both,
+}
+
+impl Lang {
+    /// The English or the Polish wording of a note, or both.
+    pub fn text(self, en: String, pl: String) -> String {
+        match self {
+            Lang::En => en,
+            Lang::Pl => pl,
+            Lang::Both if en == pl => en,
+            Lang::Both => format!("{} / {}", pl, en),
+        }
+    }
+
+    /// A note built once per language, so `Both` repeats the whole note
+    /// rather than each of its words.
+    pub fn note(self, build: impl Fn(Lang) -> String) -> String {
+        match self {
+            Lang::Both => self.text(build(Lang::En), build(Lang::Pl)),
+            lang => build(lang),
+        }
+    }
+
+    /// A runway surface as SkyDemon and most sources name it, e.g. `Grass`.
+    /// Surfaces without a Polish name are kept as given.
+    pub fn surface(self, surface: &str) -> String {
+        let polish = match surface.trim().to_lowercase().as_str() {
+            "grass" | "turf" => "trawa",
+            "asphalt" | "asp" | "bitumen" | "tarmac" => "asfalt",
+            "concrete" | "con" => "beton",
+            "paved" | "hard" => "utwardzona",
+            "gravel" | "grv" => "żwir",
+            "dirt" | "earth" | "soil" | "unpaved" => "grunt",
+            "sand" => "piasek",
+            "water" => "woda",
+            "snow" => "śnieg",
+            "ice" => "lód",
+            _ => return surface.to_owned(),
+        };
+        self.text(surface.to_owned(), polish.to_owned())
+    }
+
+    /// Abbreviation of a runway, `RWY` or the Polish `DS` (droga startowa).
+    pub fn runway(self, designator: &str) -> String {
+        self.text(format!("RWY {}", designator), format!("DS {}", designator))
+    }
+
+    /// A distance in nautical miles, `6 NM` or the Polish `6 Mm`.
+    pub fn nautical_miles(self, nm: f64) -> String {
+        self.text(format!("{:.0} NM", nm), format!("{:.0} Mm", nm))
+    }
+}
@@ -0,0 +1,398 @@
+//! Reading SkyDemon airfield data and other Polish aeronautical sources into
+//! Little Navmap userpoints.
+//!
+//! [`Waypoint`] is the userpoint record every reader produces and every
+//! writer consumes. [`read_airfields`] parses a SkyDemon airfields XML file
+//! ([`AirfieldReader`] streams it one record at a time),
+//! [`read_dataset`] reads an existing userpoints CSV, and the modules hold
+//! the individual readers, post-processing passes and output formats.
+//! [`pipeline`] strings them together into the whole conversion the command
+//! line runs.
+
+pub mod aip;
+pub mod aip_vfr;
+#[cfg(not(target_arch = "wasm32"))]
+mod ask;
+#[cfg(not(target_arch = "wasm32"))]
+mod audit;
+#[cfg(not(target_arch = "wasm32"))]
+pub mod backup;
+pub mod builder;
+#[cfg(not(target_arch = "wasm32"))]
+mod cache;
+pub mod circuit;
+pub mod columns;
+pub mod conflict;
+pub mod cup;
+pub mod dafif;
+pub mod dedupe;
+pub mod dem;
+pub mod diff;
+pub mod ead;
+#[cfg(not(target_arch = "wasm32"))]
+pub mod download;
+pub mod elevation;
+pub mod encoding;
+pub mod enrich;
+pub mod error;
+pub mod ffi;
+pub mod filter;
+pub mod format;
+pub mod fpl;
+pub mod geo;
+pub mod geojson;
+pub mod glider;
+pub mod gpx;
+pub mod hems;
+pub mod idents;
+pub mod input;
+pub mod kml;
+pub mod lang;
+pub mod line_ending;
+pub mod mapping;
+pub mod matching;
+pub mod merge;
+pub mod metar;
+pub mod nasr;
+pub mod navaid;
+#[cfg(not(target_arch = "wasm32"))]
+pub mod notam;
+pub mod obstacle;
+#[cfg(not(target_arch = "wasm32"))]
+pub mod openaip;
+pub mod osm;
+pub mod package;
+#[cfg(not(target_arch = "wasm32"))]
+pub mod pipeline;
+pub mod preview;
+#[cfg(not(target_arch = "wasm32"))]
+mod progress;
+#[cfg(feature = "python")]
+mod python;
+pub mod region;
+pub mod reporting;
+#[cfg(not(target_arch = "wasm32"))]
+mod review;
+#[cfg(not(target_arch = "wasm32"))]
+pub mod roundtrip;
+pub mod runway;
+pub mod skydemon;
+#[cfg(not(target_arch = "wasm32"))]
+pub mod spill;
+pub mod split;
+pub mod stats;
+pub mod summary;
+pub mod tagging;
+pub mod template;
+#[cfg(not(target_arch = "wasm32"))]
+mod timing;
+pub mod truncate;
+pub mod ulc;
+pub mod ultralight;
+pub mod validate;
+pub mod verify;
+#[cfg(feature = "wasm")]
+mod wasm;
+pub mod xml;
+
+use csv::ReaderBuilder;
+use serde::{Deserialize, Deserializer, Serialize, Serializer};
+use std::{fmt, io::Read, str::FromStr};
+
+pub use builder::WaypointBuilder;
+pub use error::Error;
+pub use skydemon::{airfield_waypoint, userpoint_type, AirfieldReader};
+
+/// One axis of a SkyDemon position, such as `N530956.01` or `E0174449.00`.
+/// Serialized as its fields, since the string form depends on the axis;
+/// either is accepted when deserializing.
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct Coordinate {
+    /// Negative south and west.
+    pub degrees: i32,
+    pub minutes: u32,
+    pub seconds: f32,
+    /// South or west; needed besides the sign of `degrees` within a degree
+    /// of the equator or the prime meridian, where `degrees` is zero.
+    pub negative: bool,
+}
+
+/// The `n` ASCII digits at the start of `s` as a number, and the rest.
+fn leading_digits(s: &str, n: usize) -> Option<(u32, &str)> {
+    let digits = s.get(..n)?;
+    if !digits.bytes().all(|b| b.is_ascii_digit()) {
+        return None;
+    }
+    Some((digits.parse().ok()?, &s[n..]))
+}
+
+impl FromStr for Coordinate {
+    type Err = Error;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let invalid = || Error::invalid("coordinate", s);
+        let mut chars = s.chars();
+        let (negative, width, max) = match chars.next() {
+            Some('N') => (false, 2, 90),
+            Some('S') => (true, 2, 90),
+            Some('E') => (false, 3, 180),
+            Some('W') => (true, 3, 180),
+            _ => return Err(invalid()),
+        };
+        let (deg, rest) = leading_digits(chars.as_str(), width).ok_or_else(invalid)?;
+        let (min, sec) = leading_digits(rest, 2).ok_or_else(invalid)?;
+        // Plain digits with at most one decimal point: `f32::from_str` would
+        // also take signs, exponents, `inf` and `NaN`.
+        let (whole, fraction) = sec.split_once('.').unwrap_or((sec, "0"));
+        if whole.is_empty() || !(whole.bytes().chain(fraction.bytes())).all(|b| b.is_ascii_digit()) {
+            return Err(invalid());
+        }
+        let sec = sec.parse::<f32>().map_err(|_| invalid())?;
+        if deg > max || min >= 60 || sec >= 60. || (deg == max && (min > 0 || sec > 0.)) {
+            return Err(invalid());
+        }
+        let deg = deg as i32;
+        Ok(Coordinate {
+            degrees: if negative { -deg } else { deg },
+            minutes: min,
+            seconds: sec,
+            negative,
+        })
+    }
+}
+
+impl Coordinate {
+    /// Accepts both decimal degrees within ±180 and the
+    /// `N520101.50`/`E0170101.50` form used in the airfield data.
+    pub fn parse_decimal(s: &str) -> Result<f32, Error> {
+        let s = s.trim();
+        if let Ok(decimal) = s.parse::<f32>() {
+            if !decimal.is_finite() || decimal.abs() > 180. {
+                return Err(Error::invalid("coordinate", s));
+            }
+            return Ok(decimal);
+        }
+        Ok(Coordinate::from_str(s)?.to_decimal_degrees())
+    }
+
+    pub fn is_negative(&self) -> bool {
+        self.negative || self.degrees < 0
+    }
+
+    pub fn to_decimal_degrees(&self) -> f32 {
+        let minutes: f32 = self.minutes as f32 / 60.;
+        let seconds = self.seconds / 3600.;
+        let degrees = self.degrees.unsigned_abs() as f32 + minutes + seconds;
+        if self.is_negative() {
+            -degrees
+        } else {
+            degrees
+        }
+    }
+
+    /// The string form, with `positive`/`negative` as the hemisphere letters
+    /// and `width` degree digits.
+    fn write(&self, f: &mut fmt::Formatter, positive: char, negative: char, width: usize) -> fmt::Result {
+        let hemisphere = if self.is_negative() { negative } else { positive };
+        // Rounded here rather than by the formatter so that 59.999 seconds
+        // carries into the minutes instead of printing as 60.00.
+        let mut hundredths = (self.seconds as f64 * 100.).round() as u32;
+        let (mut degrees, mut minutes) = (self.degrees.unsigned_abs(), self.minutes);
+        if hundredths >= 6000 {
+            hundredths -= 6000;
+            minutes += 1;
+        }
+        if minutes >= 60 {
+            minutes -= 60;
+            degrees += 1;
+        }
+        write!(f, "{}{:0width$}{:02}{:02}.{:02}", hemisphere, degrees, minutes, hundredths / 100, hundredths % 100)
+    }
+}
+
+impl<'de> Deserialize<'de> for Coordinate {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        #[derive(Deserialize)]
+        #[serde(untagged)]
+        enum Repr {
+            Text(String),
+            Fields {
+                degrees: i32,
+                minutes: u32,
+                seconds: f32,
+                #[serde(default)]
+                negative: bool,
+            },
+        }
+        match Repr::deserialize(deserializer)? {
+            Repr::Text(s) => Coordinate::from_str(&s).map_err(serde::de::Error::custom),
+            Repr::Fields { degrees, minutes, seconds, negative } => {
+                Ok(Coordinate { degrees, minutes, seconds, negative: negative || degrees < 0 })
+            }
+        }
+    }
+}
+
+/// A SkyDemon `Position` attribute: latitude and longitude separated by a space.
+/// Serialized in that string form.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Position {
+    pub lat: Coordinate,
+    pub lon: Coordinate,
+}
+
+impl FromStr for Position {
+    type Err = Error;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let (lat, long) = s.trim().split_once(' ').ok_or_else(|| Error::invalid("position", s))?;
+        let lat = Coordinate::from_str(lat)?;
+        let lon = Coordinate::from_str(long.trim_start())?;
+        Ok(Position { lat, lon })
+    }
+}
+
+impl fmt::Display for Position {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        self.lat.write(f, 'N', 'S', 2)?;
+        f.write_str(" ")?;
+        self.lon.write(f, 'E', 'W', 3)
+    }
+}
+
+impl Serialize for Position {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.collect_str(self)
+    }
+}
+
+impl<'de> Deserialize<'de> for Position {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        let s = String::deserialize(deserializer)?;
+        Position::from_str(&s).map_err(serde::de::Error::custom)
+    }
+}
+
+/// A Little Navmap userpoint, serialized with the userpoints CSV column names.
+/// Elevation is in feet.
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub struct Waypoint {
+    #[serde(rename = "Type")]
+    pub waypoint_type: String,
+    #[serde(rename = "Name")]
+    pub name: String,
+    #[serde(rename = "Ident")]
+    pub ident: String,
+    #[serde(rename = "Latitude")]
+    pub latitude: f32,
+    #[serde(rename = "Longitude")]
+    pub longitude: f32,
+    #[serde(rename = "Elevation")]
+    pub elevation: Option<f32>,
+    #[serde(rename = "Magnetic Declination")]
+    pub magnetic_declination: Option<f32>,
+    #[serde(rename = "Tags")]
+    pub tags: Option<String>,
+    #[serde(rename = "Description")]
+    pub description: Option<String>,
+    #[serde(rename = "Region")]
+    pub region: Option<String>,
+    #[serde(rename = "Visible From")]
+    pub visible_from: Option<i32>,
+    #[serde(rename = "Last Edit")]
+    pub last_edit: Option<String>,
+    #[serde(rename = "Import Filename")]
+    pub import_filename: Option<String>,
+}
+
+impl Waypoint {
+    /// Adds `tag` to the comma-separated tags unless it is already there.
+    pub fn add_tag(&mut self, tag: &str) {
+        self.tags = Some(match self.tags.take() {
+            Some(tags) if tags.split(',').any(|t| t == tag) => tags,
+            Some(tags) if !tags.is_empty() => format!("{},{}", tags, tag),
+            _ => tag.to_owned(),
+        });
+    }
+
+    pub fn remove_tag(&mut self, tag: &str) {
+        let tags = self.tags.take().unwrap_or_default();
+        let kept: Vec<&str> = tags.split(',').filter(|t| !t.is_empty() && *t != tag).collect();
+        self.tags = (!kept.is_empty()).then(|| kept.join(","));
+    }
+
+    pub fn has_tag(&self, tag: &str) -> bool {
+        self.tags.as_deref().is_some_and(|tags| tags.split(',').any(|t| t == tag))
+    }
+
+    /// Makes name and ident plain ASCII. The original name is kept at the
+    /// start of the description when it changed.
+    pub fn transliterate(&mut self) {
+        let name = matching::to_ascii(&self.name);
+        if name != self.name {
+            let original = std::mem::replace(&mut self.name, name);
+            self.description = Some(match self.description.take() {
+                Some(d) if !d.is_empty() => format!("{}; {}", original, d),
+                _ => original,
+            });
+        }
+        self.ident = matching::to_ascii(&self.ident);
+    }
+
+    /// A waypoint without validation or a region; see [`Waypoint::builder`]
+    /// for source data and points derived from it.
+    pub fn new(waypoint_type: &str, name: &str, ident: &str, latitude: f32, longitude: f32, elevation: Option<f32>) -> Self {
+        Waypoint {
+            waypoint_type: waypoint_type.to_owned(),
+            name: name.to_owned(),
+            ident: ident.to_owned(),
+            latitude,
+            longitude,
+            elevation,
+            magnetic_declination: None,
+            tags: None,
+            description: None,
+            region: None,
+            visible_from: None,
+            last_edit: None,
+            import_filename: None,
+        }
+    }
+}
+
+/// Reads a userpoints CSV with a header row.
+pub fn read_dataset(path: &str, delimiter: u8) -> Result<Vec<Waypoint>, Error> {
+    parse_dataset(input::open(path)?, delimiter, true)
+}
+
+/// Reads userpoints CSV from `input`; without headers the columns are taken
+/// in the default order.
+pub fn parse_dataset(input: impl Read, delimiter: u8, has_headers: bool) -> Result<Vec<Waypoint>, Error> {
+    let mut reader = ReaderBuilder::new().delimiter(delimiter).has_headers(has_headers).from_reader(input);
+    let waypoints = reader.deserialize().collect::<Result<_, _>>()?;
+    Ok(waypoints)
+}
+
+/// The `Airfield` elements of a SkyDemon XML file as waypoints.
+pub fn read_airfields(path: &str) -> Result<Vec<Waypoint>, Error> {
+    AirfieldReader::new(input::open(path)?).with_source(path).collect()
+}
+
+/// Loads the airfields of a SkyDemon XML file, or a userpoints CSV as is.
+pub fn read_any(path: &str, delimiter: u8) -> Result<Vec<Waypoint>, Error> {
+    if !path.to_lowercase().ends_with(".xml") {
+        return read_dataset(path, delimiter);
+    }
+    read_airfields(path)
+}
+
+/// Like [`read_any`], but yields the records one at a time instead of
+/// loading the file.
+pub fn stream_any(path: &str, delimiter: u8) -> Result<Box<dyn Iterator<Item = Result<Waypoint, Error>>>, Error> {
+    let input = input::open(path)?;
+    if !path.to_lowercase().ends_with(".xml") {
+        let reader = ReaderBuilder::new().delimiter(delimiter).from_reader(input);
+        return Ok(Box::new(reader.into_deserialize().map(|w| w.map_err(Error::from))));
+    }
+    Ok(Box::new(AirfieldReader::new(input).with_source(path)))
+}
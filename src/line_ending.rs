@@ -0,0 +1,72 @@
+use clap::ValueEnum;
+use std::borrow::Cow;
+use std::fs::File;
+use std::io::{self, BufWriter, Write};
+
+/// Line terminator used by every generated text file.
+#[derive(Debug, Clone, Copy, Default, PartialEq, ValueEnum)]
+pub enum LineEnding {
+    #[default]
+    Lf,
+    /// Required by some avionics SD-card importers.
+    Crlf,
+}
+
+impl LineEnding {
+    pub fn terminator(self) -> csv::Terminator {
+        match self {
+            LineEnding::Lf => csv::Terminator::Any(b'\n'),
+            LineEnding::Crlf => csv::Terminator::CRLF,
+        }
+    }
+
+    /// `text`, written with `\n`, converted to this line ending.
+    pub fn apply(self, text: &str) -> Cow<'_, str> {
+        match self {
+            LineEnding::Lf => Cow::Borrowed(text),
+            LineEnding::Crlf => Cow::Owned(text.replace('\n', "\r\n")),
+        }
+    }
+
+    pub fn create(self, path: &str) -> io::Result<TextFile> {
+        Ok(self.wrap(BufWriter::new(File::create(path)?)))
+    }
+
+    pub fn wrap<W: Write>(self, out: W) -> TextFile<W> {
+        TextFile { out, ending: self }
+    }
+}
+
+/// A buffered file, or any other writer, that rewrites each `\n` written
+/// to it.
+pub struct TextFile<W = BufWriter<File>> {
+    out: W,
+    ending: LineEnding,
+}
+
+impl<W> TextFile<W> {
+    pub fn into_inner(self) -> W {
+        self.out
+    }
+}
+
+impl<W: Write> Write for TextFile<W> {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        if self.ending == LineEnding::Lf {
+            return self.out.write(buf);
+        }
+        let mut lines = buf.split(|&b| b == b'\n');
+        if let Some(first) = lines.next() {
+            self.out.write_all(first)?;
+        }
+        for line in lines {
+            self.out.write_all(b"\r\n")?;
+            self.out.write_all(line)?;
+        }
+        Ok(buf.len())
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        self.out.flush()
+    }
+}
@@ -0,0 +1,34 @@
+//! Diagnostic logs on standard error: library notes and warnings, such as
+//! download retries, by default, `-v` each stage and decision as well and
+//! `-vv` every record.
+
+use clap::ValueEnum;
+use std::io::{self, IsTerminal};
+use tracing::Level;
+use tracing_subscriber::filter::Targets;
+use tracing_subscriber::prelude::*;
+
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, ValueEnum)]
+pub enum LogFormat {
+    /// One line per event for reading
+    #[default]
+    Text,
+    /// One JSON object per event for log tooling
+    Json,
+}
+
+/// Installs the subscriber for `verbose` `-v` flags. Only this crate's
+/// events are shown; warnings about the data go through the summary.
+pub fn init(verbose: u8, format: LogFormat) {
+    let level = match verbose {
+        0 => Level::INFO,
+        1 => Level::DEBUG,
+        _ => Level::TRACE,
+    };
+    let filter = Targets::new().with_target("polish_airports", level);
+    let layer = tracing_subscriber::fmt::layer().with_writer(io::stderr).with_ansi(io::stderr().is_terminal());
+    match format {
+        LogFormat::Text => tracing_subscriber::registry().with(layer.with_filter(filter)).init(),
+        LogFormat::Json => tracing_subscriber::registry().with(layer.json().with_filter(filter)).init(),
+    }
+}
@@ -1,156 +1,1123 @@
-use csv::Writer;
-use roxmltree::Document;
-use serde::{Deserialize, Serialize};
-use std::{error::Error, fs, str::FromStr};
-
-#[derive(Debug, PartialEq)]
-struct Coordinate {
-    degrees: i32,
-    minutes: u32,
-    seconds: f32,
-}
-
-#[derive(Debug, PartialEq, Eq)]
-struct ParseCoordinateError;
-#[derive(Debug, PartialEq, Eq)]
-struct ParsePositionError;
-
-impl FromStr for Coordinate {
-    type Err = ParseCoordinateError;
-
-    fn from_str(s: &str) -> Result<Self, Self::Err> {
-        let nth = s.chars().nth(0).unwrap_or('N');
-        let sign = match nth {
-            'N' => 1,
-            'E' => 1,
-            'W' => -1,
-            'S' => -1,
-            _ => return Err(ParseCoordinateError),
-        };
-        let offset = match nth {
-            'E' => 1,
-            'W' => 1,
-            _ => 0,
+mod logging;
+mod query;
+mod serve;
+mod update;
+
+use logging::LogFormat;
+use clap::builder::{PossibleValue, PossibleValuesParser};
+use clap::{Parser, Subcommand};
+use polish_airports::circuit::PatternSide;
+use polish_airports::conflict::ConflictResolver;
+use polish_airports::dedupe::DedupeMode;
+use polish_airports::dem::Dem;
+use polish_airports::download::{Backoff, Downloads, RateLimit};
+use polish_airports::elevation::ElevationUnit;
+use polish_airports::encoding::Encoding;
+use polish_airports::format::{self, Limits, OutputFormat, ReadOptions, WriteOptions};
+use polish_airports::idents::DuplicatePolicy;
+use polish_airports::lang::Lang;
+use polish_airports::line_ending::LineEnding;
+use polish_airports::mapping::Mapping;
+use polish_airports::matching::SiteMatcher;
+use polish_airports::notam::{self, FaaNotams};
+use polish_airports::openaip::{self, OpenAip};
+use polish_airports::osm::{self, Overpass, Preset};
+use polish_airports::pipeline::{self, Conversion, OutputStyle, AIP_VFR_CHART_URL};
+use polish_airports::region::RegionRule;
+use polish_airports::split::SplitKey;
+use polish_airports::summary::{self, Summary};
+use polish_airports::verify::ReferenceFormat;
+use polish_airports::{
+    aip, backup, columns, diff, ead, filter, fpl, gpx, merge, metar, roundtrip, stats, truncate, validate, verify,
+};
+use polish_airports::{read_any, read_dataset, Waypoint};
+use std::collections::HashMap;
+use std::io::{self, IsTerminal};
+use std::path::{Path, PathBuf};
+use std::time::Duration;
+use std::{error::Error, fs, num::{NonZeroU32, NonZeroUsize}, thread};
+
+#[derive(Parser, Debug)]
+#[command(about = "Converts SkyDemon airfield XML into Little Navmap userpoints")]
+struct Args {
+    #[command(subcommand)]
+    command: Option<Command>,
+    /// Field separator of CSV files read and written, e.g. `;` for Polish Excel or `tab`
+    #[arg(long, global = true, default_value = ",", value_parser = parse_delimiter)]
+    delimiter: u8,
+    /// Character encoding of the CSV output
+    #[arg(long, global = true, value_enum, default_value_t)]
+    encoding: Encoding,
+    /// Line endings of every generated text file
+    #[arg(long, global = true, value_enum, default_value_t)]
+    line_endings: LineEnding,
+    /// Leave out the CSV header row
+    #[arg(long, global = true, conflicts_with = "header_line")]
+    no_header: bool,
+    /// Exact first line of the CSV output, written instead of the column header
+    #[arg(long, global = true, value_name = "LINE")]
+    header_line: Option<String>,
+    /// Write CSV numbers with a decimal comma, as Polish-locale spreadsheets expect
+    #[arg(long, global = true)]
+    decimal_comma: bool,
+    /// Round fractional CSV numbers to this many decimal places
+    #[arg(long, global = true, value_name = "PLACES")]
+    precision: Option<u32>,
+    /// Never draw progress bars, even on a terminal
+    #[arg(long, global = true)]
+    no_progress: bool,
+    /// Directory for downloaded data, instead of the user cache directory
+    #[arg(long, global = true, value_name = "DIR")]
+    download_dir: Option<PathBuf>,
+    /// Download again when the cached copy is older than this, e.g. `30m`, `24h` or `7d`
+    #[arg(long, global = true, value_name = "AGE", default_value = "24h", value_parser = parse_duration)]
+    download_ttl: Duration,
+    /// Download remote data again even when a fresh copy is cached
+    #[arg(long, global = true)]
+    refresh: bool,
+    /// Never access the network; use cached downloads of any age and fail
+    /// when one is missing
+    #[arg(long, global = true, conflicts_with = "refresh")]
+    offline: bool,
+    /// Proxy for downloads, e.g. `http://proxy.example:8080`; without it
+    /// HTTPS_PROXY, HTTP_PROXY, ALL_PROXY and NO_PROXY are honoured
+    #[arg(long, global = true, value_name = "URL")]
+    proxy: Option<String>,
+    /// How many timestamped backups to keep of each userpoints CSV a run
+    /// overwrites; 0 keeps none
+    #[arg(long, global = true, default_value_t = 5, value_name = "N")]
+    backups: usize,
+    /// Retry failed downloads this many times
+    #[arg(long, global = true, default_value_t = 3, value_name = "N")]
+    retries: u32,
+    /// Wait before the first retry, doubling for each further one
+    #[arg(long, global = true, default_value = "1s", value_parser = parse_duration, value_name = "DURATION")]
+    retry_delay: Duration,
+    /// Tag airfields with ICAO idents that have current NOTAMs, and mark
+    /// closed ones, using the FAA NOTAM API
+    #[arg(long)]
+    notams: bool,
+    /// FAA NOTAM API client id
+    #[arg(long, env = "FAA_NOTAM_CLIENT_ID", hide_env_values = true, requires = "notams")]
+    notam_client_id: Option<String>,
+    /// FAA NOTAM API client secret
+    #[arg(long, env = "FAA_NOTAM_CLIENT_SECRET", hide_env_values = true, requires = "notams")]
+    notam_client_secret: Option<String>,
+    /// Where to query NOTAMs
+    #[arg(long, default_value = notam::FAA_NOTAM_URL, value_name = "URL", requires = "notams")]
+    notam_url: String,
+    /// NOTAM queries per second
+    #[arg(long, default_value_t = 2.0, value_name = "N", requires = "notams")]
+    notam_rate: f64,
+    /// Add the nearest METAR station and its distance to airfield descriptions
+    #[arg(long)]
+    nearest_metar: bool,
+    /// Reporting stations, aviationweather.gov `stationinfo` JSON or
+    /// userpoints CSV, as a file or URL
+    #[arg(long, default_value = metar::STATIONS_URL, value_name = "SOURCE", requires = "nearest_metar")]
+    metar_stations: String,
+    /// Only name stations at most this far away (NM)
+    #[arg(long, default_value_t = 30.0, value_name = "NM", requires = "nearest_metar")]
+    metar_radius: f64,
+    /// Trim and upper-case idents claiming to be Polish ICAO codes and tag
+    /// malformed ones such as EP12 as pseudo-icao
+    #[arg(long)]
+    check_icao: bool,
+    /// Fail instead of warning when a Polish record lands outside Poland
+    #[arg(long)]
+    strict_geo: bool,
+    /// Tag glider sites, their winch launches and aerotows from the source
+    /// data, and note them in descriptions
+    #[arg(long)]
+    glider: bool,
+    /// CSV with Ident,Winch,Aerotow,Runway columns for glider sites the
+    /// source data says nothing about; implies --glider
+    #[arg(long, value_name = "FILE")]
+    glider_sites: Option<String>,
+    /// Tag fields likely used by ultralights, by words in their names,
+    /// descriptions and source files
+    #[arg(long)]
+    classify_ultralight: bool,
+    /// Rules for --classify-ultralight in the --tag-rules format, instead of
+    /// the built-in rules/ultralight.toml; implies --classify-ultralight
+    #[arg(long, value_name = "PATH")]
+    ultralight_rules: Option<String>,
+    /// Don't print what the run did at the end
+    #[arg(long)]
+    no_summary: bool,
+    /// Also write the end-of-run summary to this file
+    #[arg(long, value_name = "PATH")]
+    summary: Option<String>,
+    /// Write a JSON report of the run for CI: the summary, every warning
+    /// with the record it concerns, the outcome and the arguments used
+    #[arg(long, value_name = "PATH")]
+    report: Option<String>,
+    /// Print a rough map of the converted records within Poland's outline,
+    /// listing any that fall outside it
+    #[arg(long)]
+    preview: bool,
+    /// Ask on the terminal about records without an elevation, suggesting
+    /// one from `--dem`, and about positions outside Poland that make sense
+    /// read another way
+    #[arg(long)]
+    ask: bool,
+    /// Directory of SRTM .hgt tiles to suggest elevations from with `--ask`
+    #[arg(long, value_name = "DIR", requires = "ask")]
+    dem: Option<PathBuf>,
+    /// Fill in missing elevations from a directory of SRTM .hgt tiles
+    #[arg(long, value_name = "DIR")]
+    fill_elevations: Option<PathBuf>,
+    /// Before writing, list the records with their validation warnings in
+    /// the terminal to exclude or edit them
+    #[arg(long)]
+    review: bool,
+    /// Write a JSON Lines log of every record as parsed and of every change
+    /// each step made to it: enrichment, truncation, dedupe and filtering
+    #[arg(long, value_name = "PATH")]
+    audit: Option<String>,
+    /// Report how long each stage took and the records per second at the end
+    #[arg(long)]
+    stats_timing: bool,
+    /// Log each stage and decision; twice to log every record too
+    #[arg(short, long, global = true, action = clap::ArgAction::Count)]
+    verbose: u8,
+    /// How `-v` logs are written
+    #[arg(long, global = true, value_enum, default_value_t)]
+    log_format: LogFormat,
+    /// Input file to read, or an http(s) URL to download it from
+    #[arg(short, long, default_value = "skydemon_PL_missing.airfields.xml")]
+    input: String,
+    /// Kind of data the input file holds
+    #[arg(long, default_value = "skydemon", value_parser = input_formats())]
+    input_format: String,
+    /// Userpoints CSV to write
+    #[arg(short, long, default_value = "userpoints.csv")]
+    output: String,
+    /// Also emit a waypoint at each runway threshold, e.g. `EPXX27`
+    #[arg(long)]
+    runway_thresholds: bool,
+    /// Emit fixes on each runway's extended centerline at these distances
+    /// (NM), named in tenths of a mile: 2.5 NM out from EPXX27 is EPXX27F25
+    #[arg(long, value_delimiter = ',', value_name = "NM", value_parser = parse_distance)]
+    centerline_fixes: Vec<f64>,
+    /// Emit overhead, downwind and base join points for each runway
+    #[arg(long)]
+    circuit_points: bool,
+    /// Distance of the downwind leg from the runway centerline (NM)
+    #[arg(long, default_value_t = 1.0, value_name = "NM")]
+    circuit_offset: f64,
+    /// Side of the runway the circuit is flown on
+    #[arg(long, value_enum, default_value_t = PatternSide::Left)]
+    circuit_side: PatternSide,
+    /// Language of generated description text: surfaces and the glider,
+    /// METAR and NOTAM notes
+    #[arg(long, value_enum, default_value_t)]
+    lang: Lang,
+    /// Put a link to the AIP VFR aerodrome chart into the description of airfields with an ICAO code
+    #[arg(long)]
+    aip_links: bool,
+    /// Chart URL, with `{icao}` replaced by the aerodrome's code
+    #[arg(long, default_value = AIP_VFR_CHART_URL, value_name = "TEMPLATE")]
+    aip_url_template: String,
+    /// Waypoint fields to write to CSV and JSON, in order, e.g. `ident,name,lat,lon,elev`
+    #[arg(long, value_name = "LIST", default_value_t = columns::Columns::default())]
+    columns: columns::Columns,
+    /// TOML file renaming, adding and transforming output columns per target
+    #[arg(long, value_name = "PATH")]
+    mapping: Option<String>,
+    /// Also write the waypoints as a JSON array to this path
+    #[arg(long, value_name = "PATH")]
+    json: Option<String>,
+    /// Build airfield descriptions from a template such as
+    /// "Elev {elev_ft} ft, {surface}, {freq}"; see the template module for placeholders
+    #[arg(long, value_name = "TEMPLATE")]
+    description_template: Option<String>,
+    /// Also write a KML review layer to this path
+    #[arg(long, value_name = "PATH")]
+    kml: Option<String>,
+    /// Also write the waypoints in another format, as `FORMAT=PATH`
+    #[arg(long, value_name = "FORMAT=PATH", value_parser = parse_export)]
+    export: Vec<(String, String)>,
+    /// Check exported documents against their format's schema, such as
+    /// RFC 7946 for GeoJSON, and fail rather than write invalid ones
+    #[arg(long)]
+    validate_output: bool,
+    /// Existing userpoints whose idents generated reporting point idents must not reuse
+    #[arg(long, value_name = "CSV")]
+    airfield_dataset: Option<String>,
+    /// Draw each runway as a line in the KML layer
+    #[arg(long, requires = "kml")]
+    kml_runways: bool,
+    /// Treat waypoints of the same type closer than this as one site (metres)
+    #[arg(long, value_name = "M")]
+    dedupe_distance: Option<f64>,
+    /// Also treat nearby waypoints of the same type with names at least this
+    /// similar (Jaro-Winkler, 0-1, diacritics folded) as one site
+    #[arg(long, value_name = "SCORE")]
+    name_similarity: Option<f64>,
+    /// How far apart similarly named waypoints may be (metres)
+    #[arg(long, default_value_t = 5000.0, value_name = "M")]
+    name_radius: f64,
+    /// What to do with sites found by `--dedupe-distance` or `--name-similarity`
+    #[arg(long, value_enum, default_value_t = DedupeMode::Flag)]
+    dedupe_mode: DedupeMode,
+    /// TOML rules assigning tags to records matching filter expressions
+    #[arg(long, value_name = "PATH")]
+    tag_rules: Option<String>,
+    /// Only export records matching this expression, e.g.
+    /// "elevation > 500 && type == 'Airstrip' && name =~ 'ląd'"
+    #[arg(long, value_name = "EXPR", value_parser = str::parse::<filter::Filter>)]
+    filter: Option<filter::Filter>,
+    /// Only export these waypoint types, e.g. `airstrip,helipad`
+    #[arg(long, value_delimiter = ',', value_name = "TYPE")]
+    types: Vec<String>,
+    /// Set the ICAO region of every record to CODE, or with `ident` to the
+    /// region of its ident where that is an ICAO code
+    #[arg(long, value_name = "CODE|ident", value_parser = str::parse::<RegionRule>)]
+    assign_region: Option<RegionRule>,
+    /// Only export waypoints in these ICAO regions, e.g. `EP`
+    #[arg(long, value_delimiter = ',', value_name = "REGION")]
+    region: Vec<String>,
+    /// Only export waypoints whose ident starts with one of these, e.g. `EPK`
+    #[arg(long, value_delimiter = ',', value_name = "PREFIX")]
+    ident_prefix: Vec<String>,
+    /// Only export the N waypoints nearest to the `--near` point or route
+    #[arg(long, value_name = "N", requires = "near")]
+    nearest: Option<usize>,
+    /// Point as `LAT,LON`; repeat to describe a route
+    #[arg(long, value_name = "LAT,LON", value_parser = parse_lat_lon)]
+    near: Vec<(f64, f64)>,
+    /// Add only records not already in the output file (by ident, or by the
+    /// dedupe settings) instead of overwriting it
+    #[arg(long)]
+    append: bool,
+    /// Reuse per-airfield results stored in this file for records that have
+    /// not changed since the previous run
+    #[arg(long, value_name = "PATH")]
+    cache: Option<String>,
+    /// Transliterate names and idents to ASCII (Łódź → Lodz), keeping the
+    /// original name in the description
+    #[arg(long)]
+    ascii: bool,
+    /// Shorten idents in `--export` files to this many characters, keeping
+    /// them distinct, instead of to the format's own limit
+    #[arg(long, value_name = "N")]
+    max_ident_length: Option<NonZeroUsize>,
+    /// Shorten names in `--export` files to this many characters, keeping
+    /// them distinct, instead of to the format's own limit
+    #[arg(long, value_name = "N")]
+    max_name_length: Option<NonZeroUsize>,
+    /// How to handle different waypoints sharing an ident
+    #[arg(long, value_enum, default_value_t = DuplicatePolicy::Report)]
+    duplicate_idents: DuplicatePolicy,
+    /// Unit of the elevations in the userpoints CSV and JSON; source values
+    /// in metres or feet are converted. Exports keep their format's unit
+    #[arg(long, value_enum, default_value_t)]
+    elevation_unit: ElevationUnit,
+    /// Split the CSV output into numbered files of at most this many
+    /// waypoints, for devices that cap user waypoints per file
+    #[arg(long, value_name = "N", conflicts_with = "append")]
+    chunk_size: Option<NonZeroUsize>,
+    /// Write one CSV file per group next to the output instead, named after
+    /// the group (`helipads.csv`, `airstrips_malopolskie.csv`)
+    #[arg(long, value_enum, value_delimiter = ',', conflicts_with = "append")]
+    split_by: Vec<SplitKey>,
+    /// Also bundle every generated file and a manifest into this zip archive
+    #[arg(long, value_name = "ZIP")]
+    package: Option<String>,
+    /// Sign the package with this minisign secret key
+    #[arg(long, value_name = "KEY", requires = "package")]
+    sign_key: Option<String>,
+}
+
+impl Args {
+    fn downloads(&self) -> Result<Downloads, Box<dyn Error>> {
+        let downloads = Downloads::new(self.download_dir.clone(), self.download_ttl, self.refresh)
+            .offline(self.offline)
+            .backoff(self.backoff());
+        Ok(match &self.proxy {
+            Some(proxy) => downloads.proxy(proxy)?,
+            None => downloads,
+        })
+    }
+
+    fn backoff(&self) -> Backoff {
+        Backoff { retries: self.retries, delay: self.retry_delay, ..Default::default() }
+    }
+
+    fn notam_source(&self) -> Result<FaaNotams, Box<dyn Error>> {
+        if self.offline {
+            return Err("--notams needs network access, which --offline disables".into());
+        }
+        let (Some(id), Some(secret)) = (&self.notam_client_id, &self.notam_client_secret) else {
+            return Err("--notams needs FAA_NOTAM_CLIENT_ID and FAA_NOTAM_CLIENT_SECRET".into());
         };
-        let deg = &s[1..3 + offset];
-        let deg = sign * deg.parse::<i32>().map_err(|_| ParseCoordinateError)?;
-        let min = &s[3 + offset..5 + offset];
-        let min = min.parse::<u32>().map_err(|_| ParseCoordinateError)?;
-        let sec = &s[5 + offset..];
-        let sec = sec.parse::<f32>().map_err(|_| ParseCoordinateError)?;
-        Ok(Coordinate {
-            degrees: deg,
-            minutes: min,
-            seconds: sec,
+        Ok(FaaNotams::new(id, secret)
+            .url(&self.notam_url)
+            .agent(self.downloads()?.agent())
+            .backoff(self.backoff())
+            .rate_limit(RateLimit::new(Some(self.notam_rate), Some(4))))
+    }
+
+    fn site_matcher(&self) -> SiteMatcher {
+        SiteMatcher {
+            distance: self.dedupe_distance,
+            name_similarity: self.name_similarity,
+            name_radius: self.name_radius,
+        }
+    }
+
+    /// How the CSV output and every other generated file are written.
+    fn style(&self) -> OutputStyle {
+        OutputStyle {
+            delimiter: self.delimiter,
+            encoding: self.encoding,
+            line_ending: self.line_endings,
+            header: !self.no_header,
+            header_line: self.header_line.clone(),
+            numbers: columns::NumberFormat {
+                decimal_comma: self.decimal_comma,
+                precision: self.precision,
+            },
+            backups: self.backups,
+        }
+    }
+
+    /// The conversion a run without a subcommand performs.
+    fn conversion(&self) -> Result<Conversion, Box<dyn Error>> {
+        Ok(Conversion {
+            input: self.input.clone(),
+            input_format: self.input_format.clone(),
+            output: self.output.clone(),
+            style: self.style(),
+            downloads: self.downloads()?,
+            progress: !self.no_progress,
+            cache: self.cache.clone(),
+            airfield_dataset: self.airfield_dataset.clone(),
+            runway_thresholds: self.runway_thresholds,
+            centerline_fixes: self.centerline_fixes.clone(),
+            circuit_points: self.circuit_points,
+            circuit_offset: self.circuit_offset,
+            circuit_side: self.circuit_side,
+            description_template: self.description_template.clone(),
+            aip_links: self.aip_links.then(|| self.aip_url_template.clone()),
+            lang: self.lang,
+            ask: self.ask,
+            dem: self.dem.clone(),
+            strict_geo: self.strict_geo,
+            check_icao: self.check_icao,
+            glider: self.glider || self.glider_sites.is_some(),
+            glider_sites: self.glider_sites.clone(),
+            ultralight: self.classify_ultralight || self.ultralight_rules.is_some(),
+            ultralight_rules: self.ultralight_rules.clone(),
+            tag_rules: self.tag_rules.clone(),
+            filter: self.filter.clone(),
+            types: self.types.clone(),
+            assign_region: self.assign_region.clone(),
+            region: self.region.clone(),
+            ident_prefix: self.ident_prefix.clone(),
+            matcher: self.site_matcher(),
+            dedupe_mode: self.dedupe_mode,
+            nearest: self.nearest,
+            near: self.near.clone(),
+            append: self.append,
+            fill_elevations: self.fill_elevations.clone(),
+            notams: self.notams.then(|| self.notam_source()).transpose()?,
+            metar_stations: self.nearest_metar.then(|| self.metar_stations.clone()),
+            metar_radius: self.metar_radius,
+            ascii: self.ascii,
+            duplicate_idents: self.duplicate_idents,
+            review: self.review,
+            audit: self.audit.clone(),
+            preview: self.preview,
+            columns: self.columns.clone(),
+            mapping: match &self.mapping {
+                Some(path) => Mapping::load(path)?,
+                None => Mapping::default(),
+            },
+            elevation_unit: self.elevation_unit,
+            chunk_size: self.chunk_size,
+            split_by: self.split_by.clone(),
+            json: self.json.clone(),
+            kml: self.kml.clone(),
+            kml_runways: self.kml_runways,
+            export: self.export.clone(),
+            validate_output: self.validate_output,
+            limits: Limits { ident: self.max_ident_length, name: self.max_name_length },
+            package: self.package.clone(),
+            sign_key: self.sign_key.clone(),
+            timing: self.stats_timing,
         })
     }
+
+    /// Prints the end-of-run summary and writes it to `--summary`, as asked.
+    fn report_summary(&self, summary: &Summary) -> Result<(), Box<dyn Error>> {
+        let text = summary.text();
+        if !self.no_summary {
+            eprint!("{}", text);
+        }
+        if let Some(path) = &self.summary {
+            fs::write(path, self.line_endings.apply(&text).as_ref())?;
+        }
+        Ok(())
+    }
 }
 
-impl Coordinate {
-    fn to_decimal_degrees(&self) -> f32 {
-        let minutes: f32 = self.minutes as f32 / 60.;
-        let seconds = self.seconds / 3600.;
-        let degrees = self.degrees as f32;
-        degrees + minutes + seconds
-    }
-}
-
-#[derive(Debug, PartialEq)]
-struct Position {
-    lat: Coordinate,
-    lon: Coordinate,
-}
-
-impl FromStr for Position {
-    type Err = ParsePositionError;
-
-    fn from_str(s: &str) -> Result<Self, Self::Err> {
-        let (lat, long): (&str, &str) = s.split_once(" ").unwrap();
-        let lat = Coordinate::from_str(lat).unwrap();
-        let lon = Coordinate::from_str(long).unwrap();
-        Ok(Position { lat, lon })
-    }
-}
-
-#[derive(Serialize, Deserialize, Debug)]
-struct Waypoint {
-    #[serde(rename = "Type")]
-    waypoint_type: String,
-    #[serde(rename = "Name")]
-    name: String,
-    #[serde(rename = "Ident")]
-    ident: String,
-    #[serde(rename = "Latitude")]
-    latitude: f32,
-    #[serde(rename = "Longitude")]
-    longitude: f32,
-    #[serde(rename = "Elevation")]
-    elevation: Option<f32>,
-    #[serde(rename = "Magnetic Declination")]
-    magnetic_declination: Option<f32>,
-    #[serde(rename = "Tags")]
-    tags: Option<String>,
-    #[serde(rename = "Description")]
-    description: Option<String>,
-    #[serde(rename = "Region")]
-    region: Option<String>,
-    #[serde(rename = "Visible From")]
-    visible_from: Option<i32>,
-    #[serde(rename = "Last Edit")]
-    last_edit: Option<String>,
-    #[serde(rename = "Import Filename")]
-    import_filename: Option<String>,
-}
-
-impl Waypoint {
-    fn from_position(p: &Position, name: &str, elevation: Option<f32>) -> Result<Self, Box<dyn Error>> {
-        let lat = p.lat.to_decimal_degrees();
-        let lon = p.lon.to_decimal_degrees();
-        Ok(Waypoint {
-            waypoint_type: "Airstrip".to_owned(),
-            name: name.to_owned(),
-            ident: name.to_owned(),
-            latitude: lat,
-            longitude: lon,
-            elevation,
-            magnetic_declination: None,
-            tags: None,
-            description: None,
-            region: Some("EP".to_owned()),
-            visible_from: None,
-            last_edit: None,
-            import_filename: Some("skydemon_PL_missing.airfields.xml".to_owned()),
-        })
+fn input_formats() -> PossibleValuesParser {
+    PossibleValuesParser::new(
+        format::inputs()
+            .iter()
+            .map(|f| PossibleValue::new(f.name()).help(f.description())),
+    )
+}
+
+#[derive(Subcommand, Debug)]
+enum Command {
+    /// Build a GPX route and track through the given idents
+    Route {
+        /// Idents to visit, in order
+        #[arg(required = true, num_args = 2..)]
+        idents: Vec<String>,
+        /// Converted userpoints CSV to look the idents up in
+        #[arg(short, long, default_value = "userpoints.csv")]
+        dataset: String,
+        /// GPX file to write
+        #[arg(short, long, default_value = "route.gpx")]
+        output: String,
+    },
+    /// Build a Garmin FPL flight plan through the given idents
+    Plan {
+        /// Idents to visit, in order
+        #[arg(required = true, num_args = 2..)]
+        idents: Vec<String>,
+        /// Converted userpoints CSV to look the idents up in
+        #[arg(short, long, default_value = "userpoints.csv")]
+        dataset: String,
+        /// FPL file to write
+        #[arg(short, long, default_value = "plan.fpl")]
+        output: String,
+    },
+    /// Combine several datasets field by field according to source priority
+    Merge {
+        /// Named source as `NAME=PATH`, either SkyDemon XML or userpoints CSV
+        #[arg(short, long = "source", required = true, value_parser = parse_source)]
+        sources: Vec<(String, String)>,
+        /// TOML file with per-field source precedence
+        #[arg(short, long)]
+        config: Option<String>,
+        /// Also treat records of the same type closer than this as one site (metres)
+        #[arg(long, value_name = "M")]
+        proximity: Option<f64>,
+        /// Also treat nearby records with names at least this similar as one site
+        #[arg(long, value_name = "SCORE")]
+        name_similarity: Option<f64>,
+        /// How far apart similarly named records may be (metres)
+        #[arg(long, default_value_t = 5000.0, value_name = "M")]
+        name_radius: f64,
+        /// Merged userpoints CSV to write
+        #[arg(short, long, default_value = "userpoints.csv")]
+        output: String,
+        /// Positions of one site further apart than this are a conflict (metres)
+        #[arg(long, default_value_t = 500.0, value_name = "M")]
+        conflict_distance: f64,
+        /// Ask which source to trust for each conflict
+        #[arg(long)]
+        interactive: bool,
+        /// TOML file of earlier conflict decisions to apply
+        #[arg(long, value_name = "PATH")]
+        resolve_file: Option<String>,
+        /// Write all conflict decisions, including new interactive ones, here
+        #[arg(long, value_name = "PATH")]
+        save_decisions: Option<String>,
+        /// Add a `Source: <field>` column per merged field to the CSV
+        #[arg(long)]
+        provenance_columns: bool,
+        /// Write per-record field sources to this JSON file
+        #[arg(long, value_name = "PATH")]
+        provenance_json: Option<String>,
+        /// Keep records in a temporary database in this directory instead of
+        /// memory, for very large merges
+        #[arg(long, value_name = "DIR")]
+        spill_dir: Option<PathBuf>,
+    },
+    /// Report airfields added, removed and changed between two datasets;
+    /// exits with status 1 when they differ
+    Diff {
+        /// Previous release, SkyDemon XML or userpoints CSV
+        old: String,
+        /// New release, SkyDemon XML or userpoints CSV
+        new: String,
+        /// Report position changes larger than this (metres)
+        #[arg(long, default_value_t = 50.0, value_name = "M")]
+        move_threshold: f64,
+        /// How to show the differences
+        #[arg(long, value_enum, default_value_t = diff::DiffFormat::Text)]
+        format: diff::DiffFormat,
+    },
+    /// Convert a dataset to a format and back and report what changed;
+    /// exits with status 1 when anything did
+    Roundtrip {
+        /// Dataset to convert
+        dataset: String,
+        /// Kind of data the dataset holds; by default SkyDemon XML or
+        /// userpoints CSV by extension
+        #[arg(long, value_parser = input_formats())]
+        from: Option<String>,
+        /// Format to go through, one that can be both written and read
+        #[arg(long)]
+        via: String,
+        /// Report position changes larger than this (metres)
+        #[arg(long, default_value_t = 1.0, value_name = "M")]
+        max_distance: f64,
+        /// Report elevation changes larger than this (feet)
+        #[arg(long, default_value_t = 0.5, value_name = "FT")]
+        max_elevation: f32,
+    },
+    /// Compare a dataset against an authoritative source and report
+    /// discrepancies; exits with status 1 when any are found
+    Verify {
+        /// Dataset to check, SkyDemon XML or userpoints CSV
+        dataset: String,
+        /// Authoritative data to compare against, a file or an http(s) URL
+        #[arg(short, long)]
+        reference: String,
+        #[arg(long, value_enum, default_value_t = ReferenceFormat::Ourairports)]
+        reference_format: ReferenceFormat,
+        /// Report positions further than this from the reference (metres)
+        #[arg(long, default_value_t = 200.0, value_name = "M")]
+        max_distance: f64,
+        /// Report elevations differing more than this (feet)
+        #[arg(long, default_value_t = 50.0, value_name = "FT")]
+        max_elevation: f32,
+        /// Report names less similar than this (0-1)
+        #[arg(long, default_value_t = 0.85, value_name = "SCORE")]
+        min_name_similarity: f64,
+        /// Match records without a common ident to the nearest reference within this (metres)
+        #[arg(long, default_value_t = 2000.0, value_name = "M")]
+        search_radius: f64,
+        /// Also report reference records missing from the dataset, to
+        /// reconcile against the official data
+        #[arg(long)]
+        missing: bool,
+        /// Write the report here instead of standard output
+        #[arg(long)]
+        report: Option<String>,
+    },
+    /// Load datasets and answer queries typed one per line, such as
+    /// `near EPKK 30nm`, `find name~zamość` or `count type=helipad`
+    Query {
+        /// Datasets to load first; `load` adds more
+        sources: Vec<String>,
+        /// Kind of data the datasets hold; by default SkyDemon XML or
+        /// userpoints CSV by extension
+        #[arg(long, value_parser = input_formats())]
+        format: Option<String>,
+    },
+    /// Report a dataset's counts by type and region, elevation range and
+    /// histogram, geographic extent and how often each field is filled
+    Stats {
+        /// Dataset to describe
+        dataset: String,
+        /// Kind of data the dataset holds; by default SkyDemon XML or
+        /// userpoints CSV by extension
+        #[arg(long, value_parser = input_formats())]
+        format: Option<String>,
+        /// Width of the elevation histogram's bands (feet)
+        #[arg(long, default_value = "250", value_name = "FT")]
+        bucket: NonZeroU32,
+        /// Write the report here instead of standard output
+        #[arg(long)]
+        report: Option<String>,
+    },
+    /// List the input and output formats and which fields each keeps
+    ListFormats,
+    /// Check a dataset against validation rules and report per-rule
+    /// results; exits with status 1 when any rule fails
+    Validate {
+        /// Dataset to check
+        dataset: String,
+        /// Kind of data the dataset holds; by default SkyDemon XML or
+        /// userpoints CSV by extension
+        #[arg(long, value_parser = input_formats())]
+        format: Option<String>,
+        /// TOML file of rule settings; rules it leaves out keep their defaults
+        #[arg(long, value_name = "PATH")]
+        rules: Option<String>,
+        /// Directory of SRTM .hgt tiles to check elevations against
+        #[arg(long, value_name = "DIR")]
+        dem: Option<PathBuf>,
+        /// Write the report here instead of standard output
+        #[arg(long)]
+        report: Option<String>,
+    },
+    /// Offer the converters over HTTP: `GET /formats` lists the formats and
+    /// `POST /convert?from=skydemon&to=gpx` converts the uploaded file
+    Serve {
+        /// Address to listen on
+        #[arg(long, default_value = "127.0.0.1:8080")]
+        bind: String,
+        /// Largest accepted upload (MiB)
+        #[arg(long, default_value_t = 64, value_name = "MIB")]
+        max_upload: u64,
+        /// Requests handled at the same time; defaults to the number of CPUs
+        #[arg(long, value_name = "N")]
+        workers: Option<NonZeroUsize>,
+    },
+    /// Replace this binary with the build for this platform from the latest
+    /// release, after checking it against the release's checksum
+    SelfUpdate {
+        /// Only report whether a newer release is out
+        #[arg(long)]
+        check: bool,
+        /// Also require the release's minisign signature by this public key
+        #[arg(long, value_name = "PATH")]
+        public_key: Option<PathBuf>,
+        /// Address of the latest release in the GitHub releases API
+        #[arg(long, default_value = update::RELEASES_URL)]
+        url: String,
+    },
+    /// Put back a userpoints CSV as it was before a run overwrote it, from
+    /// the backups kept next to it
+    Restore {
+        /// File to restore
+        #[arg(default_value = "userpoints.csv")]
+        output: String,
+        /// Backup to restore, by its timestamp; by default the newest
+        #[arg(long, value_name = "TIMESTAMP")]
+        from: Option<String>,
+        /// Only list the backups
+        #[arg(long, conflicts_with = "from")]
+        list: bool,
+    },
+    /// Download a published dataset and save it as userpoints CSV, ready to
+    /// use as input or merge source
+    Fetch {
+        #[command(subcommand)]
+        dataset: Dataset,
+    },
+}
+
+#[derive(Subcommand, Debug)]
+enum Dataset {
+    /// Polish AIP aerodromes from the PANSA AIXM data set
+    Aip {
+        /// Where to download the data set from
+        #[arg(long, default_value = aip::AERODROMES_URL)]
+        url: String,
+        /// Userpoints CSV to write
+        #[arg(short, long, default_value = "aip.csv")]
+        output: String,
+    },
+    /// OurAirports airports with their frequencies
+    Ourairports {
+        /// Only airports in this ISO country
+        #[arg(long, default_value = "PL")]
+        country: String,
+        /// Where to download `airports.csv` from
+        #[arg(long, default_value = verify::OURAIRPORTS_URL)]
+        url: String,
+        /// Where to download `airport-frequencies.csv` from
+        #[arg(long, default_value = verify::OURAIRPORTS_FREQUENCIES_URL)]
+        frequencies_url: String,
+        /// Userpoints CSV to write
+        #[arg(short, long, default_value = "ourairports.csv")]
+        output: String,
+    },
+    /// Airports and heliports from the openAIP API
+    Openaip {
+        /// Only airports in this ISO country
+        #[arg(long, default_value = "PL")]
+        country: String,
+        /// openAIP API key
+        #[arg(long, env = "OPENAIP_API_KEY", hide_env_values = true)]
+        api_key: String,
+        /// Address of the airports API
+        #[arg(long, default_value = openaip::OPENAIP_URL)]
+        url: String,
+        /// Page requests per second
+        #[arg(long, default_value_t = 2.0, value_name = "N")]
+        rate: f64,
+        /// Page requests at once
+        #[arg(long, default_value_t = 2, value_name = "N")]
+        concurrency: usize,
+        /// Userpoints CSV to write
+        #[arg(short, long, default_value = "openaip.csv")]
+        output: String,
+    },
+    /// Helipads and glider sites from OpenStreetMap, with a preset Overpass query
+    Osm {
+        /// Which sites to fetch
+        #[arg(long, value_enum, default_value_t = Preset::All)]
+        preset: Preset,
+        /// Only sites in this ISO country
+        #[arg(long, default_value = "PL")]
+        country: String,
+        /// Address of the Overpass API
+        #[arg(long, default_value = osm::OVERPASS_URL)]
+        url: String,
+        /// Userpoints CSV to write
+        #[arg(short, long, default_value = "osm.csv")]
+        output: String,
+    },
+}
+
+impl Dataset {
+    fn output(&self) -> &str {
+        match self {
+            Dataset::Aip { output, .. }
+            | Dataset::Ourairports { output, .. }
+            | Dataset::Openaip { output, .. }
+            | Dataset::Osm { output, .. } => output,
+        }
+    }
+
+    /// Downloads the dataset and reads it, returning the main address and
+    /// the records.
+    fn fetch(&self, downloads: &Downloads, backoff: Backoff) -> Result<(&str, Vec<Waypoint>), Box<dyn Error>> {
+        let local = |url: &str| Ok::<_, Box<dyn Error>>(downloads.fetch(url)?.to_string_lossy().into_owned());
+        match self {
+            Dataset::Aip { url, .. } => Ok((url, aip::read_aixm(&local(url)?)?)),
+            Dataset::Ourairports { country, url, frequencies_url, .. } => {
+                let frequencies = verify::read_ourairports_frequencies(&local(frequencies_url)?)?;
+                Ok((url, verify::read_ourairports(&local(url)?, Some(country), &frequencies)?))
+            }
+            Dataset::Openaip { country, api_key, url, rate, concurrency, .. } => {
+                if downloads.is_offline() {
+                    return Err("fetch openaip needs network access, which --offline disables".into());
+                }
+                let api = OpenAip::new(api_key)
+                    .url(url)
+                    .agent(downloads.agent())
+                    .backoff(backoff)
+                    .rate_limit(RateLimit::new(Some(*rate), Some(*concurrency)));
+                Ok((url, api.airports(country)?))
+            }
+            Dataset::Osm { preset, country, url, .. } => {
+                if downloads.is_offline() {
+                    return Err("fetch osm needs network access, which --offline disables".into());
+                }
+                let overpass = Overpass::default().url(url).agent(downloads.agent()).backoff(backoff);
+                Ok((url, overpass.sites(*preset, country)?))
+            }
+        }
     }
 }
 
-fn main() -> Result<(), Box<dyn Error>> {
-    let binding = fs::read_to_string("skydemon_PL_missing.airfields.xml").unwrap();
-    let data = binding.as_str();
-    let doc = Document::parse(data)?;
-    let mut writer = Writer::from_path("userpoints.csv")?;
-    // let mut writer = Writer::from_writer(vec![]);
-    let airports = doc
-        .descendants()
-        .filter(|e| e.tag_name() == "Airfield".into());
-    for airport in airports {
-        let name = airport.attribute("Name").unwrap();
-        let position = airport.attribute("Position").unwrap();
-        let elevation = match airport.attribute("Elevation") {
-            Some(s) => {
-                let f: Option<f32> = match s.parse() {
-                    Ok(t) => Some(t),
-                    _ => None
-                };
-                f
-            }
-            _ => None
-        };
-        let position = Position::from_str(position).unwrap();
-        let waypoint = Waypoint::from_position(&position, name, elevation)?;
-        println!("{:?}", waypoint);
-        writer.serialize(waypoint)?;
+fn parse_delimiter(s: &str) -> Result<u8, String> {
+    match s {
+        "tab" | "\\t" | "\t" => Ok(b'\t'),
+        s if s.len() == 1 && s.is_ascii() => Ok(s.as_bytes()[0]),
+        _ => Err(format!("delimiter must be a single ASCII character or `tab`, got {:?}", s)),
+    }
+}
+
+/// A distance that is finite and greater than zero.
+fn parse_distance(s: &str) -> Result<f64, String> {
+    match s.trim().parse::<f64>() {
+        Ok(distance) if distance.is_finite() && distance > 0. => Ok(distance),
+        _ => Err(format!("expected a distance greater than zero, got {:?}", s)),
+    }
+}
+
+/// A duration such as `90s`, `30m`, `24h` or `7d`; bare numbers are hours.
+fn parse_duration(s: &str) -> Result<Duration, String> {
+    let split = s.find(|c: char| c.is_ascii_alphabetic()).unwrap_or(s.len());
+    let seconds = match &s[split..] {
+        "s" => 1.,
+        "m" => 60.,
+        "h" | "" => 3600.,
+        "d" => 86400.,
+        unit => return Err(format!("unknown unit {:?}, use s, m, h or d", unit)),
+    };
+    match s[..split].parse::<f64>() {
+        Ok(n) if n >= 0. && n.is_finite() => Ok(Duration::from_secs_f64(n * seconds)),
+        _ => Err(format!("invalid duration {:?}", s)),
+    }
+}
+
+fn parse_lat_lon(s: &str) -> Result<(f64, f64), String> {
+    let (lat, lon) = s.split_once(',').ok_or_else(|| format!("expected LAT,LON, got {:?}", s))?;
+    let lat = lat.trim().parse().map_err(|_| format!("invalid latitude {:?}", lat))?;
+    let lon = lon.trim().parse().map_err(|_| format!("invalid longitude {:?}", lon))?;
+    Ok((lat, lon))
+}
+
+fn parse_source(s: &str) -> Result<(String, String), String> {
+    match s.split_once('=') {
+        Some((name, path)) if !name.is_empty() && !path.is_empty() => Ok((name.to_owned(), path.to_owned())),
+        _ => Err(format!("expected NAME=PATH, got {:?}", s)),
+    }
+}
+
+fn parse_export(s: &str) -> Result<(String, String), String> {
+    let (name, path) = parse_source(s).map_err(|_| format!("expected FORMAT=PATH, got {:?}", s))?;
+    if format::output(&name).is_none() {
+        let names: Vec<_> = format::outputs().iter().map(|f| f.name()).collect();
+        return Err(format!("unknown output format {:?}, expected one of {}", name, names.join(", ")));
     }
+    Ok((name, path))
+}
+
+fn main() {
+    let args = Args::parse();
+    logging::init(args.verbose, args.log_format);
+    if let Err(error) = run(&args) {
+        eprintln!("error: {}", error);
+        std::process::exit(1);
+    }
+}
 
+fn run(args: &Args) -> Result<(), Box<dyn Error>> {
+    match &args.command {
+        Some(Command::Route { idents, dataset, output }) => route(idents, dataset, args.delimiter, output, args.line_endings),
+        Some(Command::Plan { idents, dataset, output }) => plan(idents, dataset, args.delimiter, output, args.line_endings),
+        Some(Command::Merge {
+            sources,
+            config,
+            proximity,
+            name_similarity,
+            name_radius,
+            output,
+            conflict_distance,
+            interactive,
+            resolve_file,
+            save_decisions,
+            provenance_columns,
+            provenance_json,
+            spill_dir,
+        }) => {
+            let mut resolver = ConflictResolver {
+                threshold: *conflict_distance,
+                interactive: *interactive,
+                ..Default::default()
+            };
+            if let Some(path) = resolve_file {
+                resolver.load_decisions(path)?;
+            }
+            let merge = pipeline::Merge {
+                sources: sources.clone(),
+                precedence: precedence(config.as_deref())?,
+                matcher: SiteMatcher {
+                    distance: *proximity,
+                    name_similarity: *name_similarity,
+                    name_radius: *name_radius,
+                },
+                output: output.clone(),
+                style: args.style(),
+                provenance_columns: *provenance_columns,
+                provenance_json: provenance_json.clone(),
+                spill_dir: spill_dir.clone(),
+            };
+            merge.run(&mut resolver)?;
+            if let Some(path) = save_decisions {
+                resolver.save_decisions(path)?;
+            }
+            Ok(())
+        }
+        Some(Command::Diff { old, new, move_threshold, format }) => {
+            let diff = diff::diff(&read_any(old, args.delimiter)?, &read_any(new, args.delimiter)?, *move_threshold);
+            match format {
+                diff::DiffFormat::Text => print!("{}", diff.text()),
+                diff::DiffFormat::SideBySide => {
+                    let color = io::stdout().is_terminal() && std::env::var_os("NO_COLOR").is_none();
+                    print!("{}", diff.side_by_side(color));
+                }
+                diff::DiffFormat::Json => println!("{}", serde_json::to_string_pretty(&diff)?),
+            }
+            if !diff.is_empty() {
+                std::process::exit(1);
+            }
+            Ok(())
+        }
+        Some(Command::Roundtrip { dataset, from, via, max_distance, max_elevation }) => {
+            let dataset = args.downloads()?.resolve(dataset)?;
+            let waypoints = read_as(&dataset, from.as_deref(), args.delimiter)?;
+            let output = format::output(via).ok_or_else(|| format!("unknown output format {:?}", via))?;
+            let input = format::input(via).ok_or_else(|| format!("{} can be written but not read back", via))?;
+            let options = WriteOptions {
+                delimiter: args.delimiter,
+                line_ending: args.line_endings,
+                ..Default::default()
+            };
+            let back = roundtrip::convert_back(&waypoints, output, input, &options)?;
+            let tolerances = roundtrip::Tolerances { distance: *max_distance, elevation: *max_elevation };
+            let diff = roundtrip::compare(&waypoints, &back, &tolerances);
+            print!("{}", diff.text());
+            if !diff.is_empty() {
+                std::process::exit(1);
+            }
+            Ok(())
+        }
+        Some(Command::Verify {
+            dataset,
+            reference,
+            reference_format,
+            max_distance,
+            max_elevation,
+            min_name_similarity,
+            search_radius,
+            missing,
+            report,
+        }) => {
+            let reference = args.downloads()?.resolve(reference)?;
+            let reference = match reference_format {
+                ReferenceFormat::Ourairports => verify::read_ourairports(&reference, None, &HashMap::new())?,
+                ReferenceFormat::Userpoints => read_dataset(&reference, args.delimiter)?,
+                ReferenceFormat::Ead => ead::read_aixm(&reference)?,
+            };
+            let tolerances = verify::Tolerances {
+                distance: *max_distance,
+                elevation: *max_elevation,
+                name: *min_name_similarity,
+                search_radius: *search_radius,
+            };
+            let dataset = read_any(dataset, args.delimiter)?;
+            let (mut text, mut issues) = verify::verify(&dataset, &reference, &tolerances);
+            if *missing {
+                let (more, count) = verify::missing(&dataset, &reference, &tolerances);
+                text.push_str(&more);
+                issues += count;
+            }
+            match report {
+                Some(path) => fs::write(path, args.line_endings.apply(&text).as_ref())?,
+                None => print!("{}", text),
+            }
+            if issues > 0 {
+                std::process::exit(1);
+            }
+            Ok(())
+        }
+        Some(Command::Query { sources, format }) => {
+            let downloads = args.downloads()?;
+            let sources = sources.iter().map(|s| downloads.resolve(s)).collect::<Result<Vec<_>, _>>()?;
+            query::repl(&sources, format.as_deref(), args.delimiter)
+        }
+        Some(Command::ListFormats) => {
+            print!("{}", format::capabilities());
+            Ok(())
+        }
+        Some(Command::Stats { dataset, format, bucket, report }) => {
+            let dataset = args.downloads()?.resolve(dataset)?;
+            let waypoints = read_as(&dataset, format.as_deref(), args.delimiter)?;
+            let text = stats::stats(&waypoints, bucket.get()).text();
+            match report {
+                Some(path) => fs::write(path, args.line_endings.apply(&text).as_ref())?,
+                None => print!("{}", text),
+            }
+            Ok(())
+        }
+        Some(Command::Validate { dataset, format, rules, dem, report }) => {
+            let dataset = args.downloads()?.resolve(dataset)?;
+            let waypoints = read_as(&dataset, format.as_deref(), args.delimiter)?;
+            let rules = match rules {
+                Some(path) => validate::Rules::load(path)?,
+                None => validate::Rules::default(),
+            };
+            let mut dem = dem.as_ref().map(Dem::open).transpose()?;
+            let outcomes = validate::validate(&waypoints, &rules, dem.as_mut())?;
+            let text = validate::report(&outcomes, waypoints.len());
+            match report {
+                Some(path) => fs::write(path, args.line_endings.apply(&text).as_ref())?,
+                None => print!("{}", text),
+            }
+            if outcomes.iter().any(|o| !o.passed()) {
+                std::process::exit(1);
+            }
+            Ok(())
+        }
+        Some(Command::SelfUpdate { check, public_key, url }) => {
+            if args.offline {
+                return Err("self-update needs network access, which --offline disables".into());
+            }
+            update::run(&args.downloads()?.agent(), url, *check, public_key.as_deref())
+        }
+        Some(Command::Restore { output, from, list }) => {
+            if !*list {
+                return backup::restore(output, from.as_deref(), args.backups);
+            }
+            for (stamp, path) in backup::list(Path::new(output))? {
+                println!("{}  {}", stamp, path.display());
+            }
+            Ok(())
+        }
+        Some(Command::Fetch { dataset }) => {
+            let (url, mut waypoints) = dataset.fetch(&args.downloads()?, args.backoff())?;
+            for waypoint in &mut waypoints {
+                waypoint.import_filename = Some(url.to_owned());
+            }
+            let options = WriteOptions {
+                delimiter: args.delimiter,
+                line_ending: args.line_endings,
+                ..Default::default()
+            };
+            format::Userpoints.write(dataset.output(), &waypoints, &options)?;
+            eprintln!("{} records from {} written to {}", waypoints.len(), url, dataset.output());
+            Ok(())
+        }
+        Some(Command::Serve { bind, max_upload, workers }) => {
+            let workers = workers.or_else(|| thread::available_parallelism().ok()).map_or(1, NonZeroUsize::get);
+            serve::run(bind, workers, max_upload * 1024 * 1024)
+        }
+        None => {
+            let mut summary = Summary::default();
+            let result = args
+                .conversion()
+                .and_then(|conversion| conversion.run(&mut summary))
+                .and_then(|()| args.report_summary(&summary));
+            if let Some(path) = &args.report {
+                let error = result.as_ref().err().map(|e| e.to_string());
+                let report = summary::Report::new(&summary, error, std::env::args().collect());
+                fs::write(path, args.line_endings.apply(&serde_json::to_string_pretty(&report)?).as_ref())?;
+            }
+            result
+        }
+    }
+}
+
+fn resolve_idents(dataset: &[Waypoint], idents: &[String]) -> Result<Vec<Waypoint>, Box<dyn Error>> {
+    idents
+        .iter()
+        .map(|ident| {
+            dataset
+                .iter()
+                .find(|w| w.ident.eq_ignore_ascii_case(ident))
+                .cloned()
+                .ok_or_else(|| format!("ident {} not found in dataset", ident).into())
+        })
+        .collect()
+}
+
+fn precedence(config: Option<&str>) -> Result<merge::Precedence, Box<dyn Error>> {
+    let precedence: merge::Precedence = match config {
+        Some(path) => toml::from_str(&fs::read_to_string(path)?)?,
+        None => Default::default(),
+    };
+    precedence.validate()?;
+    Ok(precedence)
+}
+
+fn route(idents: &[String], dataset: &str, delimiter: u8, output: &str, ending: LineEnding) -> Result<(), Box<dyn Error>> {
+    let dataset = read_dataset(dataset, delimiter)?;
+    let waypoints = resolve_idents(&dataset, idents)?;
+    gpx::write_route(output, &idents.join(" - "), &waypoints, ending)?;
     Ok(())
 }
+
+fn plan(idents: &[String], dataset: &str, delimiter: u8, output: &str, ending: LineEnding) -> Result<(), Box<dyn Error>> {
+    let dataset = read_dataset(dataset, delimiter)?;
+    let waypoints = resolve_idents(&dataset, idents)?;
+    let name = fpl::identifiers(&waypoints).join(" ");
+    fpl::write_plan(output, &truncate::truncate(&name, 25), &waypoints, ending)?;
+    Ok(())
+}
+
+/// Reads `path` with the named input format, or as SkyDemon XML or
+/// userpoints CSV by its extension.
+fn read_as(path: &str, format: Option<&str>, delimiter: u8) -> Result<Vec<Waypoint>, Box<dyn Error>> {
+    let Some(name) = format else {
+        return Ok(read_any(path, delimiter)?);
+    };
+    let format = format::input(name).ok_or_else(|| format!("unknown input format {:?}", name))?;
+    let options = ReadOptions { delimiter, ..ReadOptions::default() };
+    Ok(format.read(path, &options)?.collect())
+}
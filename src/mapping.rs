@@ -0,0 +1,156 @@
+use crate::columns::Columns;
+use crate::elevation::ElevationUnit;
+use indexmap::IndexMap;
+use serde::Deserialize;
+use serde_json::{Map, Value};
+use std::collections::HashSet;
+use std::error::Error;
+use std::fs;
+
+/// Per-target output mapping, loaded with `--mapping`:
+///
+/// ```toml
+/// [csv]
+/// columns = "ident,name,lat,lon,elev"
+/// rename = { Ident = "ID", Elevation = "Elev (ft)" }
+/// constant = { Country = "PL" }
+/// transform = { Name = "trim|upper", Latitude = "round:5" }
+/// ```
+///
+/// Transforms chain with `|`: `upper`, `lower`, `trim`, `round:N`,
+/// `prefix:TEXT`, `suffix:TEXT` and `default:TEXT` (for empty values). Keys
+/// are the default column names; constants are appended after the columns.
+/// No two columns may end up with the same name; without `columns`, that
+/// holds for every column there is. The elevation column is labelled with
+/// `--elevation-unit` after renaming.
+#[derive(Deserialize, Debug, Default)]
+#[serde(deny_unknown_fields)]
+pub struct Mapping {
+    #[serde(default)]
+    pub csv: TargetMapping,
+    #[serde(default)]
+    pub json: TargetMapping,
+}
+
+#[derive(Deserialize, Debug, Default)]
+#[serde(deny_unknown_fields)]
+pub struct TargetMapping {
+    pub columns: Option<String>,
+    #[serde(default)]
+    pub rename: IndexMap<String, String>,
+    #[serde(default)]
+    pub constant: IndexMap<String, String>,
+    #[serde(default)]
+    pub transform: IndexMap<String, String>,
+}
+
+impl Mapping {
+    pub fn load(path: &str) -> Result<Self, Box<dyn Error>> {
+        let mapping: Mapping = toml::from_str(&fs::read_to_string(path)?)?;
+        for target in [&mapping.csv, &mapping.json] {
+            target.check_names()?;
+            for chain in target.transform.values() {
+                for step in chain.split('|') {
+                    transform(Value::Null, step)?;
+                }
+            }
+        }
+        Ok(mapping)
+    }
+}
+
+fn transform(value: Value, step: &str) -> Result<Value, String> {
+    let (name, arg) = step.trim().split_once(':').unwrap_or((step.trim(), ""));
+    let text = |v: &Value| match v {
+        Value::Null => String::new(),
+        Value::String(s) => s.clone(),
+        v => v.to_string(),
+    };
+    Ok(match name {
+        "upper" => value.as_str().map_or(value.clone(), |s| Value::String(s.to_uppercase())),
+        "lower" => value.as_str().map_or(value.clone(), |s| Value::String(s.to_lowercase())),
+        "trim" => value.as_str().map_or(value.clone(), |s| Value::String(s.trim().to_owned())),
+        "round" => {
+            let places: i32 = arg.parse().map_err(|_| format!("round needs a number of places, got {:?}", arg))?;
+            match value.as_f64() {
+                Some(x) => {
+                    let factor = 10f64.powi(places);
+                    serde_json::Number::from_f64((x * factor).round() / factor).map_or(Value::Null, Value::Number)
+                }
+                None => value,
+            }
+        }
+        "prefix" if !value.is_null() => Value::String(format!("{}{}", arg, text(&value))),
+        "suffix" if !value.is_null() => Value::String(format!("{}{}", text(&value), arg)),
+        "prefix" | "suffix" => value,
+        "default" if text(&value).is_empty() => Value::String(arg.to_owned()),
+        "default" => value,
+        _ => return Err(format!("unknown transform {:?}", step)),
+    })
+}
+
+impl TargetMapping {
+    /// Columns to select for this target, overriding the command line.
+    pub fn columns(&self, default: &Columns) -> Columns {
+        self.columns
+            .as_deref()
+            .and_then(|c| c.parse().ok())
+            .unwrap_or_else(|| default.clone())
+    }
+
+    fn rename(&self, column: &str) -> String {
+        self.rename.get(column).cloned().unwrap_or_else(|| column.to_owned())
+    }
+
+    /// The name `column` is written under, with the elevation in `unit`.
+    fn output_name(&self, column: &str, unit: ElevationUnit) -> String {
+        match column {
+            "Elevation" => unit.label(&self.rename(column)),
+            _ => self.rename(column),
+        }
+    }
+
+    /// Fails when two columns, or a column and a constant, would be written
+    /// under one name: the row would keep only one of them and no longer
+    /// line up with the header.
+    fn check_names(&self) -> Result<(), String> {
+        let columns = match &self.columns {
+            Some(columns) => columns.parse::<Columns>()?,
+            None => Columns::default(),
+        };
+        for unit in [ElevationUnit::Ft, ElevationUnit::M] {
+            let mut names = HashSet::new();
+            for name in self.header(&columns, unit) {
+                if !names.insert(name.clone()) {
+                    return Err(format!("more than one column would be named {:?}", name));
+                }
+            }
+        }
+        Ok(())
+    }
+
+    pub fn header(&self, columns: &Columns, unit: ElevationUnit) -> Vec<String> {
+        columns
+            .0
+            .iter()
+            .map(|c| self.output_name(c, unit))
+            .chain(self.constant.keys().map(|k| self.rename(k)))
+            .collect()
+    }
+
+    pub fn apply(&self, row: Map<String, Value>, unit: ElevationUnit) -> Map<String, Value> {
+        let mut mapped = Map::new();
+        for (column, mut value) in row {
+            if let Some(chain) = self.transform.get(&column) {
+                for step in chain.split('|') {
+                    value = transform(value, step).expect("transforms are validated on load");
+                }
+            }
+            mapped.insert(self.output_name(&column, unit), value);
+        }
+        for (column, value) in &self.constant {
+            mapped.insert(self.rename(column), Value::String(value.clone()));
+        }
+        mapped
+    }
+}
@@ -0,0 +1,70 @@
+use crate::{geo, Waypoint};
+use unicode_normalization::char::is_combining_mark;
+use unicode_normalization::UnicodeNormalization;
+
+/// Words that describe the kind of site rather than name it, dropped before
+/// comparing names (after folding).
+const GENERIC_WORDS: [&str; 6] = ["ladowisko", "lotnisko", "aeroklub", "airstrip", "airfield", "strip"];
+
+/// Transliterates to ASCII: compatibility-decomposes, drops the combining
+/// marks and spells out the letters that don't decompose (`Łódź` → `Lodz`).
+/// Characters without an ASCII rendering are dropped.
+pub fn to_ascii(s: &str) -> String {
+    s.nfkd()
+        .filter(|c| !is_combining_mark(*c))
+        .filter_map(|c| match c {
+            'ł' => Some('l'),
+            'Ł' => Some('L'),
+            'ß' => Some('s'),
+            c if c.is_ascii() => Some(c),
+            _ => None,
+        })
+        .collect()
+}
+
+/// Lower-cases and strips diacritics, so `Mała` and `Mala` compare equal.
+pub fn fold(s: &str) -> String {
+    to_ascii(s).to_lowercase()
+}
+
+fn normalize_name(name: &str) -> String {
+    let folded = fold(name);
+    let words: Vec<&str> = folded
+        .split(|c: char| !c.is_alphanumeric())
+        .filter(|w| !w.is_empty() && !GENERIC_WORDS.contains(w))
+        .collect();
+    words.join(" ")
+}
+
+/// Jaro-Winkler similarity (0..=1) of two site names after folding and
+/// dropping generic words such as "Lądowisko".
+pub fn name_similarity(a: &str, b: &str) -> f64 {
+    strsim::jaro_winkler(&normalize_name(a), &normalize_name(b))
+}
+
+/// Decides whether two waypoints describe the same site.
+#[derive(Clone, Copy, Debug, Default)]
+pub struct SiteMatcher {
+    /// Same type and at most this far apart (metres).
+    pub distance: Option<f64>,
+    /// Same type, names at least this similar and within `name_radius`.
+    pub name_similarity: Option<f64>,
+    pub name_radius: f64,
+}
+
+impl SiteMatcher {
+    pub fn is_enabled(&self) -> bool {
+        self.distance.is_some() || self.name_similarity.is_some()
+    }
+
+    pub fn same_site(&self, a: &Waypoint, b: &Waypoint) -> bool {
+        if a.waypoint_type != b.waypoint_type {
+            return false;
+        }
+        let d = geo::distance(a.latitude as f64, a.longitude as f64, b.latitude as f64, b.longitude as f64);
+        self.distance.is_some_and(|max| d <= max)
+            || self
+                .name_similarity
+                .is_some_and(|min| d <= self.name_radius && name_similarity(&a.name, &b.name) >= min)
+    }
+}
@@ -0,0 +1,242 @@
+use crate::conflict::ConflictResolver;
+use crate::matching::SiteMatcher;
+use crate::Waypoint;
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeMap, HashMap};
+
+/// The tag of records from sources that are not trusted on their own, such
+/// as archived DAFIF data; a site some other source also has loses it.
+pub const UNVERIFIED: &str = "unverified";
+
+/// Fields that can be given their own source order in the precedence config.
+pub const FIELDS: [&str; 9] = [
+    "type",
+    "name",
+    "position",
+    "elevation",
+    "declination",
+    "tags",
+    "description",
+    "region",
+    "visible_from",
+];
+
+/// Source-priority config, e.g.
+///
+/// ```toml
+/// default = ["aip", "skydemon", "local"]
+///
+/// [fields]
+/// elevation = ["aip"]
+/// name = ["local"]
+/// ```
+///
+/// Sources not listed for a field fall back to `default`, then to the order
+/// they were given on the command line.
+#[derive(Deserialize, Debug, Default)]
+#[serde(deny_unknown_fields)]
+pub struct Precedence {
+    #[serde(default)]
+    pub default: Vec<String>,
+    #[serde(default)]
+    pub fields: HashMap<String, Vec<String>>,
+}
+
+/// Which source supplied each field of a merged record.
+#[derive(Serialize, Debug, Default, Clone)]
+pub struct Provenance {
+    #[serde(rename = "Source: Type")]
+    pub waypoint_type: Option<String>,
+    #[serde(rename = "Source: Name")]
+    pub name: Option<String>,
+    #[serde(rename = "Source: Position")]
+    pub position: Option<String>,
+    #[serde(rename = "Source: Elevation")]
+    pub elevation: Option<String>,
+    #[serde(rename = "Source: Magnetic Declination")]
+    pub declination: Option<String>,
+    #[serde(rename = "Source: Tags")]
+    pub tags: Option<String>,
+    #[serde(rename = "Source: Description")]
+    pub description: Option<String>,
+    #[serde(rename = "Source: Region")]
+    pub region: Option<String>,
+    #[serde(rename = "Source: Visible From")]
+    pub visible_from: Option<String>,
+}
+
+impl Provenance {
+    fn slot(&mut self, field: &str) -> Option<&mut Option<String>> {
+        Some(match field {
+            "type" => &mut self.waypoint_type,
+            "name" => &mut self.name,
+            "position" => &mut self.position,
+            "elevation" => &mut self.elevation,
+            "declination" => &mut self.declination,
+            "tags" => &mut self.tags,
+            "description" => &mut self.description,
+            "region" => &mut self.region,
+            "visible_from" => &mut self.visible_from,
+            _ => return None,
+        })
+    }
+
+    fn set(&mut self, field: &str, source: &str) {
+        if let Some(slot) = self.slot(field) {
+            *slot = Some(source.to_owned());
+        }
+    }
+
+    /// Field name to source, for the fields some source supplied.
+    pub fn by_field(&self) -> BTreeMap<&'static str, String> {
+        let mut copy = self.clone();
+        FIELDS
+            .into_iter()
+            .filter_map(|f| copy.slot(f).and_then(Option::take).map(|s| (f, s)))
+            .collect()
+    }
+}
+
+pub struct Merged {
+    pub waypoint: Waypoint,
+    pub provenance: Provenance,
+}
+
+pub struct Source {
+    pub name: String,
+    pub waypoints: Vec<Waypoint>,
+}
+
+impl Precedence {
+    pub fn validate(&self) -> Result<(), String> {
+        match self.fields.keys().find(|f| !FIELDS.contains(&f.as_str())) {
+            Some(f) => Err(format!("unknown field {:?} in precedence config, expected one of {}", f, FIELDS.join(", "))),
+            None => Ok(()),
+        }
+    }
+
+    fn order<'a>(&'a self, field: &str, sources: &[&'a str]) -> Vec<&'a str> {
+        let mut order: Vec<&str> = vec![];
+        let configured = self.fields.get(field).into_iter().flatten().chain(&self.default);
+        for name in configured.map(String::as_str).chain(sources.iter().copied()) {
+            if !order.contains(&name) {
+                order.push(name);
+            }
+        }
+        order
+    }
+}
+
+fn has(waypoint: &Waypoint, field: &str) -> bool {
+    match field {
+        "type" => !waypoint.waypoint_type.is_empty(),
+        "name" => !waypoint.name.is_empty(),
+        "position" => true,
+        "elevation" => waypoint.elevation.is_some(),
+        "declination" => waypoint.magnetic_declination.is_some(),
+        "tags" => waypoint.tags.is_some(),
+        "description" => waypoint.description.is_some(),
+        "region" => waypoint.region.is_some(),
+        "visible_from" => waypoint.visible_from.is_some(),
+        _ => false,
+    }
+}
+
+fn take(into: &mut Waypoint, from: &Waypoint, field: &str) {
+    match field {
+        "type" => into.waypoint_type = from.waypoint_type.clone(),
+        "name" => into.name = from.name.clone(),
+        "position" => {
+            into.latitude = from.latitude;
+            into.longitude = from.longitude;
+        }
+        "elevation" => into.elevation = from.elevation,
+        "declination" => into.magnetic_declination = from.magnetic_declination,
+        "tags" => into.tags = from.tags.clone(),
+        "description" => into.description = from.description.clone(),
+        "region" => into.region = from.region.clone(),
+        "visible_from" => into.visible_from = from.visible_from,
+        _ => {}
+    }
+}
+
+/// Groups records of all sources by ident, or additionally by whatever
+/// `matcher` considers the same site, and combines each group field by field,
+/// taking every field from the highest-priority source that has it unless
+/// `resolver` settles a position conflict otherwise. Records of one source
+/// are never combined with each other.
+pub fn merge(
+    sources: &[Source],
+    precedence: &Precedence,
+    matcher: &SiteMatcher,
+    resolver: &mut ConflictResolver,
+) -> Vec<Merged> {
+    let mut groups: Vec<(&Waypoint, HashMap<&str, &Waypoint>)> = vec![];
+    for source in sources {
+        for waypoint in &source.waypoints {
+            let same_site = |first: &Waypoint| {
+                first.ident.eq_ignore_ascii_case(&waypoint.ident) || matcher.same_site(first, waypoint)
+            };
+            // A site holds one record per source: a second matching record of
+            // the same source is a site of its own rather than dropped.
+            let index = match groups
+                .iter()
+                .position(|(first, records)| !records.contains_key(source.name.as_str()) && same_site(first))
+            {
+                Some(i) => i,
+                None => {
+                    groups.push((waypoint, HashMap::new()));
+                    groups.len() - 1
+                }
+            };
+            groups[index].1.insert(source.name.as_str(), waypoint);
+        }
+    }
+    let names: Vec<&str> = sources.iter().map(|s| s.name.as_str()).collect();
+    groups
+        .into_iter()
+        .map(|(_, records)| merge_group(&records, &names, precedence, resolver))
+        .collect()
+}
+
+/// Combines the records of one site, keyed by source name; `sources` are
+/// the names in command-line order.
+pub(crate) fn merge_group(
+    records: &HashMap<&str, &Waypoint>,
+    sources: &[&str],
+    precedence: &Precedence,
+    resolver: &mut ConflictResolver,
+) -> Merged {
+    let first = sources
+        .iter()
+        .find_map(|s| records.get(s))
+        .expect("groups are never empty");
+    let mut merged = (*first).clone();
+    let mut provenance = Provenance::default();
+    let candidates = |field| -> Vec<(&str, &Waypoint)> {
+        precedence
+            .order(field, sources)
+            .into_iter()
+            .filter_map(|name| records.get_key_value(name))
+            .map(|(name, w)| (*name, *w))
+            .filter(|(_, w)| has(w, field))
+            .collect()
+    };
+    let decided = resolver.position(&first.ident, &candidates("position"));
+    for field in FIELDS {
+        let candidate = candidates(field)
+            .into_iter()
+            .find(|(name, _)| field != "position" || decided.as_deref().is_none_or(|d| d == *name));
+        if let Some((source, from)) = candidate {
+            take(&mut merged, from, field);
+            provenance.set(field, source);
+        }
+    }
+    if records.values().any(|w| !w.has_tag(UNVERIFIED)) {
+        merged.remove_tag(UNVERIFIED);
+    }
+    Merged {
+        waypoint: merged,
+        provenance,
+    }
+}
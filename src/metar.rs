@@ -0,0 +1,83 @@
+//! Pointing each airfield at the nearest station that reports METARs, so
+//! users know which weather report is most representative for a strip.
+
+use crate::lang::Lang;
+use crate::{geo, hems, input, read_dataset, Waypoint};
+use serde::Deserialize;
+use std::error::Error;
+
+/// Reporting stations in and around Poland from the aviationweather.gov
+/// data API.
+pub const STATIONS_URL: &str =
+    "https://aviationweather.gov/api/data/stationinfo?bbox=48,13,56,25&format=json";
+
+/// Waypoint types that get a station; generated points such as VRPs and
+/// obstacles are left alone.
+const AIRFIELD_TYPES: [&str; 5] = ["Airport", "Airstrip", "Helipad", "Seaport", hems::HEMS_TYPE];
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Station {
+    pub icao: String,
+    pub latitude: f64,
+    pub longitude: f64,
+}
+
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct StationRecord {
+    icao_id: Option<String>,
+    lat: f64,
+    lon: f64,
+    #[serde(default)]
+    site_type: Vec<String>,
+}
+
+/// Reads the stations that report METARs, either from aviationweather.gov
+/// `stationinfo` JSON or, for `.csv` files, from userpoints whose idents
+/// are the station codes.
+pub fn read_stations(path: &str, delimiter: u8) -> Result<Vec<Station>, Box<dyn Error>> {
+    if path.to_lowercase().ends_with(".csv") {
+        let stations = read_dataset(path, delimiter)?
+            .into_iter()
+            .map(|w| Station { icao: w.ident, latitude: w.latitude as f64, longitude: w.longitude as f64 })
+            .collect();
+        return Ok(stations);
+    }
+    let records: Vec<StationRecord> = serde_json::from_reader(input::open(path)?)?;
+    Ok(records
+        .into_iter()
+        .filter(|r| r.site_type.is_empty() || r.site_type.iter().any(|t| t == "METAR"))
+        .filter_map(|r| {
+            Some(Station {
+                icao: r.icao_id.filter(|i| !i.is_empty())?,
+                latitude: r.lat,
+                longitude: r.lon,
+            })
+        })
+        .collect())
+}
+
+/// The station closest to `waypoint` and its distance in metres.
+pub fn nearest<'a>(waypoint: &Waypoint, stations: &'a [Station]) -> Option<(&'a Station, f64)> {
+    let (lat, lon) = (waypoint.latitude as f64, waypoint.longitude as f64);
+    stations
+        .iter()
+        .map(|s| (s, geo::distance(lat, lon, s.latitude, s.longitude)))
+        .min_by(|a, b| a.1.total_cmp(&b.1))
+}
+
+/// Appends the nearest station within `max_distance` metres to each
+/// airfield's description, e.g. `METAR EPWA 6 NM`.
+pub fn associate(waypoints: &mut [Waypoint], stations: &[Station], max_distance: f64, lang: Lang) {
+    for waypoint in waypoints.iter_mut().filter(|w| AIRFIELD_TYPES.contains(&w.waypoint_type.as_str())) {
+        let Some((station, distance)) = nearest(waypoint, stations).filter(|(_, d)| *d <= max_distance) else {
+            continue;
+        };
+        let distance = lang.note(|lang| lang.nautical_miles(distance / geo::METRES_PER_NM));
+        let note = format!("METAR {} {}", station.icao, distance);
+        waypoint.description = Some(match waypoint.description.take() {
+            Some(d) if !d.is_empty() => format!("{}; {}", d, note),
+            _ => note,
+        });
+    }
+}
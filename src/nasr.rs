@@ -0,0 +1,148 @@
+//! Airports of the United States from the FAA's 28-day NASR subscription,
+//! read from its `APT_BASE.csv` file.
+
+use crate::format::{Fields, InputFormat, ReadOptions, Records};
+use crate::{glider, input, region, ultralight, Waypoint};
+use serde::Deserialize;
+use std::error::Error;
+
+#[derive(Deserialize)]
+#[serde(rename_all = "SCREAMING_SNAKE_CASE")]
+struct AptRecord {
+    site_type_code: String,
+    state_code: Option<String>,
+    arpt_id: String,
+    arpt_name: String,
+    lat_deg: Option<f32>,
+    lat_min: Option<f32>,
+    lat_sec: Option<f32>,
+    lat_hemis: Option<String>,
+    lat_decimal: Option<f32>,
+    long_deg: Option<f32>,
+    long_min: Option<f32>,
+    long_sec: Option<f32>,
+    long_hemis: Option<String>,
+    long_decimal: Option<f32>,
+    elev: Option<f32>,
+    mag_varn: Option<f32>,
+    mag_hemis: Option<String>,
+    icao_id: Option<String>,
+    arpt_status: Option<String>,
+}
+
+/// Degrees from the decimal column, or else the degree, minute, second and
+/// hemisphere columns.
+fn degrees(
+    decimal: Option<f32>,
+    parts: (Option<f32>, Option<f32>, Option<f32>),
+    hemisphere: Option<&str>,
+    negative: &str,
+) -> Option<f32> {
+    if decimal.is_some() {
+        return decimal;
+    }
+    let degrees = parts.0? + parts.1.unwrap_or(0.) / 60. + parts.2.unwrap_or(0.) / 3600.;
+    match hemisphere {
+        Some(h) if h == negative => Some(-degrees),
+        _ => Some(degrees),
+    }
+}
+
+/// The userpoint type for a NASR `SITE_TYPE_CODE`; balloonports (`B`) are
+/// airstrips too.
+fn userpoint_type(code: &str) -> &'static str {
+    match code {
+        "A" => "Airport",
+        "H" => "Helipad",
+        "C" => "Seaport",
+        "G" | "U" => "Airstrip",
+        _ => "Airstrip",
+    }
+}
+
+/// The region of a facility without an ICAO code: Alaska and Hawaii have
+/// their own, everything else is in the contiguous `K`.
+fn state_region(state: Option<&str>) -> &'static str {
+    match state {
+        Some("AK") => "PA",
+        Some("HI") => "PH",
+        _ => "K",
+    }
+}
+
+/// Reads NASR `APT_BASE.csv`. The ICAO code is the ident where the facility
+/// has one, the FAA location identifier otherwise; permanently closed
+/// facilities are skipped and indefinitely closed ones tagged `closed`.
+/// Gliderports are tagged `glider` and ultralight parks `ultralight`.
+pub fn read_apt(path: &str, delimiter: u8) -> Result<Vec<Waypoint>, Box<dyn Error>> {
+    let mut reader = csv::ReaderBuilder::new().delimiter(delimiter).from_reader(input::open(path)?);
+    let mut waypoints = vec![];
+    for record in reader.deserialize() {
+        let record: AptRecord = record?;
+        let status = record.arpt_status.as_deref().unwrap_or_default();
+        if status == "CP" {
+            continue;
+        }
+        let icao = record.icao_id.as_deref().map(str::trim).filter(|i| !i.is_empty());
+        let ident = icao.unwrap_or(record.arpt_id.trim());
+        let latitude = degrees(
+            record.lat_decimal,
+            (record.lat_deg, record.lat_min, record.lat_sec),
+            record.lat_hemis.as_deref(),
+            "S",
+        )
+        .ok_or_else(|| format!("{}: no latitude", ident))?;
+        let longitude = degrees(
+            record.long_decimal,
+            (record.long_deg, record.long_min, record.long_sec),
+            record.long_hemis.as_deref(),
+            "W",
+        )
+        .ok_or_else(|| format!("{}: no longitude", ident))?;
+        let region = icao.and_then(region::of_icao).unwrap_or_else(|| state_region(record.state_code.as_deref()));
+        let mut waypoint = Waypoint::builder(record.arpt_name.trim())
+            .waypoint_type(userpoint_type(&record.site_type_code))
+            .ident(ident)
+            .coordinates(latitude, longitude)
+            .elevation(record.elev)
+            .region(region)
+            .import_filename(path);
+        if let Some(variation) = record.mag_varn {
+            waypoint = waypoint.magnetic_declination(match record.mag_hemis.as_deref() {
+                Some("W") => -variation,
+                _ => variation,
+            });
+        }
+        let mut waypoint = waypoint.build().map_err(|e| e.in_record(ident))?;
+        match record.site_type_code.as_str() {
+            "G" => waypoint.add_tag(glider::GLIDER_TAG),
+            "U" => waypoint.add_tag(ultralight::ULTRALIGHT_TAG),
+            _ => {}
+        }
+        if status == "CI" {
+            waypoint.add_tag("closed");
+        }
+        waypoints.push(waypoint);
+    }
+    Ok(waypoints)
+}
+
+pub struct NasrApt;
+
+impl InputFormat for NasrApt {
+    fn name(&self) -> &'static str {
+        "nasr-apt"
+    }
+
+    fn description(&self) -> &'static str {
+        "FAA NASR APT_BASE.csv"
+    }
+
+    fn fields(&self) -> Fields {
+        Fields { magnetic_declination: true, elevation: true, tags: true, ..Fields::default() }
+    }
+
+    fn read(&self, path: &str, options: &ReadOptions) -> Result<Records, Box<dyn Error>> {
+        Ok(Box::new(read_apt(path, options.delimiter)?.into_iter()))
+    }
+}
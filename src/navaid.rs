@@ -0,0 +1,122 @@
+use crate::format::{Fields, InputFormat, ReadOptions, Records};
+use crate::{input, region, Coordinate, Error as DataError, Waypoint};
+use serde::Deserialize;
+use std::error::Error;
+
+/// A radio navigation aid. Frequencies are MHz for VOR/DME and kHz for NDB;
+/// range is in nautical miles.
+#[derive(Debug, PartialEq)]
+pub struct Navaid {
+    pub ident: String,
+    pub name: String,
+    pub navaid_type: String,
+    pub latitude: f32,
+    pub longitude: f32,
+    pub elevation: Option<f32>,
+    pub frequency: Option<f32>,
+    pub channel: Option<String>,
+    pub range: Option<f32>,
+}
+
+#[derive(Deserialize)]
+struct NavaidRecord {
+    #[serde(rename = "Ident")]
+    ident: String,
+    #[serde(rename = "Name")]
+    name: String,
+    #[serde(rename = "Type")]
+    navaid_type: String,
+    #[serde(rename = "Latitude")]
+    latitude: String,
+    #[serde(rename = "Longitude")]
+    longitude: String,
+    #[serde(rename = "Elevation")]
+    elevation: Option<f32>,
+    #[serde(rename = "Frequency")]
+    frequency: Option<f32>,
+    #[serde(rename = "Channel")]
+    channel: Option<String>,
+    #[serde(rename = "Range")]
+    range: Option<f32>,
+}
+
+/// Reads a CSV with `Ident,Name,Type,Latitude,Longitude,Elevation,Frequency,Channel,Range` columns.
+pub fn read_csv(path: &str, delimiter: u8) -> Result<Vec<Navaid>, Box<dyn Error>> {
+    let mut reader = csv::ReaderBuilder::new().delimiter(delimiter).from_reader(input::open(path)?);
+    let mut navaids = vec![];
+    for record in reader.deserialize() {
+        let record: NavaidRecord = record?;
+        navaids.push(Navaid {
+            latitude: Coordinate::parse_decimal(&record.latitude).map_err(|e| e.in_record(&record.ident))?,
+            longitude: Coordinate::parse_decimal(&record.longitude).map_err(|e| e.in_record(&record.ident))?,
+            ident: record.ident.trim().to_uppercase(),
+            name: record.name,
+            navaid_type: record.navaid_type.trim().to_uppercase().replace(['-', '/'], ""),
+            elevation: record.elevation,
+            frequency: record.frequency,
+            channel: record.channel.filter(|c| !c.trim().is_empty()),
+            range: record.range,
+        });
+    }
+    Ok(navaids)
+}
+
+impl Navaid {
+    /// Little Navmap userpoint type; anything it has no icon for becomes a plain waypoint.
+    fn userpoint_type(&self) -> &str {
+        match self.navaid_type.as_str() {
+            "VOR" | "VORDME" | "VORTAC" | "TACAN" | "DME" | "NDB" => &self.navaid_type,
+            _ => "Waypoint",
+        }
+    }
+
+    pub fn to_waypoint(&self, source: &str) -> Result<Waypoint, DataError> {
+        let waypoint = Waypoint::builder(&self.name)
+            .waypoint_type(self.userpoint_type())
+            .ident(&self.ident)
+            .coordinates(self.latitude, self.longitude)
+            .elevation(self.elevation)
+            .region(region::POLAND)
+            .import_filename(source);
+        let mut description = vec![];
+        if let Some(frequency) = self.frequency {
+            description.push(match self.navaid_type.as_str() {
+                "NDB" => format!("{} kHz", frequency),
+                _ => format!("{:.2} MHz", frequency),
+            });
+        }
+        if let Some(channel) = &self.channel {
+            description.push(format!("CH {}", channel.trim()));
+        }
+        if let Some(range) = self.range {
+            description.push(format!("range {} NM", range));
+        }
+        let waypoint = match description.is_empty() {
+            true => waypoint,
+            false => waypoint.description(description.join(", ")),
+        };
+        waypoint.build().map_err(|e| e.in_record(&self.ident))
+    }
+}
+
+pub struct NavaidCsv;
+
+impl InputFormat for NavaidCsv {
+    fn name(&self) -> &'static str {
+        "navaid-csv"
+    }
+
+    fn description(&self) -> &'static str {
+        "VOR/NDB/DME list as CSV"
+    }
+
+    fn fields(&self) -> Fields {
+        Fields { elevation: true, description: true, ..Fields::default() }
+    }
+
+    fn read(&self, path: &str, options: &ReadOptions) -> Result<Records, Box<dyn Error>> {
+        let navaids = read_csv(path, options.delimiter)?;
+        let waypoints = navaids.iter().map(|n| n.to_waypoint(path)).collect::<Result<Vec<_>, _>>()?;
+        Ok(Box::new(waypoints.into_iter()))
+    }
+}
@@ -0,0 +1,244 @@
+//! Checking airfields against current NOTAMs, so closed ones are marked
+//! instead of being exported as usable. Only waypoints with a known ICAO
+//! code are looked up.
+
+use crate::download::{is_transient, Backoff, RateLimit};
+use crate::enrich::LookupError;
+use crate::lang::Lang;
+use crate::Waypoint;
+use rayon::prelude::*;
+use serde::Deserialize;
+use std::fmt;
+
+/// The FAA NOTAM API, which also carries international NOTAMs.
+pub const FAA_NOTAM_URL: &str = "https://external-api.faa.gov/notamapi/v1/notams";
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Notam {
+    /// E.g. `A1234/26`.
+    pub number: String,
+    pub location: String,
+    /// The five-letter Q code, e.g. `QFALC`, when the NOTAM has one.
+    pub q_code: Option<String>,
+    pub text: String,
+}
+
+impl Notam {
+    /// Whether the NOTAM closes the aerodrome: Q code `QFALC` or `AD CLSD`
+    /// in the text.
+    pub fn closes_aerodrome(&self) -> bool {
+        let text = self.text.to_uppercase();
+        self.q_code.as_deref().is_some_and(|q| q.eq_ignore_ascii_case("QFALC"))
+            || text.contains("AD CLSD")
+            || text.contains("AD CLOSED")
+    }
+}
+
+/// Where current NOTAMs come from.
+pub trait NotamSource: Sync {
+    fn notams(&self, icao: &str) -> Result<Vec<Notam>, LookupError>;
+}
+
+pub fn is_icao(code: &str) -> bool {
+    code.len() == 4 && code.chars().all(|c| c.is_ascii_uppercase())
+}
+
+/// The ident, when it is an ICAO code.
+pub fn icao_ident(waypoint: &Waypoint) -> Option<String> {
+    is_icao(&waypoint.ident).then(|| waypoint.ident.clone())
+}
+
+/// Tags the waypoints `icao` finds a code for when they have NOTAMs:
+/// `closed`, with the closing NOTAM at the start of the description, when
+/// one closes the aerodrome, otherwise `notam` with the NOTAM numbers
+/// appended. Failed queries leave the waypoint unchanged and are returned
+/// with its index.
+pub fn flag(
+    waypoints: &mut [Waypoint],
+    source: &impl NotamSource,
+    icao: impl Fn(&Waypoint) -> Option<String> + Sync,
+    lang: Lang,
+) -> Vec<(usize, LookupError)> {
+    waypoints
+        .par_iter_mut()
+        .enumerate()
+        .filter_map(|(i, waypoint)| {
+            let code = icao(waypoint)?;
+            match source.notams(&code) {
+                Ok(notams) => {
+                    apply(&notams, waypoint, lang);
+                    None
+                }
+                Err(e) => Some((i, e)),
+            }
+        })
+        .collect()
+}
+
+fn apply(notams: &[Notam], waypoint: &mut Waypoint, lang: Lang) {
+    let description = waypoint.description.take().filter(|d| !d.is_empty());
+    if let Some(closing) = notams.iter().find(|n| n.closes_aerodrome()) {
+        waypoint.add_tag("closed");
+        let note = lang.text(format!("CLOSED per NOTAM {}", closing.number), format!("ZAMKNIĘTE wg NOTAM {}", closing.number));
+        waypoint.description = Some(match description {
+            Some(d) => format!("{}; {}", note, d),
+            None => note,
+        });
+    } else if !notams.is_empty() {
+        waypoint.add_tag("notam");
+        let numbers: Vec<&str> = notams.iter().map(|n| n.number.as_str()).collect();
+        let note = format!("NOTAM {}", numbers.join(", "));
+        waypoint.description = Some(match description {
+            Some(d) => format!("{}; {}", d, note),
+            None => note,
+        });
+    } else {
+        waypoint.description = description;
+    }
+}
+
+#[derive(Deserialize)]
+struct FaaResponse {
+    #[serde(default)]
+    items: Vec<FaaItem>,
+}
+
+#[derive(Deserialize)]
+struct FaaItem {
+    properties: FaaProperties,
+}
+
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct FaaProperties {
+    #[serde(rename = "coreNOTAMData")]
+    core: FaaCore,
+}
+
+#[derive(Deserialize)]
+struct FaaCore {
+    notam: FaaNotam,
+}
+
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct FaaNotam {
+    #[serde(default)]
+    number: String,
+    #[serde(default)]
+    icao_location: Option<String>,
+    #[serde(default)]
+    location: String,
+    #[serde(default)]
+    selection_code: Option<String>,
+    #[serde(default)]
+    text: String,
+}
+
+/// NOTAMs from the FAA NOTAM API, which needs a client id and secret.
+pub struct FaaNotams {
+    url: String,
+    client_id: String,
+    client_secret: String,
+    agent: ureq::Agent,
+    backoff: Backoff,
+    limit: RateLimit,
+}
+
+impl FaaNotams {
+    pub fn new(client_id: impl Into<String>, client_secret: impl Into<String>) -> Self {
+        FaaNotams {
+            url: FAA_NOTAM_URL.to_owned(),
+            client_id: client_id.into(),
+            client_secret: client_secret.into(),
+            agent: ureq::Agent::new_with_defaults(),
+            backoff: Backoff::default(),
+            limit: RateLimit::new(None, None),
+        }
+    }
+
+    pub fn url(mut self, url: impl Into<String>) -> Self {
+        self.url = url.into();
+        self
+    }
+
+    /// The agent to send requests with, e.g. one set up with a proxy.
+    pub fn agent(mut self, agent: ureq::Agent) -> Self {
+        self.agent = agent;
+        self
+    }
+
+    pub fn backoff(mut self, backoff: Backoff) -> Self {
+        self.backoff = backoff;
+        self
+    }
+
+    pub fn rate_limit(mut self, limit: RateLimit) -> Self {
+        self.limit = limit;
+        self
+    }
+
+    /// Where NOTAMs are queried.
+    pub fn address(&self) -> &str {
+        &self.url
+    }
+
+    fn query(&self, icao: &str) -> Result<Vec<Notam>, LookupError> {
+        let mut response = self
+            .agent
+            .get(&self.url)
+            .query("icaoLocation", icao)
+            .query("pageSize", "1000")
+            .header("client_id", &self.client_id)
+            .header("client_secret", &self.client_secret)
+            .call()?;
+        let response: FaaResponse = serde_json::from_reader(response.body_mut().as_reader())?;
+        Ok(response
+            .items
+            .into_iter()
+            .map(|item| {
+                let notam = item.properties.core.notam;
+                Notam {
+                    number: notam.number,
+                    location: notam.icao_location.unwrap_or(notam.location),
+                    q_code: notam.selection_code,
+                    text: notam.text,
+                }
+            })
+            .collect())
+    }
+}
+
+/// A failed query, saying which aerodrome it was for.
+#[derive(Debug)]
+struct QueryError {
+    icao: String,
+    source: LookupError,
+    attempts: u32,
+}
+
+impl fmt::Display for QueryError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "NOTAMs for {}: {}", self.icao, self.source)?;
+        if self.attempts > 1 {
+            write!(f, " (gave up after {} attempts)", self.attempts)?;
+        }
+        Ok(())
+    }
+}
+
+impl std::error::Error for QueryError {}
+
+impl NotamSource for FaaNotams {
+    fn notams(&self, icao: &str) -> Result<Vec<Notam>, LookupError> {
+        let attempt = || {
+            self.limit
+                .run(|| self.query(icao))
+                .map_err(|source| QueryError { icao: icao.to_owned(), source, attempts: 1 })
+        };
+        let retryable = |e: &QueryError| e.source.downcast_ref::<ureq::Error>().is_some_and(is_transient);
+        self.backoff
+            .run(attempt, retryable)
+            .map_err(|(e, attempts)| QueryError { attempts, ..e }.into())
+    }
+}
@@ -0,0 +1,177 @@
+use crate::elevation::FEET_PER_METRE;
+use crate::format::{Fields, InputFormat, ReadOptions, Records};
+use crate::xml::{Element, Elements};
+use crate::{input, region, Coordinate, Error as DataError, Waypoint};
+use std::error::Error;
+
+/// A single eTOD obstacle. Elevation (AMSL) and height (AGL) are in feet.
+#[derive(Debug, PartialEq)]
+pub struct Obstacle {
+    pub ident: String,
+    pub obstacle_type: String,
+    pub latitude: f32,
+    pub longitude: f32,
+    pub elevation: Option<f32>,
+    pub height: Option<f32>,
+    pub lighted: Option<bool>,
+}
+
+/// `value` in feet, given in `unit` (`M` or `FT`, any case); no unit is feet.
+fn in_feet(value: f32, unit: Option<&str>) -> f32 {
+    match unit {
+        Some(unit) if unit.trim().eq_ignore_ascii_case("m") => value * FEET_PER_METRE,
+        _ => value,
+    }
+}
+
+/// A header such as `Elevation (m)` or `Height [ft]` split into its name and unit.
+fn header_unit(header: &str) -> (String, Option<String>) {
+    let header = header.trim();
+    match header.strip_suffix([')', ']']).and_then(|h| h.rsplit_once(['(', '['])) {
+        Some((name, unit)) => (name.trim().to_lowercase(), Some(unit.trim().to_owned())),
+        None => (header.to_lowercase(), None),
+    }
+}
+
+/// Reads an eTOD CSV export with `Identifier,Type,Latitude,Longitude,Elevation,Height,Lighting`
+/// columns. Elevation and height are feet unless the header gives a unit,
+/// as in `Elevation (m)`, or a `Unit` column says `M` for the row.
+pub fn read_csv(path: &str, delimiter: u8) -> Result<Vec<Obstacle>, Box<dyn Error>> {
+    let mut reader = csv::ReaderBuilder::new().delimiter(delimiter).from_reader(input::open(path)?);
+    let headers: Vec<(String, Option<String>)> = reader.headers()?.iter().map(header_unit).collect();
+    let column = |name: &str| headers.iter().position(|(h, _)| h == name);
+    let required = |name: &str| column(name).ok_or_else(|| format!("{}: no {} column", path, name));
+    let (ident, kind) = (required("identifier")?, required("type")?);
+    let (latitude, longitude) = (required("latitude")?, required("longitude")?);
+    let (elevation, height, lighting) = (column("elevation"), column("height"), column("lighting"));
+    let unit = column("unit").or_else(|| column("uom"));
+    let mut obstacles = vec![];
+    for record in reader.records() {
+        let record = record?;
+        let field = |i: usize| record.get(i).map(str::trim).unwrap_or_default();
+        let measure = |i: Option<usize>| -> Result<Option<f32>, Box<dyn Error>> {
+            let Some(i) = i.filter(|&i| !field(i).is_empty()) else {
+                return Ok(None);
+            };
+            let value = field(i).parse::<f32>().map_err(|_| format!("{}: invalid number {:?}", field(ident), field(i)))?;
+            Ok(Some(in_feet(value, headers[i].1.as_deref().or(unit.map(field)))))
+        };
+        let id = field(ident);
+        obstacles.push(Obstacle {
+            latitude: Coordinate::parse_decimal(field(latitude)).map_err(|e| e.in_record(id))?,
+            longitude: Coordinate::parse_decimal(field(longitude)).map_err(|e| e.in_record(id))?,
+            ident: id.to_owned(),
+            obstacle_type: field(kind).to_owned(),
+            elevation: measure(elevation)?,
+            height: measure(height)?,
+            lighted: lighting.map(field).filter(|l| !l.is_empty()).map(|l| {
+                let l = l.to_uppercase();
+                l == "Y" || l == "YES" || l == "TAK"
+            }),
+        });
+    }
+    Ok(obstacles)
+}
+
+fn feet(node: &Element) -> Option<f32> {
+    let value = node.text.trim().parse::<f32>().ok()?;
+    Some(in_feet(value, node.attribute("uom")))
+}
+
+/// Reads the `VerticalStructure` features of an AIXM 5.1 obstacle dataset.
+pub fn read_aixm(path: &str) -> Result<Vec<Obstacle>, Box<dyn Error>> {
+    let mut obstacles = vec![];
+    for structure in Elements::new(input::open(path)?, "VerticalStructure") {
+        let structure = structure?;
+        let Some(pos) = structure.descendant("pos").map(|p| p.text.as_str()) else {
+            continue;
+        };
+        let mut pos = pos.split_whitespace().map(str::parse::<f32>);
+        let (Some(Ok(latitude)), Some(Ok(longitude))) = (pos.next(), pos.next()) else {
+            return Err(format!("invalid gml:pos in {:?}", structure.attribute("id")).into());
+        };
+        let text = |name: &str| structure.descendant(name).map(|n| n.text.trim());
+        obstacles.push(Obstacle {
+            ident: text("name")
+                .or_else(|| text("identifier"))
+                .unwrap_or_default()
+                .to_owned(),
+            obstacle_type: text("type").unwrap_or("OTHER").to_owned(),
+            latitude,
+            longitude,
+            elevation: structure.descendant("elevation").and_then(feet),
+            height: structure.descendant("verticalExtent").and_then(feet),
+            lighted: text("lighted").map(|l| l == "YES"),
+        });
+    }
+    Ok(obstacles)
+}
+
+impl Obstacle {
+    /// The obstacle as a waypoint; without an ident, the name stands in.
+    pub fn to_waypoint(&self, source: &str) -> Result<Waypoint, DataError> {
+        let mut waypoint = Waypoint::builder(format!("{} {}", self.obstacle_type, self.ident))
+            .waypoint_type("Obstacle")
+            .coordinates(self.latitude, self.longitude)
+            .elevation(self.elevation)
+            .region(region::POLAND)
+            .import_filename(source);
+        if !self.ident.is_empty() {
+            waypoint = waypoint.ident(&self.ident);
+        }
+        let mut description = vec![];
+        if let Some(height) = self.height {
+            description.push(format!("Height {} ft AGL", height.round()));
+        }
+        if let Some(lighted) = self.lighted {
+            description.push(if lighted { "lighted" } else { "unlit" }.to_owned());
+        }
+        if !description.is_empty() {
+            waypoint = waypoint.description(description.join(", "));
+        }
+        waypoint.build().map_err(|e| e.in_record(&self.ident))
+    }
+}
+
+pub struct EtodCsv;
+
+impl InputFormat for EtodCsv {
+    fn name(&self) -> &'static str {
+        "etod-csv"
+    }
+
+    fn description(&self) -> &'static str {
+        "eTOD obstacle list as CSV"
+    }
+
+    fn fields(&self) -> Fields {
+        Fields { elevation: true, ..Fields::default() }
+    }
+
+    fn read(&self, path: &str, options: &ReadOptions) -> Result<Records, Box<dyn Error>> {
+        let obstacles = read_csv(path, options.delimiter)?;
+        let waypoints = obstacles.iter().map(|o| o.to_waypoint(path)).collect::<Result<Vec<_>, _>>()?;
+        Ok(Box::new(waypoints.into_iter()))
+    }
+}
+
+pub struct EtodAixm;
+
+impl InputFormat for EtodAixm {
+    fn name(&self) -> &'static str {
+        "etod-aixm"
+    }
+
+    fn description(&self) -> &'static str {
+        "eTOD obstacles as AIXM 5.1 VerticalStructure features"
+    }
+
+    fn fields(&self) -> Fields {
+        Fields { elevation: true, ..Fields::default() }
+    }
+
+    fn read(&self, path: &str, _: &ReadOptions) -> Result<Records, Box<dyn Error>> {
+        let waypoints = read_aixm(path)?.iter().map(|o| o.to_waypoint(path)).collect::<Result<Vec<_>, _>>()?;
+        Ok(Box::new(waypoints.into_iter()))
+    }
+}
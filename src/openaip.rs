@@ -0,0 +1,195 @@
+//! Airports and heliports of one country from the openAIP core API, which
+//! needs an API key and hands out results a page at a time.
+
+use crate::download::{is_transient, Backoff, RateLimit};
+use crate::elevation::FEET_PER_METRE;
+use crate::{glider, region, ultralight, Error as DataError, Waypoint};
+use rayon::prelude::*;
+use serde::Deserialize;
+use std::error::Error;
+use tracing::warn;
+
+pub const OPENAIP_URL: &str = "https://api.core.openaip.net/api/airports";
+
+/// The most results the API returns per page.
+const PAGE_SIZE: u32 = 1000;
+
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct Page {
+    #[serde(default)]
+    total_pages: u32,
+    #[serde(default)]
+    items: Vec<Airport>,
+}
+
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct Airport {
+    name: String,
+    icao_code: Option<String>,
+    #[serde(rename = "type")]
+    airport_type: u8,
+    geometry: Geometry,
+    elevation: Option<Elevation>,
+    #[serde(default)]
+    frequencies: Vec<Frequency>,
+}
+
+#[derive(Deserialize)]
+struct Geometry {
+    /// Longitude, latitude.
+    coordinates: (f32, f32),
+}
+
+#[derive(Deserialize)]
+struct Elevation {
+    value: f32,
+    /// 0 is metres, 1 feet.
+    unit: u8,
+}
+
+#[derive(Deserialize)]
+struct Frequency {
+    value: String,
+    name: Option<String>,
+}
+
+/// The userpoint type for an openAIP airport `type`.
+fn userpoint_type(code: u8) -> &'static str {
+    match code {
+        4 | 7 => "Helipad",
+        10 => "Seaport",
+        0 | 3 | 5 | 9 => "Airport",
+        _ => "Airstrip",
+    }
+}
+
+impl Airport {
+    /// The airport as a waypoint in the region of its ICAO code, or else
+    /// `country_region`.
+    fn into_waypoint(self, country_region: Option<&str>) -> Option<Waypoint> {
+        let icao = self.icao_code.filter(|i| region::of_icao(i).is_some());
+        let elevation = self.elevation.map(|e| match e.unit {
+            0 => (e.value * FEET_PER_METRE).round(),
+            _ => e.value,
+        });
+        let (longitude, latitude) = self.geometry.coordinates;
+        let mut waypoint = Waypoint::builder(&self.name)
+            .waypoint_type(userpoint_type(self.airport_type))
+            .coordinates(latitude, longitude)
+            .elevation(elevation);
+        if let Some(region) = icao.as_deref().and_then(region::of_icao).or(country_region) {
+            waypoint = waypoint.region(region);
+        }
+        if let Some(icao) = &icao {
+            waypoint = waypoint.ident(icao);
+        }
+        let mut waypoint = match waypoint.build() {
+            Ok(waypoint) => waypoint,
+            Err(e) => {
+                warn!("skipping openAIP airport {:?}: {}", self.name, e);
+                return None;
+            }
+        };
+        match self.airport_type {
+            1 => waypoint.add_tag(glider::GLIDER_TAG),
+            5 => waypoint.add_tag("military"),
+            6 => waypoint.add_tag(ultralight::ULTRALIGHT_TAG),
+            8 => waypoint.add_tag("closed"),
+            _ => {}
+        }
+        let frequencies: Vec<String> = self
+            .frequencies
+            .into_iter()
+            .map(|f| match f.name {
+                Some(name) if !name.is_empty() => format!("{} {} MHz", name, f.value),
+                _ => format!("{} MHz", f.value),
+            })
+            .collect();
+        waypoint.description = (!frequencies.is_empty()).then(|| frequencies.join(", "));
+        Some(waypoint)
+    }
+}
+
+/// The openAIP airports API. Pages after the first are fetched in
+/// parallel, within the [`RateLimit`].
+pub struct OpenAip {
+    url: String,
+    api_key: String,
+    agent: ureq::Agent,
+    backoff: Backoff,
+    limit: RateLimit,
+}
+
+impl OpenAip {
+    pub fn new(api_key: impl Into<String>) -> Self {
+        OpenAip {
+            url: OPENAIP_URL.to_owned(),
+            api_key: api_key.into(),
+            agent: ureq::Agent::new_with_defaults(),
+            backoff: Backoff::default(),
+            limit: RateLimit::new(None, None),
+        }
+    }
+
+    pub fn url(mut self, url: impl Into<String>) -> Self {
+        self.url = url.into();
+        self
+    }
+
+    /// The agent to send requests with, e.g. one set up with a proxy.
+    pub fn agent(mut self, agent: ureq::Agent) -> Self {
+        self.agent = agent;
+        self
+    }
+
+    pub fn backoff(mut self, backoff: Backoff) -> Self {
+        self.backoff = backoff;
+        self
+    }
+
+    pub fn rate_limit(mut self, limit: RateLimit) -> Self {
+        self.limit = limit;
+        self
+    }
+
+    fn page(&self, country: &str, page: u32) -> Result<Page, DataError> {
+        let request = || {
+            let mut response = self
+                .agent
+                .get(&self.url)
+                .query("country", country)
+                .query("page", page.to_string())
+                .query("limit", PAGE_SIZE.to_string())
+                .header("x-openaip-api-key", &self.api_key)
+                .call()
+                .map_err(|e| DataError::download(&self.url, e))?;
+            response.body_mut().read_to_string().map_err(|e| DataError::download(&self.url, e))
+        };
+        let attempt = || self.limit.run(request);
+        let retryable = |e: &DataError| matches!(e, DataError::Download { source, .. } if is_transient(source));
+        let body = self.backoff.run(attempt, retryable).map_err(|(e, attempts)| match e {
+            DataError::Download { url, source, .. } => DataError::Download { url, source, attempts },
+            e => e,
+        })?;
+        Ok(serde_json::from_str(&body)?)
+    }
+
+    /// Every airport and heliport in the ISO `country`, going through all
+    /// the pages. Airports without an ICAO code are put in the country's
+    /// region; those with an unusable name or position are skipped with a
+    /// warning.
+    pub fn airports(&self, country: &str) -> Result<Vec<Waypoint>, Box<dyn Error>> {
+        let country_region = region::of_country(country);
+        let first = self.page(country, 1)?;
+        // An empty page means there are no more, whatever the total says.
+        let last = if first.items.is_empty() { 1 } else { first.total_pages };
+        let rest: Vec<Page> = (2..=last).into_par_iter().map(|page| self.page(country, page)).collect::<Result<_, _>>()?;
+        Ok(std::iter::once(first)
+            .chain(rest)
+            .flat_map(|page| page.items)
+            .filter_map(|airport| airport.into_waypoint(country_region))
+            .collect())
+    }
+}
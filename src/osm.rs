@@ -0,0 +1,246 @@
+//! Helipads and glider sites mapped in OpenStreetMap, fetched with preset
+//! Overpass queries or read from a saved Overpass JSON response.
+
+use crate::elevation::FEET_PER_METRE;
+use crate::format::{Fields, InputFormat, ReadOptions, Records};
+use crate::{glider, input, region, Waypoint};
+use clap::ValueEnum;
+use serde::Deserialize;
+use std::collections::HashMap;
+use std::error::Error;
+use tracing::warn;
+
+pub const OVERPASS_URL: &str = "https://overpass-api.de/api/interpreter";
+
+/// Which sites to ask Overpass for.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, ValueEnum)]
+pub enum Preset {
+    /// `aeroway=helipad` and `aeroway=heliport`
+    Helipads,
+    /// Aerodromes and strips used for gliding
+    Gliders,
+    /// Both of the above
+    All,
+}
+
+impl Preset {
+    /// The Overpass QL query for the preset's sites in the ISO `country`,
+    /// with ways and relations reduced to their centres.
+    pub fn query(self, country: &str) -> String {
+        let helipads = r#"nwr["aeroway"~"^(helipad|heliport)$"](area.country);"#;
+        let gliders = concat!(
+            r#"nwr["aeroway"~"^(aerodrome|airstrip)$"]["aerodrome:type"="gliding"](area.country);"#,
+            r#"nwr["aeroway"~"^(aerodrome|airstrip)$"]["sport"="gliding"](area.country);"#,
+            r#"nwr["aeroway"~"^(aerodrome|airstrip)$"]["aerodrome"="gliding"](area.country);"#,
+        );
+        let statements = match self {
+            Preset::Helipads => helipads.to_owned(),
+            Preset::Gliders => gliders.to_owned(),
+            Preset::All => format!("{}{}", helipads, gliders),
+        };
+        format!(
+            r#"[out:json][timeout:180];area["ISO3166-1"="{}"][admin_level=2]->.country;({});out center tags;"#,
+            country.replace('"', ""),
+            statements
+        )
+    }
+}
+
+#[derive(Deserialize)]
+struct Response {
+    #[serde(default)]
+    elements: Vec<Element>,
+}
+
+#[derive(Deserialize)]
+struct Element {
+    #[serde(rename = "type")]
+    element_type: String,
+    id: u64,
+    lat: Option<f32>,
+    lon: Option<f32>,
+    /// Where `out center` puts the position of ways and relations.
+    center: Option<Center>,
+    #[serde(default)]
+    tags: HashMap<String, String>,
+}
+
+#[derive(Deserialize)]
+struct Center {
+    lat: f32,
+    lon: f32,
+}
+
+/// An OSM `ele`, metres unless it says feet, e.g. `212`, `212 m` or `700 ft`.
+fn elevation(value: &str) -> Option<f32> {
+    let value = value.trim().to_lowercase().replace(',', ".");
+    let feet = value.ends_with("ft") || value.ends_with('\'');
+    let number = value.trim_end_matches(|c: char| c.is_alphabetic() || c == '\'' || c.is_whitespace());
+    let number = number.parse::<f32>().ok()?;
+    Some(if feet { number } else { (number * FEET_PER_METRE).round() })
+}
+
+fn is_glider_site(tags: &HashMap<String, String>) -> bool {
+    ["aerodrome:type", "sport", "aerodrome"].iter().any(|key| tags.get(*key).is_some_and(|v| v == "gliding"))
+}
+
+impl Element {
+    /// The site as a waypoint in the region of its ICAO code, or else of
+    /// its `addr:country`, or else `country_region`.
+    fn into_waypoint(self, source: &str, country_region: Option<&str>) -> Option<Waypoint> {
+        let (latitude, longitude) = match (self.lat, self.lon, &self.center) {
+            (Some(lat), Some(lon), _) => (lat, lon),
+            (_, _, Some(center)) => (center.lat, center.lon),
+            _ => return None,
+        };
+        let tag = |key: &str| self.tags.get(key).map(|v| v.trim()).filter(|v| !v.is_empty());
+        let icao = tag("icao").filter(|i| region::of_icao(i).is_some());
+        let name = tag("name:pl")
+            .or_else(|| tag("name"))
+            .or(icao)
+            .or_else(|| tag("ref"))
+            .map(str::to_owned)
+            .unwrap_or_else(|| format!("OSM {} {}", self.element_type, self.id));
+        let helipad = matches!(tag("aeroway"), Some("helipad" | "heliport"));
+        let mut waypoint = Waypoint::builder(&name)
+            .waypoint_type(if helipad { "Helipad" } else { "Airstrip" })
+            .coordinates(latitude, longitude)
+            .elevation(tag("ele").and_then(elevation))
+            .import_filename(source);
+        let address_region = || tag("addr:country").and_then(region::of_country);
+        if let Some(region) = icao.and_then(region::of_icao).or_else(address_region).or(country_region) {
+            waypoint = waypoint.region(region);
+        }
+        if let Some(icao) = icao {
+            waypoint = waypoint.ident(icao);
+        }
+        let mut waypoint = match waypoint.build() {
+            Ok(waypoint) => waypoint,
+            Err(e) => {
+                warn!("skipping OSM {} {}: {}", self.element_type, self.id, e);
+                return None;
+            }
+        };
+        if is_glider_site(&self.tags) {
+            waypoint.add_tag(glider::GLIDER_TAG);
+        }
+        let description: Vec<String> = [
+            tag("surface").map(|s| format!("surface {}", s)),
+            tag("operator").map(str::to_owned),
+            Some(format!("OSM {} {}", self.element_type, self.id)),
+        ]
+        .into_iter()
+        .flatten()
+        .collect();
+        waypoint.description = Some(description.join("; "));
+        Some(waypoint)
+    }
+}
+
+/// The sites in an Overpass JSON response for the ISO `country`, or for
+/// Poland when the area queried is not known; elements without a position,
+/// as when the query lacks `out center`, are left out, and elements with an
+/// unusable name or position are skipped with a warning.
+pub fn parse(json: &str, source: &str, country: Option<&str>) -> Result<Vec<Waypoint>, Box<dyn Error>> {
+    let country_region = country.map_or(Some(region::POLAND), region::of_country);
+    let response: Response = serde_json::from_str(json)?;
+    Ok(response.elements.into_iter().filter_map(|e| e.into_waypoint(source, country_region)).collect())
+}
+
+/// Reads a saved Overpass JSON response.
+pub fn read_overpass(path: &str) -> Result<Vec<Waypoint>, Box<dyn Error>> {
+    let input = input::open(path)?;
+    parse(std::str::from_utf8(&input.get_ref()[..])?, path, None)
+}
+
+pub struct OsmOverpass;
+
+impl InputFormat for OsmOverpass {
+    fn name(&self) -> &'static str {
+        "osm-overpass"
+    }
+
+    fn description(&self) -> &'static str {
+        "OpenStreetMap helipads and glider sites as Overpass JSON"
+    }
+
+    fn fields(&self) -> Fields {
+        Fields { elevation: true, ..Fields::default() }
+    }
+
+    fn read(&self, path: &str, _: &ReadOptions) -> Result<Records, Box<dyn Error>> {
+        Ok(Box::new(read_overpass(path)?.into_iter()))
+    }
+}
+
+#[cfg(not(target_arch = "wasm32"))]
+pub use client::Overpass;
+
+#[cfg(not(target_arch = "wasm32"))]
+mod client {
+    use super::{parse, Preset, OVERPASS_URL};
+    use crate::download::{is_transient, Backoff};
+    use crate::{Error as DataError, Waypoint};
+    use std::error::Error;
+
+    /// Responses for a whole country run to tens of megabytes.
+    const BODY_LIMIT: u64 = 256 * 1024 * 1024;
+
+    /// An Overpass API endpoint.
+    pub struct Overpass {
+        url: String,
+        agent: ureq::Agent,
+        backoff: Backoff,
+    }
+
+    impl Default for Overpass {
+        fn default() -> Self {
+            Overpass { url: OVERPASS_URL.to_owned(), agent: ureq::Agent::new_with_defaults(), backoff: Backoff::default() }
+        }
+    }
+
+    impl Overpass {
+        pub fn url(mut self, url: impl Into<String>) -> Self {
+            self.url = url.into();
+            self
+        }
+
+        /// The agent to send requests with, e.g. one set up with a proxy.
+        pub fn agent(mut self, agent: ureq::Agent) -> Self {
+            self.agent = agent;
+            self
+        }
+
+        pub fn backoff(mut self, backoff: Backoff) -> Self {
+            self.backoff = backoff;
+            self
+        }
+
+        /// The raw JSON response to an Overpass QL query.
+        pub fn run(&self, query: &str) -> Result<String, Box<dyn Error>> {
+            let attempt = || {
+                let mut response = self
+                    .agent
+                    .post(&self.url)
+                    .send_form([("data", query)])
+                    .map_err(|e| DataError::download(&self.url, e))?;
+                response
+                    .body_mut()
+                    .with_config()
+                    .limit(BODY_LIMIT)
+                    .read_to_string()
+                    .map_err(|e| DataError::download(&self.url, e))
+            };
+            let retryable = |e: &DataError| matches!(e, DataError::Download { source, .. } if is_transient(source));
+            Ok(self.backoff.run(attempt, retryable).map_err(|(e, attempts)| match e {
+                DataError::Download { url, source, .. } => DataError::Download { url, source, attempts },
+                e => e,
+            })?)
+        }
+
+        /// The preset's sites in the ISO `country`.
+        pub fn sites(&self, preset: Preset, country: &str) -> Result<Vec<Waypoint>, Box<dyn Error>> {
+            parse(&self.run(&preset.query(country))?, &self.url, Some(country))
+        }
+    }
+}
@@ -0,0 +1,91 @@
+use serde::Serialize;
+use sha2::{Digest, Sha256};
+use std::error::Error;
+use std::fs::{self, File};
+use std::io::Write;
+use std::path::Path;
+use zip::write::SimpleFileOptions;
+use zip::{CompressionMethod, ZipWriter};
+
+const MANIFEST: &str = "manifest.json";
+
+/// A file written during the run and how many records it holds.
+#[derive(Debug, Serialize)]
+pub struct Generated {
+    pub file: String,
+    pub records: usize,
+}
+
+#[derive(Debug, Serialize)]
+struct PackedFile {
+    file: String,
+    records: usize,
+    sha256: String,
+}
+
+#[derive(Debug, Serialize)]
+struct SourceFile {
+    file: String,
+    sha256: String,
+}
+
+#[derive(Debug, Serialize)]
+struct Manifest<'a> {
+    generator: String,
+    generated_at: String,
+    sources: Vec<SourceFile>,
+    files: &'a [PackedFile],
+}
+
+pub fn sha256(bytes: &[u8]) -> String {
+    Sha256::digest(bytes).iter().map(|b| format!("{:02x}", b)).collect()
+}
+
+fn file_name(path: &str) -> String {
+    Path::new(path)
+        .file_name()
+        .map_or_else(|| path.to_owned(), |name| name.to_string_lossy().into_owned())
+}
+
+/// Bundles the generated files, stored by file name, into a zip archive
+/// together with a `manifest.json` describing them and the sources they were
+/// built from. Sources are given as the name to record, e.g. the URL a file
+/// was downloaded from, and the local file to checksum. The archive's own
+/// checksum goes to `<path>.sha256`, in the format `sha256sum -c` reads.
+pub fn write(path: &str, generated: &[Generated], sources: &[(&str, &str)]) -> Result<(), Box<dyn Error>> {
+    let options = SimpleFileOptions::default().compression_method(CompressionMethod::Deflated);
+    let mut zip = ZipWriter::new(File::create(path)?);
+    let mut files = vec![];
+    for file in generated {
+        let contents = fs::read(&file.file)?;
+        zip.start_file(file_name(&file.file), options)?;
+        zip.write_all(&contents)?;
+        files.push(PackedFile { file: file_name(&file.file), records: file.records, sha256: sha256(&contents) });
+    }
+    let manifest = Manifest {
+        generator: format!("polish-airports {}", env!("CARGO_PKG_VERSION")),
+        generated_at: chrono::Utc::now().to_rfc3339_opts(chrono::SecondsFormat::Secs, true),
+        sources: sources
+            .iter()
+            .map(|(name, path)| Ok(SourceFile { file: file_name(name), sha256: sha256(&fs::read(path)?) }))
+            .collect::<Result<_, std::io::Error>>()?,
+        files: &files,
+    };
+    zip.start_file(MANIFEST, options)?;
+    zip.write_all(serde_json::to_string_pretty(&manifest)?.as_bytes())?;
+    zip.finish()?;
+    let checksum = format!("{}  {}\n", sha256(&fs::read(path)?), file_name(path));
+    fs::write(format!("{}.sha256", path), checksum)?;
+    Ok(())
+}
+
+/// Signs the archive with a minisign secret key, writing `<path>.minisig`
+/// so it can be checked with `minisign -Vm <path> -p <public key>`. The key
+/// password is taken from `POLISH_AIRPORTS_KEY_PASSWORD`, or asked for.
+pub fn sign(path: &str, key: &str) -> Result<(), Box<dyn Error>> {
+    let key = minisign::SecretKey::from_file(key, std::env::var("POLISH_AIRPORTS_KEY_PASSWORD").ok())?;
+    let trusted_comment = format!("file:{}", file_name(path));
+    let signature = minisign::sign(None, &key, File::open(path)?, Some(&trusted_comment), None)?;
+    fs::write(format!("{}.minisig", path), signature.to_string())?;
+    Ok(())
+}
@@ -0,0 +1,1157 @@
+//! The whole conversion a run of the program performs, for callers that
+//! want more than the single readers and writers: [`Conversion`] reads an
+//! input, runs the post-processing passes over its records and writes every
+//! requested output, and [`Merge`] combines several datasets into one
+//! userpoints CSV. [`OutputStyle`] holds what both need to know about the
+//! files they write.
+
+use crate::audit::Audit;
+use crate::cache::RecordCache;
+use crate::circuit::{PatternGeometry, PatternSide};
+use crate::columns::{self, Columns, NumberFormat};
+use crate::conflict::ConflictResolver;
+use crate::dedupe::{self, DedupeMode};
+use crate::dem::{Dem, MissingElevations};
+use crate::download::Downloads;
+use crate::elevation::ElevationUnit;
+use crate::encoding::Encoding;
+use crate::filter::Filter;
+use crate::format::{self, Limits, OutputFormat, ReadOptions, WriteOptions};
+use crate::idents::{self, DuplicatePolicy, IcaoIdent};
+use crate::lang::Lang;
+use crate::line_ending::LineEnding;
+use crate::mapping::Mapping;
+use crate::matching::SiteMatcher;
+use crate::notam::{self, FaaNotams};
+use crate::region::{self, RegionRule};
+use crate::runway::{self, RunwayEnd};
+use crate::spill::SpillMerge;
+use crate::split::{self, SplitKey};
+use crate::summary::{Summary, Written};
+use crate::timing::Stopwatch;
+use crate::xml::{Element, Elements};
+use crate::{
+    airfield_waypoint, ask, backup, enrich, geo, glider, input, kml, merge, metar, package, parse_dataset, preview,
+    progress, read_any, read_dataset, review, stream_any, tagging, template, ultralight, validate, AirfieldReader,
+    Waypoint, WaypointBuilder,
+};
+use csv::WriterBuilder;
+use rayon::prelude::*;
+use serde::{Deserialize, Serialize};
+use std::collections::{HashMap, HashSet};
+use std::error::Error;
+use std::num::NonZeroUsize;
+use std::path::{Path, PathBuf};
+use std::{fs, thread};
+use tracing::{debug, trace};
+
+/// Address of the AIP VFR aerodrome chart, with `{icao}` replaced by the
+/// aerodrome's code.
+pub const AIP_VFR_CHART_URL: &str = "https://www.ais.pansa.pl/aip-vfr/ad/{icao}.pdf";
+
+/// How generated files are written.
+#[derive(Debug, Clone)]
+pub struct OutputStyle {
+    /// Field separator of CSV files read and written.
+    pub delimiter: u8,
+    /// Character encoding of the CSV output.
+    pub encoding: Encoding,
+    /// Line endings of every generated text file.
+    pub line_ending: LineEnding,
+    /// Whether CSV output starts with the column header row.
+    pub header: bool,
+    /// Exact first line of the CSV output, written instead of the column
+    /// header.
+    pub header_line: Option<String>,
+    pub numbers: NumberFormat,
+    /// How many timestamped backups to keep of each userpoints CSV that is
+    /// overwritten; 0 keeps none.
+    pub backups: usize,
+}
+
+impl Default for OutputStyle {
+    fn default() -> Self {
+        OutputStyle {
+            delimiter: b',',
+            encoding: Encoding::default(),
+            line_ending: LineEnding::default(),
+            header: true,
+            header_line: None,
+            numbers: NumberFormat::default(),
+            backups: 5,
+        }
+    }
+}
+
+impl OutputStyle {
+    /// Whether CSV output starts with the column header row.
+    fn column_header(&self) -> bool {
+        self.header && self.header_line.is_none()
+    }
+
+    /// CSV text with the custom header line, if any, put first.
+    fn csv_text(&self, records: Vec<u8>) -> Result<String, Box<dyn Error>> {
+        let records = String::from_utf8(records)?;
+        Ok(match &self.header_line {
+            Some(line) => format!("{}{}{}", line, self.line_ending.apply("\n"), records),
+            None => records,
+        })
+    }
+
+    /// The records of a userpoints CSV written in this style.
+    fn read(&self, path: &str) -> Result<Vec<Waypoint>, Box<dyn Error>> {
+        let text = self.encoding.decode(&fs::read(path)?);
+        let records = match &self.header_line {
+            Some(_) => text.split_once('\n').map_or("", |(_, rest)| rest),
+            None => &text,
+        };
+        Ok(parse_dataset(records.as_bytes(), self.delimiter, self.column_header())?)
+    }
+}
+
+/// A conversion of one input into userpoints CSV and any other outputs.
+/// [`Conversion::new`] sets everything but the input and output to what the
+/// command line does by default; the fields switch on the passes and
+/// outputs, which run in the order they are listed in.
+pub struct Conversion {
+    /// Input file to read, or an http(s) URL to download it from.
+    pub input: String,
+    /// Name of the input format, one of [`format::inputs`] or `skydemon`.
+    pub input_format: String,
+    /// Userpoints CSV to write.
+    pub output: String,
+    pub style: OutputStyle,
+    /// Where downloads are cached and how they are fetched.
+    pub downloads: Downloads,
+    /// Draw progress bars on stderr when it is a terminal.
+    pub progress: bool,
+    /// Reuse per-airfield results stored in this file for SkyDemon records
+    /// that have not changed since the previous run.
+    pub cache: Option<String>,
+    /// Existing userpoints whose idents generated idents must not reuse.
+    pub airfield_dataset: Option<String>,
+    /// Also emit a waypoint at each runway threshold, e.g. `EPXX27`.
+    pub runway_thresholds: bool,
+    /// Emit fixes on each runway's extended centerline at these distances
+    /// (NM).
+    pub centerline_fixes: Vec<f64>,
+    /// Emit overhead, downwind and base join points for each runway.
+    pub circuit_points: bool,
+    /// Distance of the downwind leg from the runway centerline (NM).
+    pub circuit_offset: f64,
+    pub circuit_side: PatternSide,
+    /// Build airfield descriptions from a template; see [`template`].
+    pub description_template: Option<String>,
+    /// Add the chart link made from this template, such as
+    /// [`AIP_VFR_CHART_URL`], to airfields with a Polish ICAO code.
+    pub aip_links: Option<String>,
+    /// Language of generated description text.
+    pub lang: Lang,
+    /// Ask on the terminal about records without an elevation and positions
+    /// that make sense read another way.
+    pub ask: bool,
+    /// Directory of SRTM .hgt tiles to suggest elevations from when asking.
+    pub dem: Option<PathBuf>,
+    /// Fail instead of warning when a Polish record lands outside Poland.
+    pub strict_geo: bool,
+    /// Normalize idents claiming to be Polish ICAO codes and tag malformed
+    /// ones.
+    pub check_icao: bool,
+    /// Tag glider sites, their winch launches and aerotows.
+    pub glider: bool,
+    /// CSV of glider sites the source data says nothing about; needs
+    /// `glider`.
+    pub glider_sites: Option<String>,
+    /// Tag fields likely used by ultralights.
+    pub ultralight: bool,
+    /// Rules for `ultralight` instead of the built-in ones.
+    pub ultralight_rules: Option<String>,
+    /// TOML rules assigning tags to records matching filter expressions.
+    pub tag_rules: Option<String>,
+    /// Only keep records matching this expression.
+    pub filter: Option<Filter>,
+    /// Only keep these waypoint types.
+    pub types: Vec<String>,
+    pub assign_region: Option<RegionRule>,
+    /// Only keep waypoints in these ICAO regions.
+    pub region: Vec<String>,
+    /// Only keep waypoints whose ident starts with one of these.
+    pub ident_prefix: Vec<String>,
+    /// Which records are the same site, to flag or merge by `dedupe_mode`;
+    /// also used by `append`.
+    pub matcher: SiteMatcher,
+    pub dedupe_mode: DedupeMode,
+    /// Only keep the N waypoints nearest to `near`.
+    pub nearest: Option<usize>,
+    /// A point, or the points of a route, as latitude and longitude.
+    pub near: Vec<(f64, f64)>,
+    /// Add only records not already in the output file instead of
+    /// overwriting it.
+    pub append: bool,
+    /// Fill in missing elevations from a directory of SRTM .hgt tiles.
+    pub fill_elevations: Option<PathBuf>,
+    /// Tag airfields with current NOTAMs and mark closed ones.
+    pub notams: Option<FaaNotams>,
+    /// Reporting stations, as a file or URL, to name the nearest of in
+    /// airfield descriptions.
+    pub metar_stations: Option<String>,
+    /// Only name stations at most this far away (NM).
+    pub metar_radius: f64,
+    /// Transliterate names and idents to ASCII.
+    pub ascii: bool,
+    pub duplicate_idents: DuplicatePolicy,
+    /// List the records with their warnings in the terminal to exclude or
+    /// edit them before writing.
+    pub review: bool,
+    /// Write a JSON Lines log of every record and every change made to it.
+    pub audit: Option<String>,
+    /// Print a rough map of the records within Poland's outline.
+    pub preview: bool,
+    /// Waypoint fields to write to CSV and JSON, in order.
+    pub columns: Columns,
+    /// Renamed, added and transformed output columns.
+    pub mapping: Mapping,
+    /// Unit of the elevations in the userpoints CSV and JSON.
+    pub elevation_unit: ElevationUnit,
+    /// Split the CSV output into numbered files of at most this many
+    /// waypoints.
+    pub chunk_size: Option<NonZeroUsize>,
+    /// Write one CSV file per group next to the output instead.
+    pub split_by: Vec<SplitKey>,
+    /// Also write the waypoints as a JSON array to this path.
+    pub json: Option<String>,
+    /// Also write a KML review layer to this path.
+    pub kml: Option<String>,
+    /// Draw each runway as a line in the KML layer.
+    pub kml_runways: bool,
+    /// Also write the waypoints in other formats, as format name and path.
+    pub export: Vec<(String, String)>,
+    /// Check exported documents against their format's schema.
+    pub validate_output: bool,
+    /// Shorten idents and names in exported files to these lengths.
+    pub limits: Limits,
+    /// Also bundle every generated file and a manifest into this zip archive.
+    pub package: Option<String>,
+    /// Sign the package with this minisign secret key.
+    pub sign_key: Option<String>,
+    /// Print how long each stage took at the end.
+    pub timing: bool,
+}
+
+impl Conversion {
+    /// Converts SkyDemon XML at `input` into the userpoints CSV `output`.
+    pub fn new(input: &str, output: &str) -> Self {
+        Conversion {
+            input: input.to_owned(),
+            input_format: "skydemon".to_owned(),
+            output: output.to_owned(),
+            style: OutputStyle::default(),
+            downloads: Downloads::new(None, std::time::Duration::from_secs(24 * 3600), false),
+            progress: false,
+            cache: None,
+            airfield_dataset: None,
+            runway_thresholds: false,
+            centerline_fixes: vec![],
+            circuit_points: false,
+            circuit_offset: 1.,
+            circuit_side: PatternSide::Left,
+            description_template: None,
+            aip_links: None,
+            lang: Lang::default(),
+            ask: false,
+            dem: None,
+            strict_geo: false,
+            check_icao: false,
+            glider: false,
+            glider_sites: None,
+            ultralight: false,
+            ultralight_rules: None,
+            tag_rules: None,
+            filter: None,
+            types: vec![],
+            assign_region: None,
+            region: vec![],
+            ident_prefix: vec![],
+            matcher: SiteMatcher { name_radius: 5000., ..Default::default() },
+            dedupe_mode: DedupeMode::Flag,
+            nearest: None,
+            near: vec![],
+            append: false,
+            fill_elevations: None,
+            notams: None,
+            metar_stations: None,
+            metar_radius: 30.,
+            ascii: false,
+            duplicate_idents: DuplicatePolicy::Report,
+            review: false,
+            audit: None,
+            preview: false,
+            columns: Columns::default(),
+            mapping: Mapping::default(),
+            elevation_unit: ElevationUnit::default(),
+            chunk_size: None,
+            split_by: vec![],
+            json: None,
+            kml: None,
+            kml_runways: false,
+            export: vec![],
+            validate_output: false,
+            limits: Limits::default(),
+            package: None,
+            sign_key: None,
+            timing: false,
+        }
+    }
+
+    /// Reads the input, runs the passes and writes the outputs, counting
+    /// what was done and warning about records in `summary`.
+    pub fn run(&self, summary: &mut Summary) -> Result<(), Box<dyn Error>> {
+        let mut stopwatch = Stopwatch::start();
+        let mut outputs = Outputs::default();
+        let input = self.downloads.resolve(&self.input)?;
+        summary.read = match self.input_format.as_str() {
+            "skydemon" => self.convert_skydemon(&input, &mut outputs)?,
+            name => {
+                let format = format::input(name).ok_or_else(|| format!("unknown input format {:?}", name))?;
+                let options = ReadOptions {
+                    delimiter: self.style.delimiter,
+                    taken: match &self.airfield_dataset {
+                        Some(path) => read_dataset(path, self.style.delimiter)?.into_iter().map(|w| w.ident).collect(),
+                        None => vec![],
+                    },
+                };
+                let progress = progress::records(self.progress);
+                for waypoint in format.read(&input, &options)? {
+                    outputs.push(waypoint);
+                    progress.inc(1);
+                }
+                progress.finish_and_clear();
+                outputs.waypoints.len()
+            }
+        };
+        stopwatch.lap("parse");
+        self.finish(outputs, &input, summary, stopwatch)
+    }
+
+    /// Options that change what a single airfield converts into, and the
+    /// input its records name as their import file; cached results are only
+    /// reused when these are unchanged.
+    fn cache_settings(&self) -> String {
+        format!(
+            "{:?} {:?}",
+            self.input,
+            (
+                self.runway_thresholds,
+                &self.centerline_fixes,
+                self.circuit_points,
+                self.circuit_offset,
+                self.circuit_side,
+                &self.aip_links,
+                self.kml_runways,
+                &self.description_template,
+                self.lang,
+                self.glider,
+                &self.glider_sites,
+            )
+        )
+    }
+
+    /// Whether converting an airfield reads more than its start tag: its
+    /// runways, frequencies or ICAO code.
+    fn reads_airfield_elements(&self) -> bool {
+        self.description_template.is_some()
+            || self.aip_links.is_some()
+            || self.glider
+            || self.kml_runways
+            || self.circuit_points
+            || self.runway_thresholds
+            || !self.centerline_fixes.is_empty()
+            || self.assign_region.is_some()
+            || self.notams.is_some()
+            || self.metar_stations.is_some()
+    }
+
+    /// The fields at least one of the requested outputs writes.
+    fn written_fields(&self) -> format::Fields {
+        let mut fields = format::Fields::used_by(&self.mapping.csv.columns(&self.columns));
+        if self.json.is_some() {
+            fields = fields.or(format::Fields::used_by(&self.mapping.json.columns(&self.columns)));
+        }
+        if self.kml.is_some() {
+            fields = fields.or(kml::Kml.fields());
+        }
+        for output in self.export.iter().filter_map(|(name, _)| format::output(name)) {
+            fields = fields.or(output.fields());
+        }
+        fields
+    }
+
+    /// Converts the airfields of a SkyDemon document and returns how many there were.
+    fn convert_skydemon(&self, input: &str, outputs: &mut Outputs) -> Result<usize, Box<dyn Error>> {
+        let mut cache = match &self.cache {
+            Some(path) => Some(RecordCache::open(path, &self.cache_settings())?),
+            None => None,
+        };
+        let progress = progress::input(input, self.progress);
+        let mut converted = 0;
+        if cache.is_none() && !self.reads_airfield_elements() {
+            // Nothing below the start tag is needed, so the attributes are
+            // borrowed from the read buffer instead of copied into elements.
+            let mut airports = AirfieldReader::new(input::open(input)?).with_source(&self.input);
+            while let Some(waypoint) = airports.next() {
+                let waypoint = waypoint?;
+                trace!(?waypoint, "converted");
+                outputs.push(waypoint);
+                converted += 1;
+                if converted % AIRFIELD_BATCH == 0 {
+                    progress.set_position(airports.offset());
+                    progress.set_message(converted.to_string());
+                }
+            }
+            progress.finish_and_clear();
+            return Ok(converted);
+        }
+        let mut airports = Elements::new(input::open(input)?, "Airfield");
+        loop {
+            // Airfields are converted in parallel a batch at a time, so memory
+            // stays bounded and results keep the input order.
+            let batch = airports.by_ref().take(AIRFIELD_BATCH).collect::<Result<Vec<_>, _>>()?;
+            if batch.is_empty() {
+                break;
+            }
+            // The cache keys records by their XML, only written when it is on.
+            let sources: Vec<String> = match cache {
+                Some(_) => batch.par_iter().map(|airport| airport.to_xml()).collect(),
+                None => vec![],
+            };
+            let hits: Vec<Option<Outputs>> = match cache.as_mut() {
+                Some(cache) => sources.iter().map(|s| cache.get(s)).collect(),
+                None => vec![None; batch.len()],
+            };
+            let results: Vec<_> = batch
+                .par_iter()
+                .zip(hits)
+                .map(|(airport, hit)| match hit {
+                    Some(hit) => Ok((hit, true)),
+                    None => {
+                        let mut produced = Outputs::default();
+                        self.convert_airfield(airport, &mut produced).map_err(|e| e.to_string())?;
+                        Ok::<_, String>((produced, false))
+                    }
+                })
+                .collect();
+            for (i, result) in results.into_iter().enumerate() {
+                let (produced, cached) = result?;
+                if cached {
+                    trace!(records = produced.waypoints.len(), "reused from the cache");
+                }
+                if let (Some(cache), false) = (cache.as_mut(), cached) {
+                    cache.insert(&sources[i], &produced);
+                }
+                outputs.extend(produced);
+            }
+            converted += batch.len();
+            progress.set_position(batch.last().map_or(0, |airport| airport.offset));
+            progress.set_message(converted.to_string());
+        }
+        progress.finish_and_clear();
+        if let Some(cache) = cache {
+            cache.save()?;
+        }
+        Ok(converted)
+    }
+
+    fn convert_airfield(&self, airport: &Element, outputs: &mut Outputs) -> Result<(), Box<dyn Error>> {
+        let mut waypoint = airfield_waypoint(airport, &self.input)?;
+        let elevation = waypoint.elevation;
+        if let Some(template) = &self.description_template {
+            let description = template::render(template, &template::placeholders(&waypoint, Some(airport), self.lang));
+            waypoint.description = (!description.is_empty()).then_some(description);
+        }
+        // After the template, which replaces the description.
+        let chart = self.aip_links.as_ref().and_then(|template| {
+            airport.attribute("ICAO").and_then(|icao| aip_chart_url(template, icao))
+        });
+        if let Some(link) = chart {
+            waypoint.description = Some(match waypoint.description.take() {
+                Some(d) if !d.is_empty() => format!("{}; {}", d, link),
+                _ => link,
+            });
+        }
+        if let Some(icao) = airport.attribute("ICAO").map(|icao| icao.trim().to_uppercase()).filter(|icao| notam::is_icao(icao)) {
+            outputs.icao.insert(waypoint.ident.clone(), icao);
+        }
+        if self.glider {
+            if airport.attribute("Type").is_some_and(|t| t.to_lowercase().contains("glid")) {
+                waypoint.add_tag(glider::GLIDER_TAG);
+            }
+            glider::detect(&mut waypoint);
+            let runway = airport.children_named("Runway").find_map(|r| r.attribute("Name"));
+            if let Some(runway) = runway.filter(|_| glider::is_glider_site(&waypoint)) {
+                glider::set_runway(&mut waypoint, runway);
+            }
+        }
+        trace!(?waypoint, "converted");
+        outputs.push(waypoint.clone());
+        let declination = waypoint.magnetic_declination.map(f64::from);
+        if self.kml_runways {
+            let ends = runway::parse_runway_ends(airport, waypoint.latitude as f64, waypoint.longitude as f64, declination);
+            for pair in ends.chunks_exact(2) {
+                let name = format!("{} RWY {}/{}", waypoint.name, pair[0].designator, pair[1].designator);
+                outputs.runways.push((waypoint.ident.clone(), name, pair[0].clone(), pair[1].clone()));
+            }
+        }
+        if self.circuit_points {
+            let overhead = Waypoint::builder(format!("{} overhead", waypoint.name))
+                .waypoint_type("VRP")
+                .ident(format!("{}OH", waypoint.ident))
+                .coordinates(waypoint.latitude, waypoint.longitude)
+                .elevation(elevation);
+            outputs.push(derived_from(overhead, &waypoint).build()?);
+        } else if !self.runway_thresholds && self.centerline_fixes.is_empty() {
+            return Ok(());
+        }
+        let pattern = PatternGeometry {
+            offset: self.circuit_offset * geo::METRES_PER_NM,
+            side: self.circuit_side,
+        };
+        let ends = runway::parse_runway_ends(airport, waypoint.latitude as f64, waypoint.longitude as f64, declination);
+        for end in ends {
+            let ident = format!("{}{}", waypoint.ident, end.designator);
+            let name = format!("{} RWY {}", waypoint.name, end.designator);
+            if self.runway_thresholds {
+                let threshold = Waypoint::builder(&name)
+                    .waypoint_type("Waypoint")
+                    .ident(&ident)
+                    .coordinates(end.latitude as f32, end.longitude as f32)
+                    .elevation(elevation);
+                outputs.push(derived_from(threshold, &waypoint).build()?);
+            }
+            for distance in &self.centerline_fixes {
+                let (lat, lon) = centerline_fix(&end, *distance);
+                // In tenths of a mile, so the ident has no decimal point: a
+                // fix 2.5 NM out is F25.
+                let tenths = (distance * 10.).round() as u32;
+                let fix = Waypoint::builder(format!("{} {:.1} NM final", name, distance))
+                    .waypoint_type("Waypoint")
+                    .ident(format!("{}F{}", ident, tenths))
+                    .coordinates(lat as f32, lon as f32);
+                outputs.push(derived_from(fix, &waypoint).build()?);
+            }
+            if self.circuit_points {
+                for point in pattern.points(&end, waypoint.latitude as f64, waypoint.longitude as f64) {
+                    let join = Waypoint::builder(format!("{} {}", name, point.leg))
+                        .waypoint_type("VRP")
+                        .ident(format!("{}{}", ident, point.leg[..1].to_uppercase()))
+                        .coordinates(point.latitude as f32, point.longitude as f32);
+                    outputs.push(derived_from(join, &waypoint).build()?);
+                }
+            }
+        }
+        Ok(())
+    }
+
+    /// Runs the passes over everything converted and writes every output;
+    /// `input` is the local copy of the input file.
+    fn finish(&self, outputs: Outputs, input: &str, summary: &mut Summary, mut stopwatch: Stopwatch) -> Result<(), Box<dyn Error>> {
+        let written = self.written_fields();
+        let mut waypoints = outputs.waypoints;
+        let mut runways = outputs.runways;
+        summary.converted = waypoints.len();
+        let mut audit = Audit::create(self.audit.as_deref())?;
+        audit.parsed(&waypoints, &self.input)?;
+        if self.ask {
+            let mut dem = self.dem.as_ref().map(Dem::open).transpose()?;
+            let before = audit.snapshot(&waypoints);
+            ask::ask(&mut waypoints, dem.as_mut())?;
+            audit.changed("ask", None, before, &waypoints)?;
+        }
+        check_bounds(&waypoints, self.strict_geo, summary)?;
+        if self.check_icao {
+            let before = audit.snapshot(&waypoints);
+            let idents_before = ident_list(&waypoints);
+            for waypoint in &mut waypoints {
+                if idents::normalize_icao(waypoint) == IcaoIdent::Pseudo {
+                    let message = format!("{} is not a valid ICAO code, tagged {}", waypoint.ident, idents::PSEUDO_ICAO_TAG);
+                    summary.warn(&waypoint.ident, message);
+                }
+            }
+            rekey_runways(&mut runways, &idents_before, &waypoints);
+            audit.changed("check-icao", None, before, &waypoints)?;
+        }
+        if self.glider {
+            let supplements = match &self.glider_sites {
+                Some(path) => glider::read_supplement(path, self.style.delimiter)?,
+                None => HashMap::new(),
+            };
+            let before = audit.snapshot(&waypoints);
+            for waypoint in &mut waypoints {
+                glider::detect(waypoint);
+                glider::supplement(waypoint, &supplements);
+                glider::describe(waypoint, self.lang);
+            }
+            audit.changed("glider", self.glider_sites.as_deref(), before, &waypoints)?;
+        }
+        if self.ultralight {
+            let before = audit.snapshot(&waypoints);
+            tagging::apply(&ultralight::rules(self.ultralight_rules.as_deref())?, &mut waypoints);
+            audit.changed("ultralight", self.ultralight_rules.as_deref(), before, &waypoints)?;
+        }
+        if let Some(path) = &self.tag_rules {
+            let before = audit.snapshot(&waypoints);
+            tagging::apply(&tagging::load(path)?, &mut waypoints);
+            audit.changed("tag-rules", Some(path), before, &waypoints)?;
+        }
+        if let Some(filter) = &self.filter {
+            let dropped = summary.retain(&mut waypoints, "by --filter", |w| filter.matches(w));
+            audit.dropped("filter", "doesn't match --filter", &dropped)?;
+        }
+        if !self.types.is_empty() {
+            let dropped = summary.retain(&mut waypoints, "by --type", |w| {
+                self.types.iter().any(|t| t.eq_ignore_ascii_case(&w.waypoint_type))
+            });
+            audit.dropped("type", "not of a --type", &dropped)?;
+        }
+        if let Some(rule) = &self.assign_region {
+            let before = audit.snapshot(&waypoints);
+            for waypoint in &mut waypoints {
+                let icao = outputs.icao.get(&waypoint.ident).cloned();
+                rule.apply(waypoint, icao.as_deref());
+            }
+            audit.changed("assign-region", None, before, &waypoints)?;
+        }
+        if !self.region.is_empty() {
+            let dropped = summary.retain(&mut waypoints, "by --region", |w| {
+                w.region
+                    .as_deref()
+                    .is_some_and(|r| self.region.iter().any(|a| a.eq_ignore_ascii_case(r)))
+            });
+            audit.dropped("region", "not in a --region", &dropped)?;
+        }
+        if !self.ident_prefix.is_empty() {
+            let dropped = summary.retain(&mut waypoints, "by --ident-prefix", |w| {
+                let ident = w.ident.to_uppercase();
+                self.ident_prefix.iter().any(|p| ident.starts_with(&p.to_uppercase()))
+            });
+            audit.dropped("ident-prefix", "no --ident-prefix matches", &dropped)?;
+        }
+        let matcher = self.matcher;
+        if matcher.is_enabled() {
+            let flagged = |waypoints: &[Waypoint]| waypoints.iter().filter(|w| w.has_tag(dedupe::DUPLICATE_TAG)).count();
+            let (before, flagged_before) = (waypoints.len(), flagged(&waypoints));
+            let mut decisions = vec![];
+            waypoints = dedupe::dedupe_with(waypoints, &matcher, self.dedupe_mode, |duplicate, original| {
+                let decision = match self.dedupe_mode {
+                    DedupeMode::Flag => format!("flagged as a possible duplicate of {}", original.ident),
+                    DedupeMode::Merge => format!("merged into {}", original.ident),
+                };
+                debug!(duplicate = %duplicate.ident, original = %original.ident, "duplicate site");
+                decisions.push((duplicate.ident.clone(), decision));
+            });
+            for (ident, decision) in &decisions {
+                audit.decided("dedupe", ident, decision)?;
+            }
+            summary.deduplicated = before - waypoints.len() + flagged(&waypoints) - flagged_before;
+        }
+        if let Some(n) = self.nearest {
+            let mut by_distance: Vec<(usize, f64)> = waypoints
+                .iter()
+                .map(|w| distance_to(w, &self.near))
+                .enumerate()
+                .collect();
+            by_distance.sort_by(|a, b| a.1.total_cmp(&b.1));
+            let mut keep = vec![false; waypoints.len()];
+            for (i, _) in by_distance.into_iter().take(n) {
+                keep[i] = true;
+            }
+            let mut keep = keep.into_iter();
+            let dropped = summary.retain(&mut waypoints, "beyond --nearest", |_| keep.next().unwrap_or(false));
+            audit.dropped("nearest", "beyond --nearest", &dropped)?;
+        }
+        let existing = match self.append && Path::new(&self.output).exists() {
+            true => self.style.read(&self.output)?,
+            false => vec![],
+        };
+        let dropped = summary.retain(&mut waypoints, "already in the output", |w| {
+            !existing
+                .iter()
+                .any(|e| e.ident.eq_ignore_ascii_case(&w.ident) || matcher.same_site(e, w))
+        });
+        audit.dropped("append", "already in the output", &dropped)?;
+        // NOTAM flags go into the tags and description, METAR notes into the
+        // description and terrain into the elevation; none is looked up when
+        // no output writes it.
+        let notams = self.notams.as_ref().filter(|_| written.description || written.tags);
+        let metar_stations = self.metar_stations.as_ref().filter(|_| written.description);
+        let terrain = self.fill_elevations.as_ref().filter(|_| written.elevation);
+        let (notam_flags, nearest_metar, fill_elevations) = (notams.is_some(), metar_stations.is_some(), terrain.is_some());
+        if self.notams.is_some() != notam_flags
+            || self.metar_stations.is_some() != nearest_metar
+            || self.fill_elevations.is_some() != fill_elevations
+        {
+            debug!(notams = notam_flags, nearest_metar, fill_elevations, "no output writes the enriched fields, lookups skipped");
+        }
+        let unenriched: Vec<(Option<String>, Option<String>, Option<f32>)> = match notam_flags || nearest_metar || fill_elevations {
+            true => waypoints.iter().map(|w| (w.tags.clone(), w.description.clone(), w.elevation)).collect(),
+            false => vec![],
+        };
+        if let Some(dir) = terrain {
+            let before = audit.snapshot(&waypoints);
+            let lookup = MissingElevations::new(Dem::open(dir)?);
+            for (i, e) in enrich::enrich(&mut waypoints, &lookup, written) {
+                summary.warn(&waypoints[i].ident, format!("{} elevation left out: {}", waypoints[i].ident, e));
+            }
+            audit.changed("fill-elevations", Some(&dir.to_string_lossy()), before, &waypoints)?;
+        }
+        if let Some(source) = notams {
+            let before = audit.snapshot(&waypoints);
+            let icao = |w: &Waypoint| outputs.icao.get(&w.ident).cloned().or_else(|| notam::icao_ident(w));
+            for (i, e) in notam::flag(&mut waypoints, source, icao, self.lang) {
+                summary.warn(&waypoints[i].ident, format!("{} left unchecked: {}", waypoints[i].ident, e));
+            }
+            audit.changed("notams", Some(source.address()), before, &waypoints)?;
+        }
+        if let Some(source) = metar_stations {
+            let before = audit.snapshot(&waypoints);
+            let stations = self.downloads.resolve(source)?;
+            let stations = metar::read_stations(&stations, self.style.delimiter)?;
+            metar::associate(&mut waypoints, &stations, self.metar_radius * geo::METRES_PER_NM, self.lang);
+            audit.changed("metar", Some(source), before, &waypoints)?;
+        }
+        summary.enriched = unenriched
+            .iter()
+            .zip(&waypoints)
+            .filter(|((tags, description, elevation), w)| {
+                *tags != w.tags || *description != w.description || *elevation != w.elevation
+            })
+            .count();
+        debug!(records = summary.enriched, "enriched");
+        if self.ascii {
+            let before = audit.snapshot(&waypoints);
+            let idents_before = ident_list(&waypoints);
+            waypoints.par_iter_mut().for_each(Waypoint::transliterate);
+            rekey_runways(&mut runways, &idents_before, &waypoints);
+            audit.changed("ascii", None, before, &waypoints)?;
+        }
+        let before = audit.snapshot(&waypoints);
+        let idents_before = ident_list(&waypoints);
+        for (ident, count) in idents::check(&mut waypoints, self.duplicate_idents)? {
+            summary.warn(&ident, format!("duplicate ident {} used by {} waypoints", ident, count));
+        }
+        rekey_runways(&mut runways, &idents_before, &waypoints);
+        audit.changed("duplicate-idents", None, before, &waypoints)?;
+        if self.review {
+            let mut warnings = vec![vec![]; waypoints.len()];
+            for outcome in validate::validate(&waypoints, &validate::Rules::default(), None)? {
+                for failure in outcome.failures {
+                    warnings[failure.record].push(failure.message);
+                }
+            }
+            for warning in &summary.warnings {
+                for (i, _) in waypoints.iter().enumerate().filter(|(_, w)| w.ident == warning.record) {
+                    warnings[i].push(warning.message.clone());
+                }
+            }
+            let before = audit.snapshot(&waypoints);
+            let idents_before = ident_list(&waypoints);
+            let keep = review::review(&mut waypoints, warnings)?.ok_or("review cancelled, nothing was written")?;
+            rekey_runways(&mut runways, &idents_before, &waypoints);
+            audit.changed("review", None, before, &waypoints)?;
+            let mut keep = keep.into_iter();
+            let dropped = summary.retain(&mut waypoints, "excluded in review", |_| keep.next().unwrap_or(true));
+            audit.dropped("review", "excluded in review", &dropped)?;
+        }
+        audit.finish()?;
+        // Runway lines of airfields dropped along the way go with them.
+        let surviving: HashSet<&str> = waypoints.iter().map(|w| w.ident.as_str()).collect();
+        runways.retain(|(ident, ..)| surviving.contains(ident.as_str()));
+        stopwatch.lap("transform");
+        debug!(records = waypoints.len(), "transformed");
+        if self.preview {
+            eprint!("{}", preview::render(&waypoints, 72));
+        }
+        // The writers share the finished records and run on their own
+        // threads, so each extra format costs little wall-clock time.
+        let append = !existing.is_empty();
+        let mut jobs: Vec<Job> = vec![];
+        jobs.push(Box::new(|| self.write_userpoints(&waypoints, append)));
+        if let Some(path) = &self.json {
+            let waypoints = &waypoints;
+            jobs.push(Box::new(move || {
+                let columns = self.mapping.json.columns(&self.columns);
+                let records: Vec<_> = waypoints
+                    .iter()
+                    .map(|w| self.mapping.json.apply(columns.select_in(w, self.elevation_unit), self.elevation_unit))
+                    .collect();
+                fs::write(path, self.style.line_ending.apply(&serde_json::to_string_pretty(&records)?).as_ref())?;
+                Ok(vec![package::Generated { file: path.clone(), records: records.len() }])
+            }));
+        }
+        if let Some(path) = &self.kml {
+            let (waypoints, runways) = (&waypoints, &runways);
+            jobs.push(Box::new(move || {
+                let mut kml = kml::KmlWriter::create(path, self.style.line_ending)?;
+                for waypoint in waypoints {
+                    kml.point(waypoint)?;
+                }
+                for (_, name, from, to) in runways {
+                    kml.runway(name, from, to)?;
+                }
+                kml.finish()?;
+                Ok(vec![package::Generated { file: path.clone(), records: waypoints.len() + runways.len() }])
+            }));
+        }
+        for (name, path) in &self.export {
+            let output = format::output(name).ok_or_else(|| format!("unknown output format {:?}", name))?;
+            let options = WriteOptions {
+                delimiter: self.style.delimiter,
+                line_ending: self.style.line_ending,
+                title: Path::new(&self.input).file_stem().map_or_else(String::new, |s| s.to_string_lossy().into_owned()),
+                validate: self.validate_output,
+                limits: self.limits,
+            };
+            let waypoints = &waypoints;
+            jobs.push(Box::new(move || {
+                output.write(path, waypoints, &options)?;
+                Ok(vec![package::Generated { file: path.clone(), records: waypoints.len() }])
+            }));
+        }
+        let generated = write_concurrently(jobs)?;
+        if let Some(path) = &self.package {
+            let sources: Vec<(&str, &str)> = std::iter::once((self.input.as_str(), input))
+                .chain(self.airfield_dataset.as_deref().map(|path| (path, path)))
+                .collect();
+            package::write(path, &generated, &sources)?;
+            if let Some(key) = &self.sign_key {
+                package::sign(path, key)?;
+            }
+        }
+        stopwatch.lap("write");
+        if self.timing {
+            stopwatch.report(waypoints.len());
+        }
+        for g in &generated {
+            debug!(file = %g.file, records = g.records, "wrote");
+        }
+        summary.written = generated.iter().map(|g| Written { file: g.file.clone(), records: g.records }).collect();
+        Ok(())
+    }
+
+    /// Writes the userpoints CSV, split and chunked as requested.
+    fn write_userpoints(&self, waypoints: &[Waypoint], append: bool) -> Result<Vec<package::Generated>, Box<dyn Error>> {
+        let mut generated = vec![];
+        let mut write_csv = |path: &str, waypoints: &[Waypoint], append: bool| {
+            generated.push(package::Generated { file: path.to_owned(), records: waypoints.len() });
+            self.write_csv(path, waypoints, append)
+        };
+        if self.split_by.is_empty() && self.chunk_size.is_none() {
+            write_csv(&self.output, waypoints, append)?;
+        } else {
+            let groups = match self.split_by.is_empty() {
+                true => {
+                    let stem = Path::new(&self.output).file_stem().and_then(|s| s.to_str()).unwrap_or("userpoints");
+                    [(stem.to_owned(), waypoints.to_vec())].into()
+                }
+                false => split::split(waypoints, &self.split_by),
+            };
+            for (stem, group) in &groups {
+                match self.chunk_size {
+                    Some(size) => {
+                        for (i, chunk) in group.chunks(size.get()).enumerate() {
+                            let path = sibling_path(&self.output, &format!("{}_{}", stem, i + 1));
+                            write_csv(&path, chunk, false)?;
+                        }
+                    }
+                    None => write_csv(&sibling_path(&self.output, stem), group, false)?,
+                }
+            }
+        }
+        Ok(generated)
+    }
+
+    /// Writes `waypoints` as userpoints CSV, or appends them to an existing file.
+    fn write_csv(&self, path: &str, waypoints: &[Waypoint], append: bool) -> Result<(), Box<dyn Error>> {
+        let columns = self.mapping.csv.columns(&self.columns);
+        let mut csv = WriterBuilder::new()
+            .delimiter(self.style.delimiter)
+            .terminator(self.style.line_ending.terminator())
+            .from_writer(vec![]);
+        if !append && self.style.column_header() {
+            csv.write_record(self.mapping.csv.header(&columns, self.elevation_unit))?;
+        }
+        let rows: Vec<_> = waypoints
+            .par_iter()
+            .map(|waypoint| {
+                let row = self.mapping.csv.apply(columns.select_in(waypoint, self.elevation_unit), self.elevation_unit);
+                columns::cells(row, &self.style.numbers)
+            })
+            .collect();
+        for row in rows {
+            csv.write_record(row)?;
+        }
+        let text = match append {
+            true => String::from_utf8(csv.into_inner()?)?,
+            false => self.style.csv_text(csv.into_inner()?)?,
+        };
+        backup::keep(path, self.style.backups)?;
+        self.style.encoding.write(path, &text, append)
+    }
+}
+
+/// Everything a conversion produced, held back until the post-processing
+/// passes have run over the whole set.
+#[derive(Default, Clone, Serialize, Deserialize)]
+pub(crate) struct Outputs {
+    waypoints: Vec<Waypoint>,
+    /// KML runway lines as airfield ident, name and ends.
+    runways: Vec<(String, String, RunwayEnd, RunwayEnd)>,
+    /// ICAO codes of airfields whose ident is something else, by ident.
+    icao: HashMap<String, String>,
+}
+
+impl Outputs {
+    fn push(&mut self, waypoint: Waypoint) {
+        self.waypoints.push(waypoint);
+    }
+
+    fn extend(&mut self, other: Outputs) {
+        self.waypoints.extend(other.waypoints);
+        self.runways.extend(other.runways);
+        self.icao.extend(other.icao);
+    }
+}
+
+const AIRFIELD_BATCH: usize = 1024;
+
+/// Where a point derived from `airfield` comes from: its region and input.
+fn derived_from(mut builder: WaypointBuilder, airfield: &Waypoint) -> WaypointBuilder {
+    if let Some(region) = &airfield.region {
+        builder = builder.region(region);
+    }
+    if let Some(file) = &airfield.import_filename {
+        builder = builder.import_filename(file);
+    }
+    builder
+}
+
+/// The point `distance` NM out on the extended centerline of the runway
+/// landing at `end`, along the great circle.
+fn centerline_fix(end: &RunwayEnd, distance: f64) -> (f64, f64) {
+    geo::destination(end.latitude, end.longitude, geo::reciprocal(end.heading), distance * geo::METRES_PER_NM)
+}
+
+fn aip_chart_url(template: &str, icao: &str) -> Option<String> {
+    let icao = icao.trim().to_uppercase();
+    if icao.len() != 4 || !icao.starts_with("EP") || !icao.chars().all(|c| c.is_ascii_alphabetic()) {
+        return None;
+    }
+    Some(template.replace("{icao}", &icao))
+}
+
+/// Distance in metres from a waypoint to a single point or the nearest leg of a route.
+fn distance_to(waypoint: &Waypoint, near: &[(f64, f64)]) -> f64 {
+    let (lat, lon) = (waypoint.latitude as f64, waypoint.longitude as f64);
+    match near {
+        [] => 0.,
+        [(plat, plon)] => geo::distance(lat, lon, *plat, *plon),
+        route => route
+            .windows(2)
+            .map(|leg| geo::distance_to_segment(lat, lon, leg[0], leg[1]))
+            .fold(f64::INFINITY, f64::min),
+    }
+}
+
+/// Warns about records of the Polish region outside Poland, almost always a
+/// misread coordinate or a typo in the source, or fails when `strict`.
+fn check_bounds(waypoints: &[Waypoint], strict: bool, summary: &mut Summary) -> Result<(), Box<dyn Error>> {
+    let outside: Vec<&Waypoint> = waypoints
+        .iter()
+        .filter(|w| w.region.as_deref().is_none_or(|r| r == region::POLAND))
+        .filter(|w| !geo::POLAND.contains(w.latitude as f64, w.longitude as f64))
+        .collect();
+    for waypoint in &outside {
+        let message = format!("{} at {} {} is outside Poland", waypoint.ident, waypoint.latitude, waypoint.longitude);
+        summary.warn(&waypoint.ident, message);
+    }
+    if strict && !outside.is_empty() {
+        return Err(format!("{} records outside Poland (--strict-geo)", outside.len()).into());
+    }
+    Ok(())
+}
+
+fn ident_list(waypoints: &[Waypoint]) -> Vec<String> {
+    waypoints.iter().map(|w| w.ident.clone()).collect()
+}
+
+/// Follows a step that rewrote idents in place, so runway lines stay keyed
+/// by their airfield's ident. `before` are the idents the step started with.
+fn rekey_runways(runways: &mut [(String, String, RunwayEnd, RunwayEnd)], before: &[String], after: &[Waypoint]) {
+    let mut renamed: HashMap<&str, &str> = HashMap::new();
+    for (old, waypoint) in before.iter().zip(after) {
+        renamed.entry(old).or_insert(&waypoint.ident);
+    }
+    for (ident, ..) in runways {
+        if let Some(new) = renamed.get(ident.as_str()) {
+            *ident = new.to_string();
+        }
+    }
+}
+
+type Job<'a> = Box<dyn FnOnce() -> Result<Vec<package::Generated>, Box<dyn Error>> + Send + 'a>;
+
+/// Runs each writer on its own thread and returns the files written, in
+/// job order. The first failing job, in that order, is reported.
+fn write_concurrently(jobs: Vec<Job>) -> Result<Vec<package::Generated>, Box<dyn Error>> {
+    let results: Vec<Result<_, String>> = thread::scope(|scope| {
+        let handles: Vec<_> = jobs
+            .into_iter()
+            .map(|job| scope.spawn(move || job().map_err(|e| e.to_string())))
+            .collect();
+        handles
+            .into_iter()
+            .map(|handle| handle.join().unwrap_or_else(|_| Err("output writer panicked".to_owned())))
+            .collect()
+    });
+    let mut generated = vec![];
+    for result in results {
+        generated.extend(result?);
+    }
+    Ok(generated)
+}
+
+/// `stem` with the extension of `output`, in the same directory.
+fn sibling_path(output: &str, stem: &str) -> String {
+    let output = Path::new(output);
+    let mut path = output.with_file_name(stem);
+    if let Some(extension) = output.extension() {
+        path.set_extension(extension);
+    }
+    path.to_string_lossy().into_owned()
+}
+
+/// A merge of named datasets, SkyDemon XML or userpoints CSV, into one
+/// userpoints CSV.
+pub struct Merge {
+    /// Sources as name and path, the names being those `precedence` refers to.
+    pub sources: Vec<(String, String)>,
+    pub precedence: merge::Precedence,
+    /// Which records of different sources are the same site, besides those
+    /// sharing an ident.
+    pub matcher: SiteMatcher,
+    /// Merged userpoints CSV to write.
+    pub output: String,
+    pub style: OutputStyle,
+    /// Add a `Source: <field>` column per merged field to the CSV.
+    pub provenance_columns: bool,
+    /// Write per-record field sources to this JSON file.
+    pub provenance_json: Option<String>,
+    /// Keep records in a temporary database in this directory instead of
+    /// memory, for very large merges.
+    pub spill_dir: Option<PathBuf>,
+}
+
+/// Merged records spilled to disk are written this many at a time.
+const MERGE_BATCH: usize = 4096;
+
+impl Merge {
+    /// Merges the sources and writes the result, settling conflicts with
+    /// `resolver`.
+    pub fn run(&self, resolver: &mut ConflictResolver) -> Result<(), Box<dyn Error>> {
+        let mut sidecar = vec![];
+        let mut write = |records: &[merge::Merged], first: bool| {
+            if self.provenance_json.is_some() {
+                sidecar.extend(records.iter().map(|m| {
+                    serde_json::json!({ "ident": m.waypoint.ident, "sources": m.provenance.by_field() })
+                }));
+            }
+            self.write(records, first)
+        };
+        match &self.spill_dir {
+            Some(dir) => {
+                let mut spill = SpillMerge::create(dir, &self.matcher)?;
+                for (name, path) in &self.sources {
+                    for waypoint in stream_any(path, self.style.delimiter)? {
+                        spill.add(name, &waypoint?)?;
+                    }
+                }
+                let mut batch = vec![];
+                let mut first = true;
+                spill.merge(&self.precedence, resolver, |merged| {
+                    batch.push(merged);
+                    if batch.len() == MERGE_BATCH {
+                        write(&batch, std::mem::take(&mut first))?;
+                        batch.clear();
+                    }
+                    Ok::<_, Box<dyn Error>>(())
+                })?;
+                if first || !batch.is_empty() {
+                    write(&batch, first)?;
+                }
+            }
+            None => {
+                let sources = self
+                    .sources
+                    .iter()
+                    .map(|(name, path)| {
+                        Ok(merge::Source {
+                            name: name.clone(),
+                            waypoints: read_any(path, self.style.delimiter)?,
+                        })
+                    })
+                    .collect::<Result<Vec<_>, Box<dyn Error>>>()?;
+                write(&merge::merge(&sources, &self.precedence, &self.matcher, resolver), true)?;
+            }
+        }
+        if let Some(path) = &self.provenance_json {
+            fs::write(path, self.style.line_ending.apply(&serde_json::to_string_pretty(&sidecar)?).as_ref())?;
+        }
+        Ok(())
+    }
+
+    /// Writes merged records to the output; later batches are appended
+    /// without a header.
+    fn write(&self, records: &[merge::Merged], first: bool) -> Result<(), Box<dyn Error>> {
+        let mut writer = WriterBuilder::new()
+            .delimiter(self.style.delimiter)
+            .terminator(self.style.line_ending.terminator())
+            .has_headers(first && self.style.column_header())
+            .from_writer(vec![]);
+        for record in records {
+            if self.provenance_columns {
+                writer.serialize((&record.waypoint, &record.provenance))?;
+            } else {
+                writer.serialize(&record.waypoint)?;
+            }
+        }
+        let records = writer.into_inner()?;
+        if first {
+            backup::keep(&self.output, self.style.backups)?;
+        }
+        match first {
+            true => self.style.encoding.write(&self.output, &self.style.csv_text(records)?, false),
+            false => self.style.encoding.write(&self.output, &String::from_utf8(records)?, true),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn end(designator: &str, heading: f64) -> RunwayEnd {
+        RunwayEnd { designator: designator.to_owned(), heading, latitude: 52., longitude: 20. }
+    }
+
+    #[test]
+    fn centerline_fixes_lie_on_the_approach() {
+        // Landing north, the approach comes from the south.
+        let (lat, lon) = centerline_fix(&end("36", 360.), 5.);
+        assert!((lat - 51.916723).abs() < 1e-6, "{}", lat);
+        assert!((lon - 20.).abs() < 1e-9, "{}", lon);
+        // Landing east, from the west, with the great circle curving south.
+        let (lat, lon) = centerline_fix(&end("09", 90.), 2.5);
+        assert!((lat - 51.999981).abs() < 1e-6, "{}", lat);
+        assert!((lon - 19.932368).abs() < 1e-6, "{}", lon);
+        assert!((geo::distance(52., 20., lat, lon) - 2.5 * geo::METRES_PER_NM).abs() < 0.01);
+    }
+}
@@ -0,0 +1,110 @@
+//! A rough map of records inside Poland's outline, drawn with Braille
+//! characters for a terminal, so a misplaced point stands out without
+//! opening a map viewer.
+
+use crate::geo::{self, Bounds};
+use crate::Waypoint;
+use std::fmt::Write;
+
+/// Poland's border, longitude and latitude, simplified to a few
+/// kilometres; clockwise from the mouth of the Świna.
+const OUTLINE: [(f64, f64); 49] = [
+    (14.22, 53.93), (15.40, 54.15), (16.20, 54.30), (16.90, 54.60), (17.70, 54.80), (18.40, 54.83),
+    (18.80, 54.60), (18.60, 54.40), (19.40, 54.40), (19.65, 54.45), (20.80, 54.35), (22.00, 54.33),
+    (22.80, 54.40), (23.50, 54.15), (23.50, 53.90), (23.90, 53.20), (23.60, 52.60), (23.20, 52.30),
+    (23.65, 52.08), (23.70, 51.60), (24.10, 51.00), (24.10, 50.80), (23.50, 50.40), (22.70, 49.60),
+    (22.90, 49.10), (22.55, 49.05), (22.00, 49.20), (21.00, 49.40), (20.40, 49.40), (20.00, 49.20),
+    (19.50, 49.60), (18.90, 49.50), (18.60, 49.90), (17.90, 50.00), (17.65, 50.25), (16.90, 50.45),
+    (16.65, 50.20), (16.20, 50.40), (16.40, 50.65), (15.80, 50.75), (15.00, 51.00), (14.82, 50.87),
+    (15.00, 51.40), (14.70, 51.60), (14.60, 52.30), (14.15, 52.85), (14.40, 53.30), (14.15, 53.55),
+    (14.22, 53.93),
+];
+
+/// Dot bits of a Braille cell by column and row.
+const DOTS: [[u32; 4]; 2] = [[0x01, 0x02, 0x04, 0x40], [0x08, 0x10, 0x20, 0x80]];
+
+/// Braille dots, two across and four down per character.
+struct Canvas {
+    width: usize,
+    height: usize,
+    dots: Vec<u32>,
+    /// Characters drawn over the outline where records are.
+    marks: Vec<Option<char>>,
+}
+
+impl Canvas {
+    fn new(columns: usize, rows: usize) -> Self {
+        Canvas { width: columns * 2, height: rows * 4, dots: vec![0; columns * rows], marks: vec![None; columns * rows] }
+    }
+
+    fn cell(&self, x: usize, y: usize) -> usize {
+        (y / 4) * (self.width / 2) + x / 2
+    }
+
+    fn dot(&mut self, x: usize, y: usize) {
+        if x < self.width && y < self.height {
+            let cell = self.cell(x, y);
+            self.dots[cell] |= DOTS[x % 2][y % 4];
+        }
+    }
+
+    fn line(&mut self, from: (f64, f64), to: (f64, f64)) {
+        let steps = (to.0 - from.0).abs().max((to.1 - from.1).abs()).ceil().max(1.) as usize;
+        for i in 0..=steps {
+            let t = i as f64 / steps as f64;
+            self.dot((from.0 + (to.0 - from.0) * t).round() as usize, (from.1 + (to.1 - from.1) * t).round() as usize);
+        }
+    }
+
+    fn text(&self) -> String {
+        let columns = self.width / 2;
+        let mut text = String::new();
+        for (i, (&dots, &mark)) in self.dots.iter().zip(&self.marks).enumerate() {
+            text.push(mark.unwrap_or_else(|| char::from_u32(0x2800 + dots).unwrap_or(' ')));
+            if (i + 1) % columns == 0 {
+                text.push('\n');
+            }
+        }
+        text
+    }
+}
+
+/// The map, `columns` characters wide, with a line for each record that
+/// falls outside it.
+pub fn render(waypoints: &[Waypoint], columns: usize) -> String {
+    let points: Vec<(f64, f64, char)> = waypoints.iter().map(|w| (w.latitude as f64, w.longitude as f64, '*')).collect();
+    let mut text = render_points(&points, columns);
+    for waypoint in waypoints.iter().filter(|w| !geo::POLAND.contains(w.latitude as f64, w.longitude as f64)) {
+        writeln!(text, "off the map: {} ({}) at {}, {}", waypoint.ident, waypoint.name, waypoint.latitude, waypoint.longitude)
+            .unwrap();
+    }
+    text
+}
+
+/// The map with each latitude, longitude and character given; points off
+/// the map are left out.
+pub fn render_points(points: &[(f64, f64, char)], columns: usize) -> String {
+    let Bounds { min_latitude, max_latitude, min_longitude, max_longitude } = geo::POLAND;
+    // Longitude degrees shrink with the cosine of the latitude; the dots
+    // of a Braille cell are about as far apart across as down.
+    let scale = ((min_latitude + max_latitude) / 2.).to_radians().cos();
+    let aspect = (max_latitude - min_latitude) / ((max_longitude - min_longitude) * scale);
+    let rows = ((columns * 2) as f64 * aspect / 4.).round().max(1.) as usize;
+    let mut canvas = Canvas::new(columns, rows);
+    let (width, height) = ((canvas.width - 1) as f64, (canvas.height - 1) as f64);
+    let project = |lon: f64, lat: f64| {
+        let x = (lon - min_longitude) / (max_longitude - min_longitude) * width;
+        let y = (max_latitude - lat) / (max_latitude - min_latitude) * height;
+        (x, y)
+    };
+    let outline: Vec<(f64, f64)> = OUTLINE.iter().map(|&(lon, lat)| project(lon, lat)).collect();
+    for pair in outline.windows(2) {
+        canvas.line(pair[0], pair[1]);
+    }
+    for &(lat, lon, mark) in points.iter().filter(|(lat, lon, _)| geo::POLAND.contains(*lat, *lon)) {
+        let (x, y) = project(lon, lat);
+        let cell = canvas.cell(x.round() as usize, y.round() as usize);
+        canvas.marks[cell] = Some(mark);
+    }
+    canvas.text()
+}
@@ -0,0 +1,33 @@
+use indicatif::{ProgressBar, ProgressDrawTarget, ProgressStyle};
+use std::fs;
+
+/// A bar on stderr over the bytes of the input at `path`, with the number of
+/// records converted and the time left. It draws nothing when disabled or
+/// when stderr is not a terminal, so piped runs stay clean.
+pub fn input(path: &str, enabled: bool) -> ProgressBar {
+    let length = fs::metadata(path).map_or(0, |m| m.len());
+    let bar = ProgressBar::with_draw_target(Some(length), target(enabled));
+    bar.set_style(
+        ProgressStyle::with_template("{bar:40} {percent:>3}% {msg} records, {eta} left")
+            .unwrap_or_else(|_| ProgressStyle::default_bar()),
+    );
+    bar.set_message("0");
+    bar
+}
+
+/// A spinner for inputs read without byte offsets, counting records.
+pub fn records(enabled: bool) -> ProgressBar {
+    let bar = ProgressBar::with_draw_target(None, target(enabled));
+    bar.set_style(
+        ProgressStyle::with_template("{spinner} {pos} records, {per_sec}")
+            .unwrap_or_else(|_| ProgressStyle::default_spinner()),
+    );
+    bar
+}
+
+fn target(enabled: bool) -> ProgressDrawTarget {
+    match enabled {
+        true => ProgressDrawTarget::stderr(),
+        false => ProgressDrawTarget::hidden(),
+    }
+}
@@ -0,0 +1,81 @@
+//! Python module built with `maturin build --features python`, so the list
+//! can be processed from notebooks:
+//!
+//! ```python
+//! import polish_airports
+//! airfields = polish_airports.parse(open("skydemon_PL_missing.airfields.xml").read())
+//! ```
+
+use crate::{Coordinate, Position, Waypoint};
+use pyo3::exceptions::PyValueError;
+use pyo3::prelude::*;
+use pyo3::types::PyDict;
+use std::str::FromStr;
+
+fn value_error(e: impl ToString) -> PyErr {
+    PyValueError::new_err(e.to_string())
+}
+
+fn read(xml: &str) -> PyResult<Vec<Waypoint>> {
+    crate::AirfieldReader::new(xml.as_bytes()).collect::<Result<_, _>>().map_err(value_error)
+}
+
+/// Airfields of a SkyDemon XML document as dicts keyed by the userpoints
+/// CSV column names.
+#[pyfunction]
+fn parse<'py>(py: Python<'py>, xml: &str) -> PyResult<Vec<Bound<'py, PyDict>>> {
+    read(xml)?
+        .into_iter()
+        .map(|waypoint| {
+            let record = PyDict::new(py);
+            record.set_item("Type", waypoint.waypoint_type)?;
+            record.set_item("Name", waypoint.name)?;
+            record.set_item("Ident", waypoint.ident)?;
+            record.set_item("Latitude", waypoint.latitude)?;
+            record.set_item("Longitude", waypoint.longitude)?;
+            record.set_item("Elevation", waypoint.elevation)?;
+            record.set_item("Magnetic Declination", waypoint.magnetic_declination)?;
+            record.set_item("Tags", waypoint.tags)?;
+            record.set_item("Description", waypoint.description)?;
+            record.set_item("Region", waypoint.region)?;
+            record.set_item("Visible From", waypoint.visible_from)?;
+            record.set_item("Last Edit", waypoint.last_edit)?;
+            record.set_item("Import Filename", waypoint.import_filename)?;
+            Ok(record)
+        })
+        .collect()
+}
+
+/// Converts a SkyDemon XML document to userpoints CSV text.
+#[pyfunction]
+#[pyo3(signature = (xml, delimiter = ','))]
+fn to_csv(xml: &str, delimiter: char) -> PyResult<String> {
+    let delimiter = u8::try_from(delimiter).map_err(value_error)?;
+    let mut writer = csv::WriterBuilder::new().delimiter(delimiter).from_writer(vec![]);
+    for waypoint in read(xml)? {
+        writer.serialize(waypoint).map_err(value_error)?;
+    }
+    String::from_utf8(writer.into_inner().map_err(value_error)?).map_err(value_error)
+}
+
+/// Decimal degrees of a coordinate such as `N520101.50`.
+#[pyfunction]
+fn parse_coordinate(text: &str) -> PyResult<f32> {
+    Coordinate::parse_decimal(text).map_err(value_error)
+}
+
+/// Latitude and longitude of a position such as `N530956.01 E0174449.00`.
+#[pyfunction]
+fn parse_position(text: &str) -> PyResult<(f32, f32)> {
+    let position = Position::from_str(text).map_err(value_error)?;
+    Ok((position.lat.to_decimal_degrees(), position.lon.to_decimal_degrees()))
+}
+
+#[pymodule]
+fn polish_airports(module: &Bound<'_, PyModule>) -> PyResult<()> {
+    module.add_function(wrap_pyfunction!(parse, module)?)?;
+    module.add_function(wrap_pyfunction!(to_csv, module)?)?;
+    module.add_function(wrap_pyfunction!(parse_coordinate, module)?)?;
+    module.add_function(wrap_pyfunction!(parse_position, module)?)?;
+    Ok(())
+}
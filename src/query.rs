@@ -0,0 +1,203 @@
+//! `query`: loads datasets and answers ad-hoc questions about them, one
+//! command per line, before anything is exported.
+
+use polish_airports::filter::{Field, Filter};
+use polish_airports::format::{self, WriteOptions};
+use polish_airports::{geo, Waypoint};
+use regex::{Regex, RegexBuilder};
+use std::error::Error;
+use std::fmt::Write as _;
+use std::io::{self, BufRead, IsTerminal, Write};
+
+const HELP: &str = "\
+load PATH [FORMAT]      add a dataset's records
+near IDENT|LAT,LON D    records within D of a record or point; D in nm (default), km or m
+find EXPR               records matching a --filter expression, or FIELD~TEXT or FIELD=TEXT
+count [EXPR]            how many records match
+show IDENT              every field of a record
+export FORMAT PATH      write the last near or find result, or everything before one
+sources                 the datasets loaded
+quit
+";
+
+/// A shorthand comparison, `name~zamość` (contains) or `type=helipad`
+/// (equals), both ignoring case.
+fn shorthand(s: &str) -> Option<Filter> {
+    let at = s.find(['~', '='])?;
+    let field: Field = s[..at].trim().parse().ok()?;
+    let text = regex::escape(s[at + 1..].trim());
+    let pattern = if &s[at..at + 1] == "=" { format!("^{}$", text) } else { text };
+    let regex: Regex = RegexBuilder::new(&pattern).case_insensitive(true).build().ok()?;
+    Some(Filter::Matches(field, regex))
+}
+
+fn filter(expression: &str) -> Result<Filter, String> {
+    expression.parse::<Filter>().or_else(|e| shorthand(expression).ok_or(e))
+}
+
+/// A distance such as `30nm`, `50km` or `800m`, in metres; bare numbers
+/// are nautical miles.
+fn parse_distance(s: &str) -> Result<f64, String> {
+    let split = s.find(|c: char| c.is_ascii_alphabetic()).unwrap_or(s.len());
+    let metres = match &s[split..] {
+        "nm" | "" => geo::METRES_PER_NM,
+        "km" => 1000.,
+        "m" => 1.,
+        unit => return Err(format!("unknown unit {:?}, use nm, km or m", unit)),
+    };
+    match s[..split].parse::<f64>() {
+        Ok(n) if n >= 0. && n.is_finite() => Ok(n * metres),
+        _ => Err(format!("invalid distance {:?}", s)),
+    }
+}
+
+fn row(waypoint: &Waypoint) -> String {
+    format!("{:<8} {:<10} {} ({:.4}, {:.4})", waypoint.ident, waypoint.waypoint_type, waypoint.name, waypoint.latitude, waypoint.longitude)
+}
+
+#[derive(Default)]
+struct Session {
+    waypoints: Vec<Waypoint>,
+    sources: Vec<(String, usize)>,
+    /// Indexes of the last `near` or `find` result.
+    last: Option<Vec<usize>>,
+}
+
+impl Session {
+    fn load(&mut self, path: &str, format: Option<&str>, delimiter: u8) -> Result<String, Box<dyn Error>> {
+        let records = crate::read_as(path, format, delimiter)?;
+        let n = records.len();
+        self.waypoints.extend(records);
+        self.sources.push((path.to_owned(), n));
+        Ok(format!("loaded {} records from {}, {} in all\n", n, path, self.waypoints.len()))
+    }
+
+    fn find_ident(&self, ident: &str) -> Result<&Waypoint, String> {
+        self.waypoints
+            .iter()
+            .find(|w| w.ident.eq_ignore_ascii_case(ident))
+            .ok_or_else(|| format!("no record has ident {}", ident))
+    }
+
+    fn listing(&mut self, found: Vec<(usize, String)>) -> String {
+        let mut text = String::new();
+        for (_, line) in &found {
+            writeln!(text, "{}", line).unwrap();
+        }
+        writeln!(text, "{} records", found.len()).unwrap();
+        self.last = Some(found.into_iter().map(|(i, _)| i).collect());
+        text
+    }
+
+    fn run(&mut self, line: &str, delimiter: u8) -> Result<String, Box<dyn Error>> {
+        let (command, rest) = line.split_once(char::is_whitespace).unwrap_or((line, ""));
+        let rest = rest.trim();
+        match command {
+            "help" | "?" => Ok(HELP.to_owned()),
+            "load" => match rest.split_whitespace().collect::<Vec<_>>()[..] {
+                [path] => self.load(path, None, delimiter),
+                [path, format] => self.load(path, Some(format), delimiter),
+                _ => Err("usage: load PATH [FORMAT]".into()),
+            },
+            "sources" => Ok(self.sources.iter().map(|(path, n)| format!("{} ({} records)\n", path, n)).collect()),
+            "near" => {
+                let (place, distance) = rest.rsplit_once(char::is_whitespace).ok_or("usage: near IDENT|LAT,LON DISTANCE")?;
+                let radius = parse_distance(distance.trim())?;
+                let (lat, lon) = match crate::parse_lat_lon(place.trim()) {
+                    Ok(point) => point,
+                    Err(_) => {
+                        let w = self.find_ident(place.trim())?;
+                        (w.latitude as f64, w.longitude as f64)
+                    }
+                };
+                let mut near: Vec<(usize, f64)> = self
+                    .waypoints
+                    .iter()
+                    .map(|w| geo::distance(lat, lon, w.latitude as f64, w.longitude as f64))
+                    .enumerate()
+                    .filter(|(_, d)| *d <= radius)
+                    .collect();
+                near.sort_by(|a, b| a.1.total_cmp(&b.1));
+                let found = near
+                    .into_iter()
+                    .map(|(i, d)| (i, format!("{:>6.1} nm  {}", d / geo::METRES_PER_NM, row(&self.waypoints[i]))))
+                    .collect();
+                Ok(self.listing(found))
+            }
+            "find" if !rest.is_empty() => {
+                let filter = filter(rest)?;
+                let found = (0..self.waypoints.len())
+                    .filter(|&i| filter.matches(&self.waypoints[i]))
+                    .map(|i| (i, row(&self.waypoints[i])))
+                    .collect();
+                Ok(self.listing(found))
+            }
+            "find" => Err("usage: find EXPR".into()),
+            "count" if rest.is_empty() => Ok(format!("{} records\n", self.waypoints.len())),
+            "count" => {
+                let filter = filter(rest)?;
+                Ok(format!("{} records\n", self.waypoints.iter().filter(|w| filter.matches(w)).count()))
+            }
+            "show" => {
+                let waypoint = self.find_ident(rest)?;
+                let serde_json::Value::Object(fields) = serde_json::to_value(waypoint)? else {
+                    unreachable!("a waypoint serializes as a map");
+                };
+                let mut text = String::new();
+                for (field, value) in fields.iter().filter(|(_, v)| !v.is_null()) {
+                    // The fields are f32; printed as f64 they would show noise digits.
+                    let value = match value {
+                        serde_json::Value::String(s) => s.clone(),
+                        serde_json::Value::Number(n) => n.as_f64().map_or_else(|| n.to_string(), |n| (n as f32).to_string()),
+                        other => other.to_string(),
+                    };
+                    writeln!(text, "{:<22} {}", field, value).unwrap();
+                }
+                Ok(text)
+            }
+            "export" => {
+                let [name, path] = rest.split_whitespace().collect::<Vec<_>>()[..] else {
+                    return Err("usage: export FORMAT PATH".into());
+                };
+                let output = format::output(name).ok_or_else(|| format!("unknown output format {:?}", name))?;
+                let records: Vec<Waypoint> = match &self.last {
+                    Some(last) => last.iter().map(|&i| self.waypoints[i].clone()).collect(),
+                    None => self.waypoints.clone(),
+                };
+                output.write(path, &records, &WriteOptions { delimiter, ..WriteOptions::default() })?;
+                Ok(format!("wrote {} records to {}\n", records.len(), path))
+            }
+            _ => Err(format!("unknown command {:?}; try help", command).into()),
+        }
+    }
+}
+
+/// Loads `sources` and answers commands from standard input until it ends
+/// or `quit`; a failed command is reported and the session goes on.
+pub fn repl(sources: &[String], format: Option<&str>, delimiter: u8) -> Result<(), Box<dyn Error>> {
+    let mut session = Session::default();
+    for source in sources {
+        eprint!("{}", session.load(source, format, delimiter)?);
+    }
+    let interactive = io::stdin().is_terminal();
+    let mut stdout = io::stdout();
+    let mut lines = io::stdin().lock().lines();
+    loop {
+        if interactive {
+            write!(stdout, "> ")?;
+            stdout.flush()?;
+        }
+        let Some(line) = lines.next().transpose()? else {
+            return Ok(());
+        };
+        let line = line.trim();
+        match line {
+            "" => continue,
+            "quit" | "exit" => return Ok(()),
+            _ => match session.run(line, delimiter) {
+                Ok(text) => write!(stdout, "{}", text)?,
+                Err(e) => eprintln!("error: {}", e),
+            },
+        }
+    }
+}
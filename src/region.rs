@@ -0,0 +1,85 @@
+//! ICAO regions, the nationality letters at the start of location
+//! indicators. Readers of Polish sources assign [`POLAND`]; readers of
+//! other countries' data, and `--assign-region`, work them out from idents
+//! or, failing that, from the country.
+
+use crate::Waypoint;
+use std::str::FromStr;
+
+/// The region the Polish sources are in.
+pub const POLAND: &str = "EP";
+
+/// Countries whose location indicators start with a single nationality
+/// letter: Canada, the contiguous United States, Russia, Australia and China.
+const SINGLE_LETTER: &str = "CKUYZ";
+
+/// The region of an ICAO location indicator, e.g. `EP` for `EPWA` and `K`
+/// for `KJFK`; `None` when `code` is not one.
+pub fn of_icao(code: &str) -> Option<&str> {
+    if code.len() != 4 || !code.chars().all(|c| c.is_ascii_uppercase()) {
+        return None;
+    }
+    match SINGLE_LETTER.contains(&code[..1]) {
+        true => Some(&code[..1]),
+        false => Some(&code[..2]),
+    }
+}
+
+/// ISO 3166 country codes and the ICAO region of their aerodromes, for
+/// records that have no location indicator of their own.
+const COUNTRIES: &[(&str, &str)] = &[
+    ("AL", "LA"), ("AT", "LO"), ("AU", "Y"), ("BA", "LQ"), ("BE", "EB"), ("BG", "LB"),
+    ("BY", "UM"), ("CA", "C"), ("CH", "LS"), ("CN", "Z"), ("CY", "LC"), ("CZ", "LK"),
+    ("DE", "ED"), ("DK", "EK"), ("EE", "EE"), ("ES", "LE"), ("FI", "EF"), ("FR", "LF"),
+    ("GB", "EG"), ("GR", "LG"), ("HR", "LD"), ("HU", "LH"), ("IE", "EI"), ("IS", "BI"),
+    ("IT", "LI"), ("LT", "EY"), ("LU", "EL"), ("LV", "EV"), ("MD", "LU"), ("ME", "LY"),
+    ("MK", "LW"), ("MT", "LM"), ("NL", "EH"), ("NO", "EN"), ("PL", "EP"), ("PT", "LP"),
+    ("RO", "LR"), ("RS", "LY"), ("RU", "U"), ("SE", "ES"), ("SI", "LJ"), ("SK", "LZ"),
+    ("TR", "LT"), ("UA", "UK"), ("US", "K"),
+];
+
+/// The ICAO region of the ISO 3166 `country`, e.g. `EP` for `PL`; `None`
+/// for countries not in the table.
+pub fn of_country(country: &str) -> Option<&'static str> {
+    let country = country.trim();
+    COUNTRIES.iter().find(|(iso, _)| iso.eq_ignore_ascii_case(country)).map(|(_, region)| *region)
+}
+
+/// How `--assign-region` sets the region of each record.
+#[derive(Clone, Debug, PartialEq)]
+pub enum RegionRule {
+    /// The same region for every record.
+    Fixed(String),
+    /// The region of the record's ICAO code, or of its ident where that is
+    /// one; others keep what the reader gave them.
+    FromIdent,
+}
+
+impl RegionRule {
+    /// Sets the region of `waypoint`, whose ICAO code, when the source gives
+    /// one apart from the ident, is `icao`.
+    pub fn apply(&self, waypoint: &mut Waypoint, icao: Option<&str>) {
+        match self {
+            RegionRule::Fixed(region) => waypoint.region = Some(region.clone()),
+            RegionRule::FromIdent => {
+                if let Some(region) = icao.and_then(of_icao).or_else(|| of_icao(&waypoint.ident)) {
+                    waypoint.region = Some(region.to_owned());
+                }
+            }
+        }
+    }
+}
+
+impl FromStr for RegionRule {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "ident" => Ok(RegionRule::FromIdent),
+            s if (1..=2).contains(&s.len()) && s.chars().all(|c| c.is_ascii_alphabetic()) => {
+                Ok(RegionRule::Fixed(s.to_uppercase()))
+            }
+            _ => Err(format!("expected `ident` or a one or two letter ICAO region, got {:?}", s)),
+        }
+    }
+}
@@ -0,0 +1,118 @@
+use crate::format::{Fields, InputFormat, ReadOptions, Records};
+use crate::{input, region, Coordinate, Error as DataError, Waypoint};
+use serde::Deserialize;
+use std::collections::HashSet;
+use std::error::Error;
+
+/// A VFR reporting point from an AIP ENR/AD table, already carrying its
+/// generated ident (e.g. `EPKK-N`).
+#[derive(Debug, PartialEq)]
+pub struct ReportingPoint {
+    pub ident: String,
+    pub name: String,
+    pub aerodrome: String,
+    pub latitude: f32,
+    pub longitude: f32,
+    pub compulsory: bool,
+}
+
+#[derive(Deserialize)]
+struct ReportingPointRecord {
+    #[serde(rename = "Aerodrome")]
+    aerodrome: String,
+    #[serde(rename = "Designator")]
+    designator: Option<String>,
+    #[serde(rename = "Name")]
+    name: String,
+    #[serde(rename = "Latitude")]
+    latitude: String,
+    #[serde(rename = "Longitude")]
+    longitude: String,
+    #[serde(rename = "Compulsory")]
+    compulsory: Option<String>,
+}
+
+/// Short designator for a point: the explicit column when given, otherwise
+/// the first letter of the name, which matches the AIP habit of naming
+/// points with phonetic-alphabet words (`NOVEMBER` → `N`).
+fn designator(record: &ReportingPointRecord) -> String {
+    match record.designator.as_deref().map(str::trim) {
+        Some(d) if !d.is_empty() => d.to_uppercase(),
+        _ => record
+            .name
+            .trim()
+            .chars()
+            .next()
+            .map(|c| c.to_uppercase().collect())
+            .unwrap_or_else(|| "X".to_owned()),
+    }
+}
+
+/// Reads a CSV with `Aerodrome,Designator,Name,Latitude,Longitude,Compulsory`
+/// columns and assigns `<aerodrome>-<designator>` idents, numbering repeats
+/// and skipping any ident in `taken` (typically the airfield idents).
+pub fn read_csv(path: &str, taken: &[String], delimiter: u8) -> Result<Vec<ReportingPoint>, Box<dyn Error>> {
+    let mut reader = csv::ReaderBuilder::new().delimiter(delimiter).from_reader(input::open(path)?);
+    let mut taken: HashSet<String> = taken.iter().map(|t| t.to_uppercase()).collect();
+    let mut points = vec![];
+    for record in reader.deserialize() {
+        let record: ReportingPointRecord = record?;
+        let aerodrome = record.aerodrome.trim().to_uppercase();
+        let base = format!("{}-{}", aerodrome, designator(&record));
+        let mut ident = base.clone();
+        let mut n = 1;
+        while taken.contains(&ident) {
+            n += 1;
+            ident = format!("{}{}", base, n);
+        }
+        taken.insert(ident.clone());
+        points.push(ReportingPoint {
+            ident,
+            latitude: Coordinate::parse_decimal(&record.latitude).map_err(|e| e.in_record(&record.name))?,
+            longitude: Coordinate::parse_decimal(&record.longitude).map_err(|e| e.in_record(&record.name))?,
+            name: record.name.trim().to_owned(),
+            aerodrome,
+            compulsory: record
+                .compulsory
+                .is_some_and(|c| matches!(c.trim().to_uppercase().as_str(), "Y" | "YES" | "TAK")),
+        });
+    }
+    Ok(points)
+}
+
+impl ReportingPoint {
+    pub fn to_waypoint(&self, source: &str) -> Result<Waypoint, DataError> {
+        let kind = if self.compulsory { "Compulsory" } else { "On request" };
+        Waypoint::builder(&self.name)
+            .waypoint_type("VRP")
+            .ident(&self.ident)
+            .coordinates(self.latitude, self.longitude)
+            .description(format!("{} reporting point for {}", kind, self.aerodrome))
+            .region(region::POLAND)
+            .import_filename(source)
+            .build()
+            .map_err(|e| e.in_record(&self.ident))
+    }
+}
+
+pub struct ReportingCsv;
+
+impl InputFormat for ReportingCsv {
+    fn name(&self) -> &'static str {
+        "reporting-csv"
+    }
+
+    fn description(&self) -> &'static str {
+        "AIP VFR reporting point table as CSV"
+    }
+
+    fn fields(&self) -> Fields {
+        Fields::default()
+    }
+
+    fn read(&self, path: &str, options: &ReadOptions) -> Result<Records, Box<dyn Error>> {
+        let points = read_csv(path, &options.taken, options.delimiter)?;
+        let waypoints = points.iter().map(|p| p.to_waypoint(path)).collect::<Result<Vec<_>, _>>()?;
+        Ok(Box::new(waypoints.into_iter()))
+    }
+}
@@ -0,0 +1,222 @@
+//! `--review`: a terminal list of the converted records, with their
+//! validation warnings, where records can be excluded or edited before
+//! anything is written.
+
+use crate::{Coordinate, Waypoint};
+use ratatui::crossterm::event::{self, Event, KeyCode, KeyEvent, KeyEventKind};
+use ratatui::layout::{Constraint, Layout};
+use ratatui::style::{Color, Modifier, Style};
+use ratatui::text::{Line, Span};
+use ratatui::widgets::{Block, List, ListItem, ListState, Paragraph, Wrap};
+use ratatui::{DefaultTerminal, Frame};
+use std::error::Error;
+use std::io::IsTerminal;
+
+/// The fields that can be edited, in the order the details show them.
+const FIELDS: [&str; 8] = ["Name", "Ident", "Type", "Latitude", "Longitude", "Elevation", "Region", "Description"];
+
+const HELP: &str = "↑↓ move  space keep/exclude  tab field  enter edit  n next warning  w write  q cancel";
+
+fn get(waypoint: &Waypoint, field: &str) -> String {
+    let optional = |value: &Option<String>| value.clone().unwrap_or_default();
+    match field {
+        "Name" => waypoint.name.clone(),
+        "Ident" => waypoint.ident.clone(),
+        "Type" => waypoint.waypoint_type.clone(),
+        "Latitude" => waypoint.latitude.to_string(),
+        "Longitude" => waypoint.longitude.to_string(),
+        "Elevation" => waypoint.elevation.map(|e| e.to_string()).unwrap_or_default(),
+        "Region" => optional(&waypoint.region),
+        "Description" => optional(&waypoint.description),
+        _ => unreachable!("not an editable field"),
+    }
+}
+
+/// Sets `field` from what was typed; an empty value clears optional fields.
+fn set(waypoint: &mut Waypoint, field: &str, text: &str) -> Result<(), String> {
+    let text = text.trim();
+    let optional = || (!text.is_empty()).then(|| text.to_owned());
+    let coordinate = || Coordinate::parse_decimal(text).map_err(|e| e.to_string());
+    match field {
+        "Name" | "Ident" | "Type" if text.is_empty() => return Err(format!("{} can't be empty", field)),
+        "Name" => waypoint.name = text.to_owned(),
+        "Ident" => waypoint.ident = text.to_owned(),
+        "Type" => waypoint.waypoint_type = text.to_owned(),
+        "Latitude" => waypoint.latitude = coordinate()?,
+        "Longitude" => waypoint.longitude = coordinate()?,
+        "Elevation" if text.is_empty() => waypoint.elevation = None,
+        "Elevation" => waypoint.elevation = Some(text.parse().map_err(|_| format!("{:?} is not a number of feet", text))?),
+        "Region" => waypoint.region = optional(),
+        "Description" => waypoint.description = optional(),
+        _ => unreachable!("not an editable field"),
+    }
+    Ok(())
+}
+
+struct Review<'a> {
+    waypoints: &'a mut [Waypoint],
+    warnings: Vec<Vec<String>>,
+    keep: Vec<bool>,
+    list: ListState,
+    /// The selected field in the details.
+    field: usize,
+    /// What is being typed into the selected field.
+    editing: Option<String>,
+    status: String,
+}
+
+enum Done {
+    Write,
+    Cancel,
+}
+
+impl Review<'_> {
+    fn selected(&self) -> usize {
+        self.list.selected().unwrap_or(0)
+    }
+
+    fn key(&mut self, key: KeyEvent) -> Option<Done> {
+        if let Some(buffer) = &mut self.editing {
+            match key.code {
+                KeyCode::Char(c) => buffer.push(c),
+                KeyCode::Backspace => {
+                    buffer.pop();
+                }
+                KeyCode::Esc => self.editing = None,
+                KeyCode::Enter => {
+                    let text = self.editing.take().unwrap_or_default();
+                    let (i, field) = (self.selected(), FIELDS[self.field]);
+                    self.status = match set(&mut self.waypoints[i], field, &text) {
+                        Ok(()) => format!("{} set", field),
+                        Err(e) => e,
+                    };
+                }
+                _ => {}
+            }
+            return None;
+        }
+        self.status.clear();
+        let last = self.waypoints.len().saturating_sub(1);
+        match key.code {
+            KeyCode::Up | KeyCode::Char('k') => self.list.select(Some(self.selected().saturating_sub(1))),
+            KeyCode::Down | KeyCode::Char('j') => self.list.select(Some((self.selected() + 1).min(last))),
+            KeyCode::PageUp => self.list.select(Some(self.selected().saturating_sub(20))),
+            KeyCode::PageDown => self.list.select(Some((self.selected() + 20).min(last))),
+            KeyCode::Home => self.list.select(Some(0)),
+            KeyCode::End => self.list.select(Some(last)),
+            KeyCode::Char(' ') if !self.keep.is_empty() => {
+                let i = self.selected();
+                self.keep[i] = !self.keep[i];
+            }
+            KeyCode::Tab => self.field = (self.field + 1) % FIELDS.len(),
+            KeyCode::BackTab => self.field = (self.field + FIELDS.len() - 1) % FIELDS.len(),
+            KeyCode::Enter | KeyCode::Char('e') if !self.waypoints.is_empty() => {
+                self.editing = Some(get(&self.waypoints[self.selected()], FIELDS[self.field]));
+            }
+            KeyCode::Char('n') => {
+                let from = self.selected() + 1;
+                let next = (0..self.warnings.len()).map(|i| (from + i) % self.warnings.len()).find(|&i| !self.warnings[i].is_empty());
+                match next {
+                    Some(i) => self.list.select(Some(i)),
+                    None => self.status = "no records have warnings".to_owned(),
+                }
+            }
+            KeyCode::Char('w') => return Some(Done::Write),
+            KeyCode::Char('q') | KeyCode::Esc => return Some(Done::Cancel),
+            _ => {}
+        }
+        None
+    }
+
+    fn draw(&mut self, frame: &mut Frame) {
+        let [main, footer] = Layout::vertical([Constraint::Min(1), Constraint::Length(1)]).areas(frame.area());
+        let [records, details] = Layout::horizontal([Constraint::Percentage(50), Constraint::Percentage(50)]).areas(main);
+
+        let items: Vec<ListItem> = self
+            .waypoints
+            .iter()
+            .zip(&self.keep)
+            .zip(&self.warnings)
+            .map(|((w, &keep), warnings)| {
+                let mark = if keep { "[x]" } else { "[ ]" };
+                let style = match (keep, warnings.is_empty()) {
+                    (false, _) => Style::new().fg(Color::DarkGray).add_modifier(Modifier::CROSSED_OUT),
+                    (true, false) => Style::new().fg(Color::Yellow),
+                    (true, true) => Style::new(),
+                };
+                ListItem::new(format!("{} {:<8} {}", mark, w.ident, w.name)).style(style)
+            })
+            .collect();
+        let kept = self.keep.iter().filter(|&&k| k).count();
+        let title = format!(" Records: keeping {} of {} ", kept, self.waypoints.len());
+        let list = List::new(items)
+            .block(Block::bordered().title(title))
+            .highlight_style(Style::new().add_modifier(Modifier::REVERSED));
+        frame.render_stateful_widget(list, records, &mut self.list);
+
+        let mut lines = vec![];
+        if let Some(waypoint) = self.waypoints.get(self.selected()) {
+            for (i, field) in FIELDS.iter().enumerate() {
+                let value = match (&self.editing, i == self.field) {
+                    (Some(buffer), true) => format!("{}_", buffer),
+                    _ => get(waypoint, field),
+                };
+                let style = if i == self.field { Style::new().add_modifier(Modifier::REVERSED) } else { Style::new() };
+                lines.push(Line::from(vec![Span::styled(format!("{:<12}", field), style), Span::raw(value)]));
+            }
+            lines.push(Line::raw(""));
+            for warning in &self.warnings[self.selected()] {
+                lines.push(Line::styled(format!("! {}", warning), Style::new().fg(Color::Red)));
+            }
+        }
+        let details_block = Block::bordered().title(" Details ");
+        frame.render_widget(Paragraph::new(lines).block(details_block).wrap(Wrap { trim: false }), details);
+
+        let footer_text = if self.editing.is_some() {
+            "type the new value  enter set  esc cancel"
+        } else if !self.status.is_empty() {
+            &self.status
+        } else {
+            HELP
+        };
+        frame.render_widget(Paragraph::new(footer_text), footer);
+    }
+}
+
+fn run(terminal: &mut DefaultTerminal, review: &mut Review) -> Result<Done, Box<dyn Error>> {
+    loop {
+        terminal.draw(|frame| review.draw(frame))?;
+        if let Event::Key(key) = event::read()? {
+            if key.kind == KeyEventKind::Press {
+                if let Some(done) = review.key(key) {
+                    return Ok(done);
+                }
+            }
+        }
+    }
+}
+
+/// Shows `waypoints` with each one's `warnings` for review. Edits are made
+/// in place; returns which records to keep, or `None` when the review was
+/// cancelled.
+pub fn review(waypoints: &mut [Waypoint], warnings: Vec<Vec<String>>) -> Result<Option<Vec<bool>>, Box<dyn Error>> {
+    if !std::io::stdin().is_terminal() || !std::io::stdout().is_terminal() {
+        return Err("--review needs a terminal".into());
+    }
+    let mut review = Review {
+        keep: vec![true; waypoints.len()],
+        waypoints,
+        warnings,
+        list: ListState::default().with_selected(Some(0)),
+        field: 0,
+        editing: None,
+        status: String::new(),
+    };
+    let mut terminal = ratatui::try_init()?;
+    let done = run(&mut terminal, &mut review);
+    ratatui::restore();
+    Ok(match done? {
+        Done::Write => Some(review.keep),
+        Done::Cancel => None,
+    })
+}
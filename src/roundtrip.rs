@@ -0,0 +1,48 @@
+//! Converting records to a format and reading them back, to show whether
+//! the format keeps everything a dataset holds before it is adopted.
+
+use crate::diff::{self, DatasetDiff};
+use crate::format::{InputFormat, OutputFormat, ReadOptions, WriteOptions};
+use crate::Waypoint;
+use std::error::Error;
+use std::io::Write;
+use tempfile::NamedTempFile;
+
+/// Differences small enough to be rounding rather than loss.
+#[derive(Clone, Copy, Debug)]
+pub struct Tolerances {
+    /// Metres.
+    pub distance: f64,
+    /// Feet.
+    pub elevation: f32,
+}
+
+/// Writes `waypoints` with `output` and reads the result with `input`.
+pub fn convert_back(
+    waypoints: &[Waypoint],
+    output: &dyn OutputFormat,
+    input: &dyn InputFormat,
+    options: &WriteOptions,
+) -> Result<Vec<Waypoint>, Box<dyn Error>> {
+    // The readers take a path, so the document goes to a temporary file.
+    let mut file = NamedTempFile::new()?;
+    file.write_all(&output.render(waypoints, options)?)?;
+    file.flush()?;
+    let options = ReadOptions { delimiter: options.delimiter, ..ReadOptions::default() };
+    Ok(input.read(&file.path().to_string_lossy(), &options)?.collect())
+}
+
+/// What changed on the way through the format, by ident; elevations within
+/// the tolerance count as unchanged.
+pub fn compare(original: &[Waypoint], back: &[Waypoint], tolerances: &Tolerances) -> DatasetDiff {
+    let mut result = diff::diff(original, back, tolerances.distance);
+    let close = |a: &str, b: &str| match (a.parse::<f32>(), b.parse::<f32>()) {
+        (Ok(a), Ok(b)) => (a - b).abs() <= tolerances.elevation,
+        _ => false,
+    };
+    for changed in &mut result.changed {
+        changed.fields.retain(|f| f.field != "elevation" || !close(&f.old, &f.new));
+    }
+    result.changed.retain(|c| c.moved.is_some() || !c.fields.is_empty());
+    result
+}
@@ -0,0 +1,74 @@
+use crate::geo;
+use crate::xml::Element;
+use serde::{Deserialize, Serialize};
+
+/// One landing direction of a physical runway, e.g. the `27` end of `09/27`.
+#[derive(Debug, PartialEq, Clone, Serialize, Deserialize)]
+pub struct RunwayEnd {
+    pub designator: String,
+    pub heading: f64,
+    pub latitude: f64,
+    pub longitude: f64,
+}
+
+/// Reads the `<Runway Name="09/27" Heading="92" Length="650"/>` children of
+/// an airfield. `Heading` is true, of the first designator, and `Length` is
+/// in metres; runways without a length are skipped rather than placed on the
+/// airfield reference point. Without a `Heading` the designator times ten is
+/// used, which is magnetic and rounded to 10°: it is turned into a true
+/// bearing with `declination` (east positive) when known, and is otherwise
+/// off by the declination, some 4–7° east across Poland.
+pub fn parse_runway_ends(airfield: &Element, lat: f64, lon: f64, declination: Option<f64>) -> Vec<RunwayEnd> {
+    let mut ends = vec![];
+    for runway in airfield.children_named("Runway") {
+        let Some(name) = runway.attribute("Name") else {
+            continue;
+        };
+        let mut designators = name.split('/').map(str::trim);
+        let Some(first) = designators.next() else {
+            continue;
+        };
+        let Some(heading) = runway
+            .attribute("Heading")
+            .and_then(|h| h.parse::<f64>().ok())
+            .or_else(|| designator_heading(first).map(|h| (h + declination.unwrap_or(0.)).rem_euclid(360.)))
+        else {
+            continue;
+        };
+        let Some(length) = runway.attribute("Length").and_then(|l| l.parse::<f64>().ok()).filter(|l| *l > 0.) else {
+            continue;
+        };
+        let second = designators
+            .next()
+            .map(str::to_owned)
+            .unwrap_or_else(|| reciprocal_designator(first));
+        let half_length = length / 2.;
+        for (designator, heading) in [(first.to_owned(), heading), (second, geo::reciprocal(heading))] {
+            // A threshold lies behind the runway midpoint when looking along the landing direction.
+            let (latitude, longitude) = geo::destination(lat, lon, geo::reciprocal(heading), half_length);
+            ends.push(RunwayEnd {
+                designator,
+                heading,
+                latitude,
+                longitude,
+            });
+        }
+    }
+    ends
+}
+
+fn designator_heading(designator: &str) -> Option<f64> {
+    let digits: String = designator.chars().take_while(char::is_ascii_digit).collect();
+    digits.parse::<f64>().ok().map(|d| d * 10.)
+}
+
+fn reciprocal_designator(designator: &str) -> String {
+    let digits: String = designator.chars().take_while(char::is_ascii_digit).collect();
+    let suffix = match &designator[digits.len()..] {
+        "L" => "R",
+        "R" => "L",
+        s => s,
+    };
+    let number = digits.parse::<u32>().unwrap_or(0);
+    format!("{:02}{}", (number + 17) % 36 + 1, suffix)
+}
@@ -0,0 +1,172 @@
+//! The `serve` subcommand: the converters behind a small HTTP API, so a
+//! website can offer conversions without its own copy of the logic.
+//!
+//! - `GET /formats` lists the input and output formats as JSON.
+//! - `POST /convert?from=skydemon&to=gpx` converts the uploaded body and
+//!   sends back the result. `delimiter`, `title` and `filename`, recorded
+//!   as the import filename, are optional.
+
+use polish_airports::format::{self, ReadOptions, WriteOptions};
+use polish_airports::Waypoint;
+use std::error::Error;
+use std::io::{Read, Write};
+use std::panic::{self, AssertUnwindSafe};
+use std::thread;
+use tempfile::NamedTempFile;
+use tiny_http::{Header, Method, Request, Response, Server};
+
+type Reply = Response<std::io::Cursor<Vec<u8>>>;
+
+/// Answers requests on `address` with `workers` threads until killed.
+/// Uploads larger than `max_upload` bytes are refused.
+pub fn run(address: &str, workers: usize, max_upload: u64) -> Result<(), Box<dyn Error>> {
+    let server = Server::http(address).map_err(|e| format!("{}: {}", address, e))?;
+    eprintln!("listening on http://{}", address);
+    thread::scope(|scope| {
+        for _ in 0..workers.max(1) {
+            scope.spawn(|| {
+                while let Ok(request) = server.recv() {
+                    respond(request, max_upload);
+                }
+            });
+        }
+    });
+    Ok(())
+}
+
+fn respond(mut request: Request, max_upload: u64) {
+    let (path, query) = request.url().split_once('?').unwrap_or((request.url(), ""));
+    let params: Vec<(String, String)> = query
+        .split('&')
+        .filter_map(|pair| pair.split_once('='))
+        .map(|(key, value)| (key.to_owned(), decode(value)))
+        .collect();
+    let reply = match (request.method(), path) {
+        (Method::Get, "/formats") => Ok(formats()),
+        (Method::Post, "/convert") => convert(&mut request, &params, max_upload),
+        (_, "/formats" | "/convert") => Err((405, "method not allowed".to_owned())),
+        _ => Err((404, "not found".to_owned())),
+    };
+    let reply = reply.unwrap_or_else(|(status, message)| {
+        Response::from_string(format!("{}\n", message))
+            .with_status_code(status)
+            .with_header(header("Content-Type", "text/plain; charset=utf-8"))
+    });
+    eprintln!("{} {} {}", request.method(), request.url(), reply.status_code().0);
+    if let Err(e) = request.respond(reply) {
+        eprintln!("warning: could not send the response: {}", e);
+    }
+}
+
+/// Runs a conversion step, answering `status` when it fails or panics, so
+/// a malformed upload can't take a worker, and with it the server, down.
+fn guarded<T>(status: u16, step: impl FnOnce() -> Result<T, Box<dyn Error>>) -> Result<T, (u16, String)> {
+    match panic::catch_unwind(AssertUnwindSafe(step)) {
+        Ok(result) => result.map_err(|e| (status, e.to_string())),
+        Err(panic) => {
+            let message = panic
+                .downcast_ref::<&str>()
+                .map(|s| s.to_string())
+                .or_else(|| panic.downcast_ref::<String>().cloned())
+                .unwrap_or_else(|| "unknown error".to_owned());
+            Err((status, format!("the conversion failed: {}", message)))
+        }
+    }
+}
+
+fn header(name: &str, value: &str) -> Header {
+    Header::from_bytes(name, value).expect("header names and values are ASCII")
+}
+
+/// `value` with `+` and `%XX` escapes of a query string decoded.
+fn decode(value: &str) -> String {
+    let mut bytes = vec![];
+    let mut rest = value.as_bytes();
+    while let Some((&b, tail)) = rest.split_first() {
+        rest = tail;
+        match b {
+            b'+' => bytes.push(b' '),
+            b'%' if rest.len() >= 2 => match u8::from_str_radix(&String::from_utf8_lossy(&rest[..2]), 16) {
+                Ok(decoded) => {
+                    bytes.push(decoded);
+                    rest = &rest[2..];
+                }
+                Err(_) => bytes.push(b),
+            },
+            b => bytes.push(b),
+        }
+    }
+    String::from_utf8_lossy(&bytes).into_owned()
+}
+
+fn formats() -> Reply {
+    let inputs: Vec<_> = format::inputs()
+        .iter()
+        .map(|f| serde_json::json!({ "name": f.name(), "description": f.description() }))
+        .collect();
+    let outputs: Vec<_> = format::outputs()
+        .iter()
+        .map(|f| serde_json::json!({ "name": f.name(), "description": f.description(), "extension": f.extension() }))
+        .collect();
+    let body = serde_json::json!({ "inputs": inputs, "outputs": outputs });
+    Response::from_string(body.to_string()).with_header(header("Content-Type", "application/json"))
+}
+
+fn content_type(extension: &str) -> &'static str {
+    match extension {
+        "csv" => "text/csv; charset=utf-8",
+        "json" => "application/json",
+        "kml" => "application/vnd.google-earth.kml+xml",
+        "gpx" => "application/gpx+xml",
+        "fpl" => "application/xml",
+        "geojson" => "application/geo+json",
+        "cup" => "text/csv; charset=utf-8",
+        _ => "application/octet-stream",
+    }
+}
+
+fn convert(request: &mut Request, params: &[(String, String)], max_upload: u64) -> Result<Reply, (u16, String)> {
+    let param = |name: &str| params.iter().find(|(key, _)| key == name).map(|(_, value)| value.as_str());
+    let from = param("from").unwrap_or("skydemon");
+    let to = param("to").unwrap_or("userpoints-csv");
+    let input = format::input(from).ok_or_else(|| (400, format!("unknown input format {:?}", from)))?;
+    let output = format::output(to).ok_or_else(|| (400, format!("unknown output format {:?}", to)))?;
+    let delimiter = match param("delimiter") {
+        None => b',',
+        Some("tab") => b'\t',
+        Some(d) if d.len() == 1 && d.is_ascii() => d.as_bytes()[0],
+        Some(d) => return Err((400, format!("delimiter must be a single ASCII character or `tab`, got {:?}", d))),
+    };
+
+    // The readers take a path, so the upload goes to a temporary file first.
+    let internal = |e: std::io::Error| (500, e.to_string());
+    let mut upload = NamedTempFile::new().map_err(internal)?;
+    let copied = std::io::copy(&mut request.as_reader().take(max_upload + 1), &mut upload).map_err(internal)?;
+    if copied > max_upload {
+        return Err((413, format!("uploads are limited to {} bytes", max_upload)));
+    }
+    upload.flush().map_err(internal)?;
+
+    let path = upload.path().to_string_lossy();
+    let options = ReadOptions { delimiter, ..Default::default() };
+    let filename = param("filename").unwrap_or("upload");
+    let waypoints: Vec<Waypoint> = guarded(400, || {
+        Ok(input
+            .read(&path, &options)?
+            .map(|mut waypoint| {
+                waypoint.import_filename = Some(filename.to_owned());
+                waypoint
+            })
+            .collect())
+    })?;
+    let options = WriteOptions {
+        delimiter,
+        title: param("title").unwrap_or_default().to_owned(),
+        ..Default::default()
+    };
+    let body = guarded(500, || output.render(&waypoints, &options))?;
+    let disposition = format!("attachment; filename=\"converted.{}\"", output.extension());
+    Ok(Response::from_data(body)
+        .with_header(header("Content-Type", content_type(output.extension())))
+        .with_header(header("Content-Disposition", &disposition)))
+}
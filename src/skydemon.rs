@@ -0,0 +1,142 @@
+use crate::xml::Element;
+use crate::{elevation, region, Error, Position, Waypoint};
+use quick_xml::events::{BytesStart, Event};
+use quick_xml::XmlVersion;
+use std::borrow::Cow;
+use std::io::BufRead;
+use std::path::Path;
+use std::str::FromStr;
+
+/// Builds the waypoint of one airfield from attributes borrowed from the
+/// input; only the waypoint's own fields are allocated.
+fn airfield<'a>(attribute: impl Fn(&'static str) -> Option<&'a str>, source: &str) -> Result<Waypoint, Error> {
+    let required = |name| attribute(name).ok_or(Error::MissingAttribute(name));
+    let source = Path::new(source).file_name().map_or(source.into(), |name| name.to_string_lossy());
+    let waypoint = Waypoint::builder(required("Name")?)
+        .position(&Position::from_str(required("Position")?)?)
+        .elevation(attribute("Elevation").and_then(elevation::parse_feet))
+        .region(region::POLAND)
+        .import_filename(source);
+    match attribute("Type") {
+        Some(source_type) => waypoint.waypoint_type(userpoint_type(source_type)).build(),
+        None => waypoint.build(),
+    }
+}
+
+fn in_airfield(error: Error, name: Option<&str>, offset: u64) -> Error {
+    error.in_record(match name {
+        Some(name) => format!("{:?}", name),
+        None => format!("at byte {}", offset),
+    })
+}
+
+/// Converts one SkyDemon `Airfield` element of the file at `source`, without
+/// its runways. Errors name the airfield, or its offset when it has no name.
+pub fn airfield_waypoint(airport: &Element, source: &str) -> Result<Waypoint, Error> {
+    airfield(|name| airport.attribute(name), source).map_err(|e| in_airfield(e, airport.attribute("Name"), airport.offset))
+}
+
+/// Maps a SkyDemon airfield `Type` to the Little Navmap userpoint type,
+/// defaulting to an airstrip like untyped records.
+pub fn userpoint_type(source_type: &str) -> &'static str {
+    let source_type = source_type.to_lowercase();
+    if source_type.starts_with("heli") {
+        "Helipad"
+    } else if source_type.contains("water") || source_type.starts_with("sea") {
+        "Seaport"
+    } else if source_type == "airport" || source_type == "aerodrome" || source_type == "airfield" {
+        "Airport"
+    } else {
+        "Airstrip"
+    }
+}
+
+/// Reads the airfields of a SkyDemon XML document one at a time, without
+/// holding the whole document in memory.
+///
+/// ```no_run
+/// # use std::{fs::File, io::BufReader};
+/// # use polish_airports::AirfieldReader;
+/// let file = BufReader::new(File::open("skydemon_PL_missing.airfields.xml")?);
+/// for waypoint in AirfieldReader::new(file) {
+///     println!("{}", waypoint?.name);
+/// }
+/// # Ok::<(), Box<dyn std::error::Error>>(())
+/// ```
+pub struct AirfieldReader<R> {
+    xml: quick_xml::Reader<R>,
+    buf: Vec<u8>,
+    skipped: Vec<u8>,
+    source: String,
+    done: bool,
+}
+
+impl<R: BufRead> AirfieldReader<R> {
+    pub fn new(reader: R) -> Self {
+        AirfieldReader {
+            xml: quick_xml::Reader::from_reader(reader),
+            buf: vec![],
+            skipped: vec![],
+            source: String::new(),
+            done: false,
+        }
+    }
+
+    /// Byte offset in the document the reader has got to.
+    pub fn offset(&self) -> u64 {
+        self.xml.buffer_position()
+    }
+
+    /// Records `source` as the import filename of every waypoint.
+    pub fn with_source(mut self, source: &str) -> Self {
+        self.source = source.to_owned();
+        self
+    }
+}
+
+/// Converts an airfield straight from its start tag, with the attribute
+/// values borrowed from the read buffer unless they contain entities.
+fn start_tag_waypoint(start: &BytesStart, offset: u64, source: &str) -> Result<Waypoint, Error> {
+    let mut attributes: Vec<(&str, Cow<str>)> = Vec::with_capacity(8);
+    for attribute in start.attributes() {
+        let attribute = attribute.map_err(quick_xml::Error::from)?;
+        let value = attribute.normalized_value(XmlVersion::Implicit1_0)?;
+        attributes.push((attribute.key.local_name().into_inner(), value));
+    }
+    let attribute = |name: &str| attributes.iter().find(|(key, _)| *key == name).map(|(_, value)| value.as_ref());
+    airfield(attribute, source).map_err(|e| in_airfield(e, attribute("Name"), offset))
+}
+
+impl<R: BufRead> Iterator for AirfieldReader<R> {
+    type Item = Result<Waypoint, Error>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        while !self.done {
+            self.buf.clear();
+            let offset = self.xml.buffer_position();
+            let waypoint = match self.xml.read_event_into(&mut self.buf) {
+                Ok(Event::Start(start)) if start.local_name().as_ref() == "Airfield" => {
+                    let waypoint = start_tag_waypoint(&start, offset, &self.source);
+                    // Runways and other children are not needed here.
+                    self.skipped.clear();
+                    match self.xml.read_to_end_into(start.name(), &mut self.skipped) {
+                        Ok(_) => waypoint,
+                        Err(e) => Err(e.into()),
+                    }
+                }
+                Ok(Event::Empty(start)) if start.local_name().as_ref() == "Airfield" => {
+                    start_tag_waypoint(&start, offset, &self.source)
+                }
+                Ok(Event::Eof) => {
+                    self.done = true;
+                    continue;
+                }
+                Ok(_) => continue,
+                Err(e) => Err(e.into()),
+            };
+            self.done = waypoint.is_err();
+            return Some(waypoint);
+        }
+        None
+    }
+}
@@ -0,0 +1,176 @@
+//! Merging through a temporary SQLite database instead of memory, for
+//! merges of millions of records. Grouping matches [`merge::merge`]: each
+//! record joins the earliest site whose first record has its ident or is the
+//! same site by the matcher and holds no record of its source yet.
+
+use crate::conflict::ConflictResolver;
+use crate::matching::SiteMatcher;
+use crate::merge::{self, Merged, Precedence};
+use crate::{Error, Waypoint};
+use rusqlite::{params, Connection, OptionalExtension};
+use std::collections::HashMap;
+use std::path::Path;
+use tempfile::NamedTempFile;
+
+const METRES_PER_DEGREE: f64 = 111_320.;
+
+pub struct SpillMerge {
+    db: Connection,
+    matcher: SiteMatcher,
+    /// Grid cell size in degrees of latitude, when the matcher looks beyond
+    /// idents.
+    cell: Option<f64>,
+    sources: Vec<String>,
+    // Removed when the merge is dropped.
+    _file: NamedTempFile,
+}
+
+impl SpillMerge {
+    /// Creates the database in `dir`.
+    pub fn create(dir: &Path, matcher: &SiteMatcher) -> Result<Self, Error> {
+        let file = NamedTempFile::with_prefix_in("polish-airports-merge", dir)
+            .map_err(Error::file(&dir.to_string_lossy()))?;
+        let db = Connection::open(file.path())?;
+        db.execute_batch(
+            "PRAGMA journal_mode = OFF;
+             PRAGMA synchronous = OFF;
+             CREATE TABLE sites (id INTEGER PRIMARY KEY, ident TEXT NOT NULL, type TEXT NOT NULL,
+                                 cell_lat INTEGER NOT NULL, cell_lon INTEGER NOT NULL, first TEXT NOT NULL);
+             CREATE INDEX sites_ident ON sites (ident);
+             CREATE INDEX sites_cell ON sites (type, cell_lat, cell_lon);
+             CREATE TABLE records (site INTEGER NOT NULL, source TEXT NOT NULL, record TEXT NOT NULL,
+                                   PRIMARY KEY (site, source)) WITHOUT ROWID;
+             BEGIN;",
+        )?;
+        let radius = [matcher.distance, matcher.name_similarity.map(|_| matcher.name_radius)]
+            .into_iter()
+            .flatten()
+            .fold(None, |max: Option<f64>, r| Some(max.map_or(r, |m| m.max(r))));
+        Ok(SpillMerge {
+            db,
+            matcher: *matcher,
+            cell: radius.map(|r| (r / METRES_PER_DEGREE).max(1e-4)),
+            sources: vec![],
+            _file: file,
+        })
+    }
+
+    fn cell_of(&self, waypoint: &Waypoint) -> (i64, i64) {
+        match self.cell {
+            Some(size) => (
+                (waypoint.latitude as f64 / size).floor() as i64,
+                (waypoint.longitude as f64 / size).floor() as i64,
+            ),
+            None => (0, 0),
+        }
+    }
+
+    /// Sites whose first record may be the same site as `waypoint`, lowest
+    /// id first.
+    fn candidates(&self, waypoint: &Waypoint) -> Result<Vec<(i64, Waypoint)>, Error> {
+        let mut found: Vec<(i64, String)> = vec![];
+        let mut by_ident = self.db.prepare_cached("SELECT id, first FROM sites WHERE ident = ?1")?;
+        let rows = by_ident.query_map([waypoint.ident.to_ascii_lowercase()], |row| Ok((row.get(0)?, row.get(1)?)))?;
+        for row in rows {
+            found.push(row?);
+        }
+        if let Some(size) = self.cell {
+            let (lat, lon) = self.cell_of(waypoint);
+            // A cell spans fewer metres of longitude away from the equator.
+            let shrink = (waypoint.latitude.abs() as f64 + size).min(89.).to_radians().cos();
+            let reach = (1. / shrink).ceil() as i64;
+            let mut nearby = self.db.prepare_cached(
+                "SELECT id, first FROM sites WHERE type = ?1 AND cell_lat BETWEEN ?2 AND ?3 AND cell_lon BETWEEN ?4 AND ?5",
+            )?;
+            let rows = nearby.query_map(
+                params![waypoint.waypoint_type, lat - 1, lat + 1, lon - reach, lon + reach],
+                |row| Ok((row.get(0)?, row.get(1)?)),
+            )?;
+            for row in rows {
+                found.push(row?);
+            }
+        }
+        found.sort_by_key(|(id, _)| *id);
+        found.dedup_by_key(|(id, _)| *id);
+        found
+            .into_iter()
+            .map(|(id, first)| Ok((id, serde_json::from_str(&first)?)))
+            .collect()
+    }
+
+    /// Whether `site` already holds a record of `source`.
+    fn holds(&self, site: i64, source: &str) -> Result<bool, Error> {
+        let mut holds = self.db.prepare_cached("SELECT 1 FROM records WHERE site = ?1 AND source = ?2")?;
+        Ok(holds.exists(params![site, source])?)
+    }
+
+    /// Adds one record of `source`. Sources rank in the order they are first
+    /// seen, like the command-line order of an in-memory merge.
+    pub fn add(&mut self, source: &str, waypoint: &Waypoint) -> Result<(), Error> {
+        if !self.sources.iter().any(|s| s == source) {
+            self.sources.push(source.to_owned());
+        }
+        let record = serde_json::to_string(waypoint)?;
+        let mut site = None;
+        for (id, first) in self.candidates(waypoint)? {
+            if (first.ident.eq_ignore_ascii_case(&waypoint.ident) || self.matcher.same_site(&first, waypoint))
+                && !self.holds(id, source)?
+            {
+                site = Some(id);
+                break;
+            }
+        }
+        let site = match site {
+            Some(id) => id,
+            None => {
+                let (lat, lon) = self.cell_of(waypoint);
+                self.db
+                    .prepare_cached("INSERT INTO sites (ident, type, cell_lat, cell_lon, first) VALUES (?1, ?2, ?3, ?4, ?5)")?
+                    .execute(params![waypoint.ident.to_ascii_lowercase(), waypoint.waypoint_type, lat, lon, record])?;
+                self.db.last_insert_rowid()
+            }
+        };
+        self.db
+            .prepare_cached("INSERT INTO records (site, source, record) VALUES (?1, ?2, ?3)")?
+            .execute(params![site, source, record])?;
+        Ok(())
+    }
+
+    /// Merges each site in turn and hands it to `emit`, holding one site in
+    /// memory at a time.
+    pub fn merge<E: From<Error>>(
+        self,
+        precedence: &Precedence,
+        resolver: &mut ConflictResolver,
+        mut emit: impl FnMut(Merged) -> Result<(), E>,
+    ) -> Result<(), E> {
+        self.db.execute_batch("COMMIT;").map_err(Error::from)?;
+        let sources: Vec<&str> = self.sources.iter().map(String::as_str).collect();
+        let mut records = self
+            .db
+            .prepare("SELECT source, record FROM records WHERE site = ?1")
+            .map_err(Error::from)?;
+        let mut site = 0;
+        loop {
+            let next: Option<i64> = self
+                .db
+                .query_row("SELECT id FROM sites WHERE id > ?1 ORDER BY id LIMIT 1", [site], |row| row.get(0))
+                .optional()
+                .map_err(Error::from)?;
+            let Some(id) = next else {
+                return Ok(());
+            };
+            site = id;
+            let rows = records
+                .query_map([site], |row| Ok((row.get::<_, String>(0)?, row.get::<_, String>(1)?)))
+                .map_err(Error::from)?;
+            let mut group: Vec<(String, Waypoint)> = vec![];
+            for row in rows {
+                let (source, record) = row.map_err(Error::from)?;
+                group.push((source, serde_json::from_str(&record).map_err(Error::from)?));
+            }
+            let by_source: HashMap<&str, &Waypoint> = group.iter().map(|(s, w)| (s.as_str(), w)).collect();
+            emit(merge::merge_group(&by_source, &sources, precedence, resolver))?;
+        }
+    }
+}
@@ -0,0 +1,80 @@
+use crate::matching::to_ascii;
+use crate::{geo, Waypoint};
+use clap::ValueEnum;
+use indexmap::IndexMap;
+
+/// What to group waypoints by when writing one file per group.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, ValueEnum)]
+pub enum SplitKey {
+    /// Userpoint type, e.g. `helipads`
+    Type,
+    /// ICAO region code, e.g. `ep`
+    Region,
+    /// Polish voivodeship, e.g. `malopolskie`
+    Voivodeship,
+}
+
+/// Approximate geographic centre of each voivodeship.
+const VOIVODESHIPS: [(&str, f64, f64); 16] = [
+    ("dolnoslaskie", 51.08, 16.41),
+    ("kujawsko-pomorskie", 53.07, 18.50),
+    ("lubelskie", 51.22, 22.90),
+    ("lubuskie", 52.20, 15.25),
+    ("lodzkie", 51.60, 19.40),
+    ("malopolskie", 49.85, 20.25),
+    ("mazowieckie", 52.35, 21.10),
+    ("opolskie", 50.65, 17.90),
+    ("podkarpackie", 49.95, 22.15),
+    ("podlaskie", 53.25, 22.90),
+    ("pomorskie", 54.15, 17.90),
+    ("slaskie", 50.35, 19.00),
+    ("swietokrzyskie", 50.75, 20.75),
+    ("warminsko-mazurskie", 53.85, 20.80),
+    ("wielkopolskie", 52.35, 17.20),
+    ("zachodniopomorskie", 53.55, 15.55),
+];
+
+/// The voivodeship whose centre is nearest. Without boundary data this can
+/// misplace fields close to a border, which is good enough for sharing
+/// files out by area.
+pub fn voivodeship(waypoint: &Waypoint) -> &'static str {
+    let distance = |&(_, lat, lon): &(&str, f64, f64)| {
+        geo::distance(waypoint.latitude as f64, waypoint.longitude as f64, lat, lon)
+    };
+    VOIVODESHIPS
+        .iter()
+        .min_by(|a, b| distance(a).total_cmp(&distance(b)))
+        .map(|(name, _, _)| *name)
+        .unwrap_or_default()
+}
+
+fn slug(s: &str) -> String {
+    to_ascii(s)
+        .to_lowercase()
+        .split(|c: char| !c.is_ascii_alphanumeric())
+        .filter(|part| !part.is_empty())
+        .collect::<Vec<_>>()
+        .join("-")
+}
+
+/// File stem for the group `waypoint` belongs to, such as
+/// `airstrips_malopolskie`.
+pub fn group(waypoint: &Waypoint, keys: &[SplitKey]) -> String {
+    keys.iter()
+        .map(|key| match key {
+            SplitKey::Type => format!("{}s", slug(&waypoint.waypoint_type)),
+            SplitKey::Region => waypoint.region.as_deref().map(slug).unwrap_or_else(|| "no-region".to_owned()),
+            SplitKey::Voivodeship => voivodeship(waypoint).to_owned(),
+        })
+        .collect::<Vec<_>>()
+        .join("_")
+}
+
+/// Groups `waypoints` by `keys`, keeping groups and their members in input order.
+pub fn split(waypoints: &[Waypoint], keys: &[SplitKey]) -> IndexMap<String, Vec<Waypoint>> {
+    let mut groups: IndexMap<String, Vec<Waypoint>> = IndexMap::new();
+    for waypoint in waypoints {
+        groups.entry(group(waypoint, keys)).or_default().push(waypoint.clone());
+    }
+    groups
+}
@@ -0,0 +1,147 @@
+//! Counts that show the shape and completeness of a dataset, to judge a
+//! release's data quality at a glance.
+
+use crate::cup::FREQUENCY;
+use crate::geo::Bounds;
+use crate::Waypoint;
+use std::collections::BTreeMap;
+use std::fmt::Write;
+
+/// What [`stats`] found.
+#[derive(Debug, Clone)]
+pub struct Stats {
+    pub records: usize,
+    pub by_type: BTreeMap<String, usize>,
+    /// Records without a region are counted under `None`.
+    pub by_region: BTreeMap<Option<String>, usize>,
+    /// Lowest and highest elevation (feet).
+    pub elevation: Option<(f32, f32)>,
+    /// Records per elevation band, by the band's lower bound (feet).
+    pub histogram: BTreeMap<i64, usize>,
+    pub bucket: u32,
+    pub extent: Option<Bounds>,
+    /// How many records have each optional field, in column order.
+    pub filled: Vec<(&'static str, usize)>,
+}
+
+/// The optional fields counted for fill rate; the frequency is the one
+/// found in the description.
+const FIELDS: [&str; 9] = [
+    "Elevation",
+    "Magnetic Declination",
+    "Tags",
+    "Description",
+    "Frequency",
+    "Region",
+    "Visible From",
+    "Last Edit",
+    "Import Filename",
+];
+
+/// Which of [`FIELDS`] `waypoint` has.
+fn present(waypoint: &Waypoint) -> [bool; 9] {
+    [
+        waypoint.elevation.is_some(),
+        waypoint.magnetic_declination.is_some(),
+        waypoint.tags.is_some(),
+        waypoint.description.is_some(),
+        waypoint.description.as_deref().is_some_and(|d| FREQUENCY.is_match(d)),
+        waypoint.region.is_some(),
+        waypoint.visible_from.is_some(),
+        waypoint.last_edit.is_some(),
+        waypoint.import_filename.is_some(),
+    ]
+}
+
+/// Statistics for `waypoints`, with elevation bands `bucket` feet wide.
+pub fn stats(waypoints: &[Waypoint], bucket: u32) -> Stats {
+    let mut stats = Stats {
+        records: waypoints.len(),
+        by_type: BTreeMap::new(),
+        by_region: BTreeMap::new(),
+        elevation: None,
+        histogram: BTreeMap::new(),
+        bucket,
+        extent: None,
+        filled: vec![],
+    };
+    let mut filled = [0; FIELDS.len()];
+    for waypoint in waypoints {
+        *stats.by_type.entry(waypoint.waypoint_type.clone()).or_default() += 1;
+        *stats.by_region.entry(waypoint.region.clone()).or_default() += 1;
+        if let Some(elevation) = waypoint.elevation {
+            stats.elevation = Some(match stats.elevation {
+                Some((min, max)) => (min.min(elevation), max.max(elevation)),
+                None => (elevation, elevation),
+            });
+            let band = (elevation / bucket as f32).floor() as i64 * bucket as i64;
+            *stats.histogram.entry(band).or_default() += 1;
+        }
+        let (lat, lon) = (waypoint.latitude as f64, waypoint.longitude as f64);
+        stats.extent = Some(match stats.extent {
+            Some(b) => Bounds {
+                min_latitude: b.min_latitude.min(lat),
+                max_latitude: b.max_latitude.max(lat),
+                min_longitude: b.min_longitude.min(lon),
+                max_longitude: b.max_longitude.max(lon),
+            },
+            None => Bounds { min_latitude: lat, max_latitude: lat, min_longitude: lon, max_longitude: lon },
+        });
+        for (count, present) in filled.iter_mut().zip(present(waypoint)) {
+            *count += present as usize;
+        }
+    }
+    stats.filled = FIELDS.into_iter().zip(filled).collect();
+    stats
+}
+
+fn percent(n: usize, of: usize) -> f64 {
+    if of == 0 {
+        0.
+    } else {
+        n as f64 * 100. / of as f64
+    }
+}
+
+impl Stats {
+    /// The human-readable report.
+    pub fn text(&self) -> String {
+        let mut text = String::new();
+        writeln!(text, "{} records", self.records).unwrap();
+        writeln!(text, "by type:").unwrap();
+        for (kind, n) in &self.by_type {
+            writeln!(text, "  {:<24} {:>6}", kind, n).unwrap();
+        }
+        writeln!(text, "by region:").unwrap();
+        for (region, n) in &self.by_region {
+            writeln!(text, "  {:<24} {:>6}", region.as_deref().unwrap_or("(none)"), n).unwrap();
+        }
+        match self.elevation {
+            Some((min, max)) => writeln!(text, "elevation: {:.0} to {:.0} ft", min, max).unwrap(),
+            None => writeln!(text, "elevation: none given").unwrap(),
+        }
+        let widest = self.histogram.values().copied().max().unwrap_or(0);
+        // Empty bands between the lowest and highest are shown too.
+        if let (Some(&first), Some(&last)) = (self.histogram.keys().next(), self.histogram.keys().last()) {
+            for band in (first..=last).step_by(self.bucket as usize) {
+                let n = self.histogram.get(&band).copied().unwrap_or(0);
+                let bar = "#".repeat((n * 40).div_ceil(widest.max(1)));
+                let band = format!("{}-{} ft", band, band + self.bucket as i64);
+                writeln!(text, "{}", format!("  {:<16} {:>6} {}", band, n, bar).trim_end()).unwrap();
+            }
+        }
+        if let Some(b) = self.extent {
+            writeln!(
+                text,
+                "extent: {:.4} to {:.4} N, {:.4} to {:.4} E",
+                b.min_latitude, b.max_latitude, b.min_longitude, b.max_longitude
+            )
+            .unwrap();
+        }
+        writeln!(text, "filled:").unwrap();
+        for (field, n) in &self.filled {
+            writeln!(text, "  {:<24} {:>6} {:>5.1}%", field, n, percent(*n, self.records)).unwrap();
+        }
+        text
+    }
+}
@@ -0,0 +1,125 @@
+//! What a conversion did, printed at the end of a run, and the `--report`
+//! document built from it.
+
+use crate::Waypoint;
+use serde::Serialize;
+use std::fmt::Write;
+use tracing::debug;
+
+/// Records a step of the run dropped, and why.
+#[derive(Debug, Serialize)]
+pub struct Skipped {
+    pub reason: &'static str,
+    pub records: usize,
+}
+
+/// A problem with one record that didn't stop the run.
+#[derive(Debug, Serialize)]
+pub struct Warning {
+    /// The record's ident.
+    pub record: String,
+    pub message: String,
+}
+
+#[derive(Debug, Serialize)]
+pub struct Written {
+    pub file: String,
+    pub records: usize,
+}
+
+/// What a conversion did, printed at the end of the run.
+#[derive(Debug, Default, Serialize)]
+pub struct Summary {
+    /// Records in the input.
+    pub read: usize,
+    /// Records made from them, including generated points such as runway
+    /// thresholds.
+    pub converted: usize,
+    pub skipped: Vec<Skipped>,
+    /// Records NOTAM or METAR lookups added to.
+    pub enriched: usize,
+    /// Records merged into another of the same site, or flagged as its
+    /// possible duplicate.
+    pub deduplicated: usize,
+    pub written: Vec<Written>,
+    pub warnings: Vec<Warning>,
+}
+
+impl Summary {
+    pub fn skip(&mut self, reason: &'static str, records: usize) {
+        if records > 0 {
+            debug!(reason, records, "skipped");
+            self.skipped.push(Skipped { reason, records });
+        }
+    }
+
+    /// Prints a warning about `record` and keeps it for the report.
+    pub fn warn(&mut self, record: &str, message: String) {
+        eprintln!("warning: {}", message);
+        self.warnings.push(Warning { record: record.to_owned(), message });
+    }
+
+    /// Keeps the waypoints `keep` accepts, counting the rest as skipped;
+    /// returns the rest.
+    pub fn retain(
+        &mut self,
+        waypoints: &mut Vec<Waypoint>,
+        reason: &'static str,
+        mut keep: impl FnMut(&Waypoint) -> bool,
+    ) -> Vec<Waypoint> {
+        let dropped: Vec<Waypoint> = waypoints.extract_if(.., |w| !keep(w)).collect();
+        self.skip(reason, dropped.len());
+        dropped
+    }
+
+    pub fn text(&self) -> String {
+        let mut text = String::new();
+        writeln!(text, "read {} records, converted to {}", self.read, self.converted).unwrap();
+        if !self.skipped.is_empty() {
+            let reasons: Vec<String> = self.skipped.iter().map(|s| format!("{} {}", s.records, s.reason)).collect();
+            let total: usize = self.skipped.iter().map(|s| s.records).sum();
+            writeln!(text, "skipped {}: {}", total, reasons.join(", ")).unwrap();
+        }
+        if self.enriched > 0 {
+            writeln!(text, "enriched {}", self.enriched).unwrap();
+        }
+        if self.deduplicated > 0 {
+            writeln!(text, "deduplicated {}", self.deduplicated).unwrap();
+        }
+        for written in &self.written {
+            writeln!(text, "wrote {} to {}", written.records, written.file).unwrap();
+        }
+        if !self.warnings.is_empty() {
+            writeln!(text, "warned about {} records", self.warnings.len()).unwrap();
+        }
+        text
+    }
+}
+
+/// How the program was run.
+#[derive(Debug, Serialize)]
+pub struct Configuration {
+    pub version: &'static str,
+    pub arguments: Vec<String>,
+}
+
+/// The `--report` document.
+#[derive(Debug, Serialize)]
+pub struct Report<'a> {
+    /// `ok`, or `failed` with the `error` that stopped the run.
+    pub outcome: &'static str,
+    pub error: Option<String>,
+    pub summary: &'a Summary,
+    pub configuration: Configuration,
+}
+
+impl<'a> Report<'a> {
+    pub fn new(summary: &'a Summary, error: Option<String>, arguments: Vec<String>) -> Self {
+        Report {
+            outcome: if error.is_none() { "ok" } else { "failed" },
+            error,
+            summary,
+            configuration: Configuration { version: env!("CARGO_PKG_VERSION"), arguments },
+        }
+    }
+}
@@ -0,0 +1,66 @@
+use crate::filter::Filter;
+use crate::Waypoint;
+use serde::Deserialize;
+use std::error::Error;
+use std::fs;
+use tracing::trace;
+
+/// Tag assignment rules, loaded with `--tag-rules`. Each rule's `when` is a
+/// `--filter` expression; every matching rule adds its tags.
+///
+/// ```toml
+/// [[rule]]
+/// when = "name =~ 'szybowc'"
+/// tags = ["glider"]
+///
+/// [[rule]]
+/// when = "type == 'Airstrip' && elevation > 1000"
+/// tags = ["high"]
+/// ```
+#[derive(Deserialize)]
+#[serde(deny_unknown_fields)]
+struct RulesFile {
+    #[serde(default)]
+    rule: Vec<RuleRecord>,
+}
+
+#[derive(Deserialize)]
+#[serde(deny_unknown_fields)]
+struct RuleRecord {
+    when: String,
+    tags: Vec<String>,
+}
+
+pub struct Rule {
+    pub when: Filter,
+    pub tags: Vec<String>,
+}
+
+pub fn load(path: &str) -> Result<Vec<Rule>, Box<dyn Error>> {
+    parse(&fs::read_to_string(path)?).map_err(|e| format!("{}: {}", path, e).into())
+}
+
+/// Rules from the text of a rules file.
+pub fn parse(text: &str) -> Result<Vec<Rule>, Box<dyn Error>> {
+    let file: RulesFile = toml::from_str(text)?;
+    file.rule
+        .into_iter()
+        .map(|r| {
+            let when = r
+                .when
+                .parse::<Filter>()
+                .map_err(|e| format!("rule {:?}: {}", r.when, e))?;
+            Ok(Rule { when, tags: r.tags })
+        })
+        .collect()
+}
+
+pub fn apply(rules: &[Rule], waypoints: &mut [Waypoint]) {
+    for waypoint in waypoints {
+        let matched: Vec<&Rule> = rules.iter().filter(|r| r.when.matches(waypoint)).collect();
+        for tag in matched.into_iter().flat_map(|r| &r.tags) {
+            trace!(ident = %waypoint.ident, tag, "tagged");
+            waypoint.add_tag(tag);
+        }
+    }
+}
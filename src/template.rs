@@ -0,0 +1,91 @@
+use crate::elevation::FEET_PER_METRE;
+use crate::lang::Lang;
+use crate::xml::Element;
+use crate::Waypoint;
+use std::collections::HashMap;
+
+/// Placeholder values for one airfield: the waypoint's own fields plus what
+/// the SkyDemon record adds (`icao`, `surface`, `runways`, `length_m`, `freq`).
+/// The surface is named in `lang`.
+pub fn placeholders(waypoint: &Waypoint, airport: Option<&Element>, lang: Lang) -> HashMap<&'static str, String> {
+    let mut values = HashMap::new();
+    values.insert("name", waypoint.name.clone());
+    values.insert("ident", waypoint.ident.clone());
+    values.insert("type", waypoint.waypoint_type.clone());
+    values.insert("lat", format!("{:.5}", waypoint.latitude));
+    values.insert("lon", format!("{:.5}", waypoint.longitude));
+    if let Some(elevation) = waypoint.elevation {
+        values.insert("elev_ft", format!("{:.0}", elevation));
+        values.insert("elev_m", format!("{:.0}", elevation / FEET_PER_METRE));
+    }
+    for (key, value) in [
+        ("region", &waypoint.region),
+        ("tags", &waypoint.tags),
+        ("description", &waypoint.description),
+    ] {
+        if let Some(value) = value {
+            values.insert(key, value.clone());
+        }
+    }
+    let Some(airport) = airport else {
+        return values;
+    };
+    if let Some(icao) = airport.attribute("ICAO") {
+        values.insert("icao", icao.to_owned());
+    }
+    let runways: Vec<&Element> = airport.children_named("Runway").collect();
+    if let Some(surface) = runways.iter().find_map(|r| r.attribute("Surface")) {
+        values.insert("surface", lang.surface(surface));
+    }
+    let names: Vec<&str> = runways.iter().filter_map(|r| r.attribute("Name")).collect();
+    if !names.is_empty() {
+        values.insert("runways", names.join(" "));
+    }
+    let longest = runways
+        .iter()
+        .filter_map(|r| r.attribute("Length")?.parse::<f64>().ok())
+        .fold(None, |max: Option<f64>, l| Some(max.map_or(l, |m| m.max(l))));
+    if let Some(length) = longest {
+        values.insert("length_m", format!("{:.0}", length));
+    }
+    let frequency = airport.attribute("Frequency").or_else(|| {
+        airport
+            .children_named("Frequency")
+            .next()
+            .and_then(|f| f.attribute("Value"))
+    });
+    if let Some(frequency) = frequency {
+        values.insert("freq", frequency.to_owned());
+    }
+    values
+}
+
+/// Fills `{placeholder}`s in a template. The template is split on `, ` and
+/// parts whose placeholders are all unknown or empty are dropped, so
+/// `"Elev {elev_ft} ft, {surface}, {freq}"` doesn't leave dangling commas.
+pub fn render(template: &str, values: &HashMap<&str, String>) -> String {
+    let mut parts = vec![];
+    for part in template.split(", ") {
+        let mut rendered = String::new();
+        let mut placeholders = 0;
+        let mut filled = 0;
+        let mut rest = part;
+        while let Some(start) = rest.find('{') {
+            let Some(len) = rest[start..].find('}') else {
+                break;
+            };
+            rendered.push_str(&rest[..start]);
+            placeholders += 1;
+            if let Some(value) = values.get(&rest[start + 1..start + len]).filter(|v| !v.is_empty()) {
+                rendered.push_str(value);
+                filled += 1;
+            }
+            rest = &rest[start + len + 1..];
+        }
+        rendered.push_str(rest);
+        if placeholders == 0 || filled > 0 {
+            parts.push(rendered);
+        }
+    }
+    parts.join(", ")
+}
@@ -0,0 +1,33 @@
+use std::time::{Duration, Instant};
+
+/// Wall-clock time of each stage of a run, for `--stats-timing`.
+pub struct Stopwatch {
+    started: Instant,
+    last: Instant,
+    stages: Vec<(&'static str, Duration)>,
+}
+
+impl Stopwatch {
+    pub fn start() -> Self {
+        let now = Instant::now();
+        Stopwatch { started: now, last: now, stages: vec![] }
+    }
+
+    /// Ends `stage`, which ran since the previous one ended.
+    pub fn lap(&mut self, stage: &'static str) {
+        let now = Instant::now();
+        self.stages.push((stage, now - self.last));
+        self.last = now;
+    }
+
+    /// Prints the stages and the overall throughput to stderr.
+    pub fn report(&self, records: usize) {
+        let total = self.last - self.started;
+        for (stage, duration) in &self.stages {
+            eprintln!("{:<10} {:>10.1} ms", stage, duration.as_secs_f64() * 1000.);
+        }
+        eprintln!("{:<10} {:>10.1} ms", "total", total.as_secs_f64() * 1000.);
+        let rate = records as f64 / total.as_secs_f64().max(f64::EPSILON);
+        eprintln!("{} records, {:.0} records/s", records, rate);
+    }
+}
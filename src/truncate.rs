@@ -0,0 +1,45 @@
+use std::collections::{HashMap, HashSet};
+
+/// The first `max` characters of `s`, without trailing whitespace.
+pub fn truncate(s: &str, max: usize) -> String {
+    let truncated: String = s.chars().take(max).collect();
+    truncated.trim_end().to_owned()
+}
+
+/// Shortens every value to at most `max` characters without letting two
+/// different values end up the same: on a collision the tail is replaced by
+/// the lowest free number (`MINIKOWO`, `MINIKOWSKI` → `MINIKO`, `MINIK2`).
+/// Equal inputs map to equal outputs, so a route visiting a point twice keeps
+/// one identifier for it. A `max` of 0 is taken as 1.
+pub fn shorten_unique(values: &[String], max: usize) -> Vec<String> {
+    let max = max.max(1);
+    let mut assigned: HashMap<&str, String> = HashMap::new();
+    let mut taken: HashSet<String> = values
+        .iter()
+        .filter(|v| v.chars().count() <= max)
+        .cloned()
+        .collect();
+    let mut result = vec![];
+    for value in values {
+        if let Some(short) = assigned.get(value.as_str()) {
+            result.push(short.clone());
+            continue;
+        }
+        let short = if value.chars().count() <= max {
+            value.clone()
+        } else {
+            let mut candidate = truncate(value, max);
+            let mut n = 2;
+            while taken.contains(&candidate) {
+                let suffix = n.to_string();
+                candidate = format!("{}{}", truncate(value, max.saturating_sub(suffix.len())), suffix);
+                n += 1;
+            }
+            taken.insert(candidate.clone());
+            candidate
+        };
+        assigned.insert(value, short.clone());
+        result.push(short);
+    }
+    result
+}
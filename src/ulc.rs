@@ -0,0 +1,304 @@
+//! The registry of landing sites (ewidencja lądowisk) kept by the Polish
+//! Civil Aviation Authority (ULC). It is published as a spreadsheet meant
+//! for reading rather than parsing: title rows above the header, names in
+//! capitals and quotes, and coordinates typed by hand in whatever notation
+//! the clerk preferred.
+
+use crate::elevation::FEET_PER_METRE;
+use crate::format::{Fields, InputFormat, ReadOptions, Records};
+use crate::{hems, input, region, Coordinate, Waypoint};
+use calamine::{Reader, Sheets};
+use regex::Regex;
+use std::error::Error;
+use std::io::Cursor;
+use std::sync::LazyLock;
+
+/// One coordinate in degrees, minutes and optional seconds, with the
+/// hemisphere letter on either side: `52°10'30,5"N`, `N 52° 10.5'`.
+static DMS: LazyLock<Regex> = LazyLock::new(|| {
+    Regex::new(
+        r#"(?x)
+        ([NSEW])?\s*
+        (\d{1,3})\s*[°º˚]\s*
+        (\d{1,2}(?:\.\d+)?)\s*(?:['′’]|$|\s)\s*
+        (?:(\d{1,2}(?:\.\d+)?)\s*(?:"|''|″|”|’’)?)?\s*
+        ([NSEW])?"#,
+    )
+    .expect("the pattern is valid")
+});
+
+/// Where each field is in a registry row.
+#[derive(Debug, Default)]
+struct Layout {
+    name: usize,
+    number: Option<usize>,
+    kind: Option<usize>,
+    /// Latitude and longitude in one cell.
+    position: Option<usize>,
+    latitude: Option<usize>,
+    longitude: Option<usize>,
+    elevation: Option<(usize, bool)>,
+    manager: Option<usize>,
+}
+
+impl Layout {
+    /// The layout given by a header row, when `row` is one: it names the
+    /// landing site and gives its position.
+    fn from_header(row: &[String]) -> Option<Self> {
+        let headers: Vec<String> = row.iter().map(|h| h.to_lowercase()).collect();
+        let find = |keys: &[&str]| headers.iter().position(|h| keys.iter().any(|k| h.contains(k)));
+        let mut layout = Layout {
+            name: find(&["nazwa"])?,
+            number: find(&["numer", "nr "]),
+            kind: find(&["rodzaj", "typ"]),
+            position: find(&["współrzędne", "wspolrzedne"]),
+            latitude: find(&["szerokość", "szerokosc"]),
+            longitude: find(&["długość", "dlugosc"]),
+            manager: find(&["zarządzający", "zarzadzajacy", "operator"]),
+            ..Default::default()
+        };
+        layout.elevation = find(&["elewacja", "wysokość", "wysokosc", "wzniesienie"])
+            .map(|i| (i, headers[i].contains("ft") || headers[i].contains("stóp")));
+        (layout.position.is_some() || (layout.latitude.is_some() && layout.longitude.is_some())).then_some(layout)
+    }
+}
+
+/// `text` with `,` decimal separators turned into `.`, so both parse.
+pub(crate) fn decimal_point(text: &str) -> String {
+    text.trim().replace(',', ".")
+}
+
+/// Parses one or two coordinates out of `text`, as `(hemisphere, degrees)`.
+/// Plain decimal degrees and the `N520101.50` form are accepted too.
+pub(crate) fn coordinates(text: &str) -> Vec<(Option<char>, f32)> {
+    let text = decimal_point(text);
+    if let Ok(degrees) = Coordinate::parse_decimal(&text) {
+        return vec![(None, degrees)];
+    }
+    let found: Vec<_> = DMS
+        .captures_iter(&text)
+        .map(|c| {
+            let number = |i: usize| c.get(i).map_or(0., |m| m.as_str().parse::<f32>().unwrap_or(0.));
+            let hemisphere = c.get(1).or_else(|| c.get(5)).and_then(|m| m.as_str().chars().next());
+            let degrees = number(2) + number(3) / 60. + number(4) / 3600.;
+            match hemisphere {
+                Some('S' | 'W') => (hemisphere, -degrees),
+                _ => (hemisphere, degrees),
+            }
+        })
+        .collect();
+    if !found.is_empty() {
+        return found;
+    }
+    // Two numbers in one cell, e.g. `52.1234 21.5678`
+    let numbers: Vec<f32> = text
+        .split(|c: char| c.is_whitespace() || c == ';' || c == '/')
+        .filter_map(|part| Coordinate::parse_decimal(part).ok())
+        .collect();
+    numbers.into_iter().map(|n| (None, n)).collect()
+}
+
+/// Latitude and longitude from a cell with both, or else from one cell
+/// each.
+pub(crate) fn position(both: &str, latitude: &str, longitude: &str) -> Option<(f32, f32)> {
+    let mut found = coordinates(both);
+    if found.len() < 2 {
+        found = coordinates(latitude);
+        found.extend(coordinates(longitude));
+    }
+    let [first, second] = found[..] else {
+        return None;
+    };
+    // Trust the hemisphere letters over the order when they are given.
+    match (first.0, second.0) {
+        (Some('E' | 'W'), _) | (_, Some('N' | 'S')) => Some((second.1, first.1)),
+        _ => Some((first.1, second.1)),
+    }
+}
+
+/// `name` as used elsewhere: quotes dropped, spaces collapsed, and capitals
+/// turned into title case.
+pub(crate) fn tidy_name(name: &str) -> String {
+    let name = name.replace(['"', '„', '”', '“', '«', '»'], "");
+    let name = name.split_whitespace().collect::<Vec<_>>().join(" ");
+    if name.chars().any(char::is_lowercase) {
+        return name;
+    }
+    let mut tidy = String::with_capacity(name.len());
+    let mut start = true;
+    for c in name.chars() {
+        if start {
+            tidy.push(c);
+        } else {
+            tidy.extend(c.to_lowercase());
+        }
+        start = !c.is_alphanumeric();
+    }
+    tidy
+}
+
+/// The userpoint type for the registry's kind of landing site.
+fn userpoint_type(kind: &str) -> &'static str {
+    let kind = kind.to_lowercase();
+    if kind.contains("wodn") {
+        "Seaport"
+    } else if kind.contains("przyszpital") {
+        hems::HEMS_TYPE
+    } else if kind.contains("śmigłow") && !kind.contains("samolot") {
+        "Helipad"
+    } else {
+        "Airstrip"
+    }
+}
+
+/// The sheet's rows as text, each cell trimmed.
+pub(crate) fn rows(path: &str, delimiter: u8) -> Result<Vec<Vec<String>>, Box<dyn Error>> {
+    if path.to_lowercase().ends_with(".csv") {
+        let mut reader = csv::ReaderBuilder::new()
+            .delimiter(delimiter)
+            .has_headers(false)
+            .flexible(true)
+            .from_reader(input::open(path)?);
+        return reader
+            .records()
+            .map(|r| Ok(r?.iter().map(|c| c.trim().to_owned()).collect()))
+            .collect();
+    }
+    let input = input::open(path)?;
+    let mut workbook: Sheets<_> = calamine::open_workbook_auto_from_rs(Cursor::new(&input.get_ref()[..]))?;
+    let range = workbook
+        .worksheet_range_at(0)
+        .ok_or_else(|| format!("{}: the workbook has no sheets", path))??;
+    Ok(range.rows().map(|row| row.iter().map(|c| c.to_string().trim().to_owned()).collect()).collect())
+}
+
+/// Reads the ULC registry from an `.xlsx`, `.xls` or `.ods` spreadsheet, or
+/// a `.csv` saved from one. The first row naming the landing site and its
+/// position is taken as the header; rows above it and rows without a name
+/// are skipped.
+pub fn read_registry(path: &str, delimiter: u8) -> Result<Vec<Waypoint>, Box<dyn Error>> {
+    let rows = rows(path, delimiter)?;
+    let (header, layout) = rows
+        .iter()
+        .enumerate()
+        .find_map(|(i, row)| Some((i, Layout::from_header(row)?)))
+        .ok_or_else(|| format!("{}: no header row with the landing site name and coordinates", path))?;
+    let mut waypoints = vec![];
+    for (i, row) in rows.iter().enumerate().skip(header + 1) {
+        let cell = |column: Option<usize>| column.and_then(|c| row.get(c)).cloned().unwrap_or_default();
+        let name = tidy_name(&cell(Some(layout.name)));
+        if name.is_empty() {
+            continue;
+        }
+        let (latitude, longitude) = position(&cell(layout.position), &cell(layout.latitude), &cell(layout.longitude))
+            .ok_or_else(|| format!("{}: row {} ({}): unreadable coordinates", path, i + 1, name))?;
+        let elevation = layout.elevation.and_then(|(column, feet)| {
+            let value = decimal_point(&cell(Some(column)));
+            let value = value.trim_end_matches(|c: char| c.is_alphabetic() || c == '.' || c.is_whitespace());
+            let value = value.parse::<f32>().ok()?;
+            Some(if feet { value } else { (value * FEET_PER_METRE).round() })
+        });
+        let mut waypoint = Waypoint::builder(&name)
+            .waypoint_type(userpoint_type(&cell(layout.kind)))
+            .coordinates(latitude, longitude)
+            .elevation(elevation)
+            .region(region::POLAND)
+            .import_filename(path)
+            .build()
+            .map_err(|e| e.in_record(format!("{}: row {}", path, i + 1)))?;
+        let description: Vec<String> = [
+            Some(cell(layout.number)).filter(|n| !n.is_empty()).map(|n| format!("ULC {}", n)),
+            Some(cell(layout.manager)).filter(|m| !m.is_empty()),
+        ]
+        .into_iter()
+        .flatten()
+        .collect();
+        waypoint.description = (!description.is_empty()).then(|| description.join("; "));
+        waypoints.push(waypoint);
+    }
+    Ok(waypoints)
+}
+
+pub struct UlcRegistry;
+
+impl InputFormat for UlcRegistry {
+    fn name(&self) -> &'static str {
+        "ulc-registry"
+    }
+
+    fn description(&self) -> &'static str {
+        "ULC registry of landing sites (lądowiska) as a spreadsheet or CSV"
+    }
+
+    fn fields(&self) -> Fields {
+        Fields { elevation: true, ..Fields::default() }
+    }
+
+    fn read(&self, path: &str, options: &ReadOptions) -> Result<Records, Box<dyn Error>> {
+        Ok(Box::new(read_registry(path, options.delimiter)?.into_iter()))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn close(actual: Option<(f32, f32)>, latitude: f32, longitude: f32) -> bool {
+        actual.is_some_and(|(lat, lon)| (lat - latitude).abs() < 1e-4 && (lon - longitude).abs() < 1e-4)
+    }
+
+    #[test]
+    fn decimal_degrees() {
+        assert!(close(position("", "52.1234", "21.5678"), 52.1234, 21.5678));
+        assert!(close(position("", "52,1234", "21,5678"), 52.1234, 21.5678));
+    }
+
+    #[test]
+    fn two_decimals_in_one_cell() {
+        assert!(close(position("52.1234 21.5678", "", ""), 52.1234, 21.5678));
+        assert!(close(position("52.1234; 21.5678", "", ""), 52.1234, 21.5678));
+        assert!(close(position("52.1234/21.5678", "", ""), 52.1234, 21.5678));
+    }
+
+    #[test]
+    fn skydemon_form() {
+        assert!(close(position("", "N520101.50", "E0170101.50"), 52.01708, 17.01708));
+        assert!(close(position("N520101.50 E0170101.50", "", ""), 52.01708, 17.01708));
+    }
+
+    #[test]
+    fn degrees_minutes_seconds_with_trailing_hemisphere() {
+        let expected = (52. + 10. / 60. + 30.5 / 3600., 21. + 5. / 60. + 7. / 3600.);
+        assert!(close(position(r#"52°10'30,5"N 021°05'07"E"#, "", ""), expected.0, expected.1));
+        assert!(close(position("", "52° 10′ 30.5″ N", "21° 05′ 07″ E"), expected.0, expected.1));
+        assert!(close(position("", "52º10’30.5”N", "21º05’07”E"), expected.0, expected.1));
+        assert!(close(position("", "52°10'30.5''N", "21°05'07''E"), expected.0, expected.1));
+    }
+
+    #[test]
+    fn degrees_and_decimal_minutes_with_leading_hemisphere() {
+        assert!(close(position("", "N 52° 10.5'", "E 21° 05.25'"), 52.175, 21.0875));
+        assert!(close(position("N52°10.5' E21°05.25'", "", ""), 52.175, 21.0875));
+    }
+
+    #[test]
+    fn hemisphere_letters_win_over_order() {
+        assert!(close(position(r#"21°05'07"E 52°10'30"N"#, "", ""), 52.175, 21.08528));
+        assert!(close(position(r#"52°10'30"S 021°05'07"W"#, "", ""), -52.175, -21.08528));
+    }
+
+    #[test]
+    fn the_position_cell_wins_over_separate_cells() {
+        assert!(close(position("52.5 21.5", "50.0", "20.0"), 52.5, 21.5));
+        assert!(close(position("52.5", "50.0", "20.0"), 50.0, 20.0));
+    }
+
+    #[test]
+    fn unreadable_positions_are_rejected() {
+        assert_eq!(position("", "", ""), None);
+        assert_eq!(position("brak danych", "", ""), None);
+        assert_eq!(position("", "52.1234", ""), None);
+        assert_eq!(position("", "północ", "wschód"), None);
+        assert_eq!(position("52.1 21.2 19.3", "", ""), None);
+    }
+}
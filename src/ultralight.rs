@@ -0,0 +1,21 @@
+//! Tags fields likely used by ultralights and microlights, which the sources
+//! rarely mark. The rules are a `--tag-rules` file kept in
+//! `rules/ultralight.toml`, so they can be tuned without a rebuild.
+
+use crate::tagging::{self, Rule};
+use std::error::Error;
+
+pub const ULTRALIGHT_TAG: &str = "ultralight";
+
+/// The rules built in, from `rules/ultralight.toml`.
+pub const DEFAULT_RULES: &str = include_str!("../rules/ultralight.toml");
+
+/// The rules in the file at `path`, or the built-in ones; apply them with
+/// [`tagging::apply`].
+pub fn rules(path: Option<&str>) -> Result<Vec<Rule>, Box<dyn Error>> {
+    match path {
+        Some(path) => tagging::load(path),
+        None => tagging::parse(DEFAULT_RULES),
+    }
+}
+
@@ -0,0 +1,114 @@
+//! `self-update`: replaces the running binary with the build for this
+//! platform from the latest release, for users without a Rust toolchain.
+
+use serde::Deserialize;
+use sha2::{Digest, Sha256};
+use std::env;
+use std::error::Error;
+use std::fs;
+use std::io::Cursor;
+use std::path::Path;
+
+pub const RELEASES_URL: &str = "https://api.github.com/repos/laxentis/polish-airports/releases/latest";
+
+/// Largest binary accepted from a release.
+const MAX_SIZE: u64 = 256 * 1024 * 1024;
+
+#[derive(Debug, Deserialize)]
+struct Release {
+    tag_name: String,
+    assets: Vec<Asset>,
+}
+
+#[derive(Debug, Deserialize)]
+struct Asset {
+    name: String,
+    browser_download_url: String,
+}
+
+impl Release {
+    fn asset(&self, name: &str) -> Option<&Asset> {
+        self.assets.iter().find(|a| a.name == name)
+    }
+}
+
+/// The release asset built for this platform, e.g.
+/// `polish-airports-x86_64-linux` or `polish-airports-x86_64-windows.exe`.
+fn asset_name() -> String {
+    format!("polish-airports-{}-{}{}", env::consts::ARCH, env::consts::OS, env::consts::EXE_SUFFIX)
+}
+
+/// `v1.2.3` or `1.2.3` as numbers to compare; anything after a `-` is ignored.
+fn version(tag: &str) -> Option<Vec<u64>> {
+    let tag = tag.trim().trim_start_matches('v');
+    tag.split('-').next()?.split('.').map(|part| part.parse().ok()).collect()
+}
+
+fn get(agent: &ureq::Agent, url: &str) -> Result<Vec<u8>, Box<dyn Error>> {
+    let response = agent
+        .get(url)
+        .header("User-Agent", concat!("polish-airports/", env!("CARGO_PKG_VERSION")))
+        .header("Accept", "application/vnd.github+json, application/octet-stream")
+        .call()
+        .map_err(|e| format!("{}: {}", url, e))?;
+    Ok(response.into_body().with_config().limit(MAX_SIZE).read_to_vec().map_err(|e| format!("{}: {}", url, e))?)
+}
+
+/// Checks it against the release's `<asset>.sha256`, and its `.minisig`
+/// when a public key is given.
+fn verify(agent: &ureq::Agent, release: &Release, name: &str, binary: &[u8], public_key: Option<&Path>) -> Result<(), Box<dyn Error>> {
+    let checksum = release.asset(&format!("{}.sha256", name)).ok_or_else(|| format!("the release has no checksum for {}", name))?;
+    let expected = String::from_utf8(get(agent, &checksum.browser_download_url)?)?;
+    let expected = expected.split_whitespace().next().unwrap_or_default().to_lowercase();
+    let actual: String = Sha256::digest(binary).iter().map(|b| format!("{:02x}", b)).collect();
+    if expected != actual {
+        return Err(format!("{} does not match its checksum, nothing was replaced", name).into());
+    }
+    if let Some(public_key) = public_key {
+        let key = minisign::PublicKey::from_file(public_key)?;
+        let signature = release.asset(&format!("{}.minisig", name)).ok_or_else(|| format!("the release has no signature for {}", name))?;
+        let signature = minisign::SignatureBox::from_string(&String::from_utf8(get(agent, &signature.browser_download_url)?)?)?;
+        minisign::verify(&key, &signature, Cursor::new(binary), true, false, false)
+            .map_err(|e| format!("{} is not signed by {}: {}", name, public_key.display(), e))?;
+    }
+    Ok(())
+}
+
+/// Writes `binary` next to the running executable and moves it into place.
+fn replace(binary: &[u8]) -> Result<(), Box<dyn Error>> {
+    let exe = env::current_exe()?;
+    let new = exe.with_extension("new");
+    fs::write(&new, binary).map_err(|e| format!("{}: {}", new.display(), e))?;
+    fs::set_permissions(&new, fs::metadata(&exe)?.permissions())?;
+    // Windows won't overwrite a running executable but will rename one.
+    if cfg!(windows) {
+        let old = exe.with_extension("old");
+        let _ = fs::remove_file(&old);
+        fs::rename(&exe, &old)?;
+    }
+    fs::rename(&new, &exe).map_err(|e| format!("{}: {}", exe.display(), e))?;
+    Ok(())
+}
+
+/// Looks up the latest release at `url` and, unless `check` is set,
+/// installs it over the running binary when it is newer.
+pub fn run(agent: &ureq::Agent, url: &str, check: bool, public_key: Option<&Path>) -> Result<(), Box<dyn Error>> {
+    let current = env!("CARGO_PKG_VERSION");
+    let release: Release = serde_json::from_slice(&get(agent, url)?)?;
+    let latest = version(&release.tag_name).ok_or_else(|| format!("unrecognised release version {:?}", release.tag_name))?;
+    if version(current).is_some_and(|current| latest <= current) {
+        eprintln!("polish-airports {} is up to date", current);
+        return Ok(());
+    }
+    if check {
+        eprintln!("polish-airports {} is available, {} is installed", release.tag_name, current);
+        return Ok(());
+    }
+    let name = asset_name();
+    let asset = release.asset(&name).ok_or_else(|| format!("release {} has no build for this platform ({})", release.tag_name, name))?;
+    let binary = get(agent, &asset.browser_download_url)?;
+    verify(agent, &release, &name, &binary, public_key)?;
+    replace(&binary)?;
+    eprintln!("updated polish-airports from {} to {}", current, release.tag_name);
+    Ok(())
+}
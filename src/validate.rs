@@ -0,0 +1,286 @@
+//! Checks a dataset against a set of rules and reports, per rule, whether
+//! it passed and which records failed it. The rules come from a TOML file;
+//! sections left out keep their defaults and `enabled = false` turns a rule
+//! off.
+//!
+//! ```toml
+//! [coordinates]
+//! min_latitude = 49.0
+//! max_latitude = 54.9
+//! min_longitude = 14.1
+//! max_longitude = 24.2
+//!
+//! [ident]
+//! pattern = '^[A-Z0-9]{3,6}$'
+//!
+//! [elevation]
+//! max = 5500
+//! dem_tolerance = 150
+//!
+//! [name_length]
+//! max = 30
+//! ```
+
+use crate::dem::Dem;
+use crate::elevation::FEET_PER_METRE;
+use crate::idents::{self, IcaoIdent};
+use crate::Waypoint;
+use regex::Regex;
+use serde::Deserialize;
+use std::collections::HashMap;
+use std::error::Error;
+use std::fmt::Write;
+use std::fs;
+
+#[derive(Debug, Clone, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct CoordinateRule {
+    pub enabled: bool,
+    pub min_latitude: f32,
+    pub max_latitude: f32,
+    pub min_longitude: f32,
+    pub max_longitude: f32,
+}
+
+impl Default for CoordinateRule {
+    fn default() -> Self {
+        CoordinateRule { enabled: true, min_latitude: -90., max_latitude: 90., min_longitude: -180., max_longitude: 180. }
+    }
+}
+
+#[derive(Debug, Clone, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct IdentRule {
+    pub enabled: bool,
+    /// A regular expression the whole ident must match; by default anything
+    /// without leading or trailing blanks.
+    pub pattern: String,
+}
+
+impl Default for IdentRule {
+    fn default() -> Self {
+        IdentRule { enabled: true, pattern: r"^\S(.*\S)?$".to_owned() }
+    }
+}
+
+/// Idents claiming to be Polish ICAO codes must be well-formed ones; see
+/// [`idents::normalize_icao`].
+#[derive(Debug, Clone, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct IcaoRule {
+    pub enabled: bool,
+}
+
+impl Default for IcaoRule {
+    fn default() -> Self {
+        IcaoRule { enabled: true }
+    }
+}
+
+#[derive(Debug, Clone, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct DuplicateRule {
+    pub enabled: bool,
+}
+
+impl Default for DuplicateRule {
+    fn default() -> Self {
+        DuplicateRule { enabled: true }
+    }
+}
+
+#[derive(Debug, Clone, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct ElevationRule {
+    pub enabled: bool,
+    /// Feet; the default range runs from the Dead Sea shore to Everest.
+    pub min: f32,
+    pub max: f32,
+    /// How far an elevation may be from the terrain model, when one is
+    /// given (feet).
+    pub dem_tolerance: f32,
+}
+
+impl Default for ElevationRule {
+    fn default() -> Self {
+        ElevationRule { enabled: true, min: -1400., max: 29100., dem_tolerance: 300. }
+    }
+}
+
+#[derive(Debug, Clone, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct NameLengthRule {
+    pub enabled: bool,
+    /// Characters.
+    pub max: usize,
+}
+
+impl Default for NameLengthRule {
+    fn default() -> Self {
+        NameLengthRule { enabled: true, max: 60 }
+    }
+}
+
+#[derive(Debug, Clone, Default, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct Rules {
+    pub coordinates: CoordinateRule,
+    pub ident: IdentRule,
+    pub icao: IcaoRule,
+    pub duplicate_idents: DuplicateRule,
+    pub elevation: ElevationRule,
+    pub name_length: NameLengthRule,
+}
+
+impl Rules {
+    pub fn load(path: &str) -> Result<Self, Box<dyn Error>> {
+        toml::from_str(&fs::read_to_string(path)?).map_err(|e| format!("{}: {}", path, e).into())
+    }
+}
+
+/// A record that failed a rule.
+#[derive(Debug, Clone)]
+pub struct Failure {
+    /// Its index in the records checked.
+    pub record: usize,
+    pub message: String,
+}
+
+/// What one rule found.
+#[derive(Debug, Clone)]
+pub struct Outcome {
+    pub rule: &'static str,
+    /// How many records the rule looked at.
+    pub checked: usize,
+    pub failures: Vec<Failure>,
+}
+
+impl Outcome {
+    pub fn passed(&self) -> bool {
+        self.failures.is_empty()
+    }
+}
+
+fn outcome(rule: &'static str, checked: usize, failures: Vec<Failure>) -> Outcome {
+    Outcome { rule, checked, failures }
+}
+
+fn failure(record: usize, message: String) -> Failure {
+    Failure { record, message }
+}
+
+/// Runs the enabled rules over `waypoints`; elevations are also compared
+/// with the terrain in `dem` when there is one.
+pub fn validate(waypoints: &[Waypoint], rules: &Rules, dem: Option<&mut Dem>) -> Result<Vec<Outcome>, Box<dyn Error>> {
+    let mut outcomes = vec![];
+    let c = &rules.coordinates;
+    if c.enabled {
+        let failures = waypoints
+            .iter()
+            .enumerate()
+            .filter(|(_, w)| {
+                !(c.min_latitude..=c.max_latitude).contains(&w.latitude)
+                    || !(c.min_longitude..=c.max_longitude).contains(&w.longitude)
+            })
+            .map(|(i, w)| failure(i, format!("{}: {} {} is outside the allowed area", w.ident, w.latitude, w.longitude)))
+            .collect();
+        outcomes.push(outcome("coordinates", waypoints.len(), failures));
+    }
+    if rules.ident.enabled {
+        let pattern = &rules.ident.pattern;
+        let regex = Regex::new(pattern).map_err(|e| format!("ident pattern {:?}: {}", pattern, e))?;
+        let failures = waypoints
+            .iter()
+            .enumerate()
+            .filter(|(_, w)| !regex.is_match(&w.ident))
+            .map(|(i, w)| failure(i, format!("{:?} ({}) doesn't match {}", w.ident, w.name, pattern)))
+            .collect();
+        outcomes.push(outcome("ident", waypoints.len(), failures));
+    }
+    if rules.icao.enabled {
+        let failures = waypoints
+            .iter()
+            .enumerate()
+            .filter(|(_, w)| {
+                // Codes that only need trimming or upper-casing fail too.
+                let mut normalized = Waypoint::clone(w);
+                match idents::normalize_icao(&mut normalized) {
+                    IcaoIdent::Icao => normalized.ident != w.ident,
+                    IcaoIdent::Pseudo => true,
+                    IcaoIdent::Other => false,
+                }
+            })
+            .map(|(i, w)| failure(i, format!("{:?} ({}) is not a well-formed ICAO code", w.ident, w.name)))
+            .collect();
+        outcomes.push(outcome("icao", waypoints.len(), failures));
+    }
+    if rules.duplicate_idents.enabled {
+        let mut counts: HashMap<String, usize> = HashMap::new();
+        for waypoint in waypoints {
+            *counts.entry(waypoint.ident.to_uppercase()).or_default() += 1;
+        }
+        let mut failures = vec![];
+        for (i, waypoint) in waypoints.iter().enumerate() {
+            match counts.remove(&waypoint.ident.to_uppercase()) {
+                Some(n) if n > 1 => failures.push(failure(i, format!("{}: used by {} records", waypoint.ident, n))),
+                _ => {}
+            }
+        }
+        outcomes.push(outcome("duplicate idents", waypoints.len(), failures));
+    }
+    let e = &rules.elevation;
+    if e.enabled {
+        let mut dem = dem;
+        let mut checked = 0;
+        let mut failures = vec![];
+        for (i, waypoint) in waypoints.iter().enumerate() {
+            let Some(elevation) = waypoint.elevation else {
+                continue;
+            };
+            checked += 1;
+            if !(e.min..=e.max).contains(&elevation) {
+                let message = format!("{}: {} ft is outside {}..{} ft", waypoint.ident, elevation, e.min, e.max);
+                failures.push(failure(i, message));
+                continue;
+            }
+            let Some(dem) = dem.as_deref_mut() else {
+                continue;
+            };
+            let Some(terrain) = dem.elevation(waypoint.latitude as f64, waypoint.longitude as f64)? else {
+                continue;
+            };
+            let terrain = (terrain * FEET_PER_METRE).round();
+            if (elevation - terrain).abs() > e.dem_tolerance {
+                failures.push(failure(i, format!("{}: {} ft, the terrain is {} ft", waypoint.ident, elevation, terrain)));
+            }
+        }
+        outcomes.push(outcome("elevation", checked, failures));
+    }
+    let n = &rules.name_length;
+    if n.enabled {
+        let failures = waypoints
+            .iter()
+            .enumerate()
+            .filter(|(_, w)| w.name.chars().count() > n.max)
+            .map(|(i, w)| failure(i, format!("{}: {:?} is longer than {} characters", w.ident, w.name, n.max)))
+            .collect();
+        outcomes.push(outcome("name length", waypoints.len(), failures));
+    }
+    Ok(outcomes)
+}
+
+/// The human-readable report: a PASS or FAIL line per rule with its counts,
+/// each followed by the records that failed it.
+pub fn report(outcomes: &[Outcome], records: usize) -> String {
+    let mut report = String::new();
+    for o in outcomes {
+        let verdict = if o.passed() { "PASS" } else { "FAIL" };
+        writeln!(report, "{} {}: {} of {} checked failed", verdict, o.rule, o.failures.len(), o.checked).unwrap();
+        for failure in &o.failures {
+            writeln!(report, "  {}", failure.message).unwrap();
+        }
+    }
+    let failed = outcomes.iter().filter(|o| !o.passed()).count();
+    writeln!(report, "{} records, {} of {} rules failed", records, failed, outcomes.len()).unwrap();
+    report
+}
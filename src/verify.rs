@@ -0,0 +1,217 @@
+use crate::format::{Fields, InputFormat, ReadOptions, Records};
+use crate::matching::name_similarity;
+use crate::{geo, input, region, Waypoint};
+use clap::ValueEnum;
+use serde::Deserialize;
+use std::collections::HashMap;
+use std::error::Error;
+use std::fmt::Write;
+
+pub const OURAIRPORTS_URL: &str = "https://davidmegginson.github.io/ourairports-data/airports.csv";
+pub const OURAIRPORTS_FREQUENCIES_URL: &str =
+    "https://davidmegginson.github.io/ourairports-data/airport-frequencies.csv";
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, ValueEnum)]
+pub enum ReferenceFormat {
+    /// OurAirports `airports.csv`
+    Ourairports,
+    /// Userpoints CSV, e.g. transcribed from the AIP
+    Userpoints,
+    /// EUROCONTROL EAD extract, AIXM 4.5
+    Ead,
+}
+
+#[derive(Deserialize)]
+struct OurAirportsRecord {
+    ident: String,
+    #[serde(rename = "type")]
+    airport_type: String,
+    name: String,
+    latitude_deg: f32,
+    longitude_deg: f32,
+    elevation_ft: Option<f32>,
+    iso_country: Option<String>,
+    gps_code: Option<String>,
+}
+
+#[derive(Deserialize)]
+struct FrequencyRecord {
+    airport_ident: String,
+    #[serde(rename = "type")]
+    frequency_type: String,
+    frequency_mhz: String,
+}
+
+/// Reads OurAirports `airport-frequencies.csv` into descriptions such as
+/// `TWR 118.300 MHz, ATIS 120.450 MHz`, keyed by OurAirports ident.
+pub fn read_ourairports_frequencies(path: &str) -> Result<HashMap<String, String>, Box<dyn Error>> {
+    let mut reader = csv::Reader::from_reader(input::open(path)?);
+    let mut frequencies: HashMap<String, String> = HashMap::new();
+    for record in reader.deserialize() {
+        let record: FrequencyRecord = record?;
+        let frequency = format!("{} {} MHz", record.frequency_type.trim(), record.frequency_mhz.trim());
+        frequencies
+            .entry(record.airport_ident)
+            .and_modify(|d| *d = format!("{}, {}", d, frequency))
+            .or_insert(frequency);
+    }
+    Ok(frequencies)
+}
+
+/// Maps an OurAirports `type` to the Little Navmap userpoint type; small
+/// airports and balloon ports are airstrips.
+fn userpoint_type(airport_type: &str) -> &'static str {
+    match airport_type {
+        "large_airport" | "medium_airport" => "Airport",
+        "heliport" => "Helipad",
+        "seaplane_base" => "Seaport",
+        _ => "Airstrip",
+    }
+}
+
+/// Reads OurAirports `airports.csv`, keeping the GPS code as ident when the
+/// record has one since that is what matches the AIP. With a `country`
+/// only airports of that ISO country code are read; `frequencies` from
+/// [`read_ourairports_frequencies`] become the descriptions. The region
+/// comes from the ident, or else the country. Closed sites are skipped.
+pub fn read_ourairports(
+    path: &str,
+    country: Option<&str>,
+    frequencies: &HashMap<String, String>,
+) -> Result<Vec<Waypoint>, Box<dyn Error>> {
+    let mut reader = csv::Reader::from_reader(input::open(path)?);
+    let mut waypoints = vec![];
+    for record in reader.deserialize() {
+        let record: OurAirportsRecord = record?;
+        let in_country = |c: &str| record.iso_country.as_deref().is_some_and(|i| i.eq_ignore_ascii_case(c));
+        if record.airport_type == "closed" || country.is_some_and(|c| !in_country(c)) {
+            continue;
+        }
+        let description = frequencies.get(&record.ident).cloned();
+        let ident = record
+            .gps_code
+            .filter(|c| !c.is_empty())
+            .unwrap_or(record.ident);
+        let mut waypoint = Waypoint::builder(&record.name)
+            .waypoint_type(userpoint_type(&record.airport_type))
+            .ident(&ident)
+            .coordinates(record.latitude_deg, record.longitude_deg)
+            .elevation(record.elevation_ft)
+            .import_filename(path);
+        let country_region = record.iso_country.as_deref().and_then(region::of_country);
+        if let Some(region) = region::of_icao(&ident).or(country_region) {
+            waypoint = waypoint.region(region);
+        }
+        if let Some(description) = description {
+            waypoint = waypoint.description(description);
+        }
+        let waypoint = waypoint.build().map_err(|e| e.in_record(&ident))?;
+        waypoints.push(waypoint);
+    }
+    Ok(waypoints)
+}
+
+/// Limits beyond which a difference to the reference is reported.
+#[derive(Clone, Copy, Debug)]
+pub struct Tolerances {
+    /// Position difference (metres).
+    pub distance: f64,
+    /// Elevation difference (feet).
+    pub elevation: f32,
+    /// Minimum name similarity (0-1).
+    pub name: f64,
+    /// How far to look for a reference record when idents don't match (metres).
+    pub search_radius: f64,
+}
+
+fn find_reference<'a>(waypoint: &Waypoint, reference: &'a [Waypoint], tolerances: &Tolerances) -> Option<&'a Waypoint> {
+    let distance = |r: &Waypoint| {
+        geo::distance(
+            waypoint.latitude as f64,
+            waypoint.longitude as f64,
+            r.latitude as f64,
+            r.longitude as f64,
+        )
+    };
+    reference
+        .iter()
+        .find(|r| r.ident.eq_ignore_ascii_case(&waypoint.ident))
+        .or_else(|| {
+            reference
+                .iter()
+                .filter(|r| distance(r) <= tolerances.search_radius)
+                .min_by(|a, b| distance(a).total_cmp(&distance(b)))
+        })
+}
+
+/// Compares every record against its counterpart in `reference` and returns
+/// the human-readable report and the number of discrepancies found.
+pub fn verify(dataset: &[Waypoint], reference: &[Waypoint], tolerances: &Tolerances) -> (String, usize) {
+    let mut report = String::new();
+    let mut issues = 0;
+    for waypoint in dataset {
+        let Some(r) = find_reference(waypoint, reference, tolerances) else {
+            writeln!(report, "{}: no reference record", waypoint.ident).unwrap();
+            issues += 1;
+            continue;
+        };
+        let distance = geo::distance(
+            waypoint.latitude as f64,
+            waypoint.longitude as f64,
+            r.latitude as f64,
+            r.longitude as f64,
+        );
+        if distance > tolerances.distance {
+            writeln!(report, "{}: position {:.0} m from {}", waypoint.ident, distance, r.ident).unwrap();
+            issues += 1;
+        }
+        if let (Some(e), Some(re)) = (waypoint.elevation, r.elevation) {
+            if (e - re).abs() > tolerances.elevation {
+                writeln!(report, "{}: elevation {} ft, {} has {} ft", waypoint.ident, e, r.ident, re).unwrap();
+                issues += 1;
+            }
+        }
+        if name_similarity(&waypoint.name, &r.name) < tolerances.name {
+            writeln!(report, "{}: named {:?}, {} is {:?}", waypoint.ident, waypoint.name, r.ident, r.name).unwrap();
+            issues += 1;
+        }
+    }
+    writeln!(report, "{} records checked, {} discrepancies", dataset.len(), issues).unwrap();
+    (report, issues)
+}
+
+/// Lists the `reference` records nothing in `dataset` corresponds to, such
+/// as aerodromes added to the official data but not yet to ours, and
+/// returns the report and their number.
+pub fn missing(dataset: &[Waypoint], reference: &[Waypoint], tolerances: &Tolerances) -> (String, usize) {
+    let mut report = String::new();
+    let mut issues = 0;
+    for r in reference {
+        if find_reference(r, dataset, tolerances).is_none() {
+            writeln!(report, "{}: {:?} is missing from the dataset", r.ident, r.name).unwrap();
+            issues += 1;
+        }
+    }
+    writeln!(report, "{} reference records checked, {} missing", reference.len(), issues).unwrap();
+    (report, issues)
+}
+
+pub struct OurAirports;
+
+impl InputFormat for OurAirports {
+    fn name(&self) -> &'static str {
+        "ourairports"
+    }
+
+    fn description(&self) -> &'static str {
+        "OurAirports airports.csv"
+    }
+
+    fn fields(&self) -> Fields {
+        Fields { elevation: true, description: true, ..Fields::default() }
+    }
+
+    fn read(&self, path: &str, _: &ReadOptions) -> Result<Records, Box<dyn Error>> {
+        Ok(Box::new(read_ourairports(path, None, &HashMap::new())?.into_iter()))
+    }
+}
@@ -0,0 +1,31 @@
+//! Browser API for converting a dropped SkyDemon file without a server;
+//! see `web/index.html`.
+
+use crate::format::{self, WriteOptions};
+use crate::{AirfieldReader, Waypoint};
+use wasm_bindgen::prelude::*;
+
+/// Converts SkyDemon airfields XML to the output format named `format`,
+/// e.g. `userpoints-csv` or `kml`.
+#[wasm_bindgen]
+pub fn convert(xml: &[u8], format: &str, source: &str) -> Result<Vec<u8>, JsError> {
+    let output = format::output(format).ok_or_else(|| JsError::new(&format!("unknown format {:?}", format)))?;
+    let waypoints = AirfieldReader::new(xml)
+        .with_source(source)
+        .collect::<Result<Vec<Waypoint>, _>>()
+        .map_err(|e| JsError::new(&e.to_string()))?;
+    let options = WriteOptions { title: "Polish airfields".to_owned(), ..WriteOptions::default() };
+    output.render(&waypoints, &options).map_err(|e| JsError::new(&e.to_string()))
+}
+
+/// Names of the output formats, for the format picker.
+#[wasm_bindgen]
+pub fn formats() -> Vec<String> {
+    format::outputs().iter().map(|f| f.name().to_owned()).collect()
+}
+
+/// File extension for the output format named `format`.
+#[wasm_bindgen]
+pub fn extension(format: &str) -> Option<String> {
+    format::output(format).map(|f| f.extension().to_owned())
+}
@@ -0,0 +1,159 @@
+use crate::Error;
+use quick_xml::escape::resolve_xml_entity;
+use quick_xml::events::{BytesStart, Event};
+use quick_xml::XmlVersion;
+use std::io::BufRead;
+
+pub fn escape(s: &str) -> String {
+    s.replace('&', "&amp;").replace('<', "&lt;").replace('>', "&gt;").replace('"', "&quot;")
+}
+
+/// One element with its subtree, read on its own from a larger document.
+/// Element and attribute names are stored without namespace prefixes.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct Element {
+    pub name: String,
+    pub attributes: Vec<(String, String)>,
+    pub children: Vec<Element>,
+    /// Text directly inside the element.
+    pub text: String,
+    /// Byte offset of the start tag in the document.
+    pub offset: u64,
+}
+
+impl Element {
+    fn start(start: &BytesStart, offset: u64) -> Result<Self, Error> {
+        let attributes = start
+            .attributes()
+            .map(|attribute| {
+                let attribute = attribute.map_err(quick_xml::Error::from)?;
+                let key = attribute.key.local_name().as_ref().to_owned();
+                Ok((key, attribute.normalized_value(XmlVersion::Implicit1_0)?.into_owned()))
+            })
+            .collect::<Result<_, Error>>()?;
+        Ok(Element {
+            name: start.local_name().as_ref().to_owned(),
+            attributes,
+            offset,
+            ..Element::default()
+        })
+    }
+
+    pub fn attribute(&self, name: &str) -> Option<&str> {
+        self.attributes.iter().find(|(key, _)| key == name).map(|(_, value)| value.as_str())
+    }
+
+    /// Direct children called `name`.
+    pub fn children_named<'a>(&'a self, name: &'a str) -> impl Iterator<Item = &'a Element> + 'a {
+        self.children.iter().filter(move |child| child.name == name)
+    }
+
+    /// The first element called `name` below this one, depth first.
+    pub fn descendant(&self, name: &str) -> Option<&Element> {
+        self.children
+            .iter()
+            .find_map(|child| if child.name == name { Some(child) } else { child.descendant(name) })
+    }
+
+    /// The element written back as XML, independent of where it was in the
+    /// document.
+    pub fn to_xml(&self) -> String {
+        let mut xml = format!("<{}", self.name);
+        for (key, value) in &self.attributes {
+            xml.push_str(&format!(" {}=\"{}\"", key, escape(value)));
+        }
+        xml.push('>');
+        xml.push_str(&escape(&self.text));
+        for child in &self.children {
+            xml.push_str(&child.to_xml());
+        }
+        xml.push_str(&format!("</{}>", self.name));
+        xml
+    }
+}
+
+/// Streams the elements called `name` out of a document, reading each
+/// subtree into memory but never the whole document.
+pub struct Elements<R> {
+    xml: quick_xml::Reader<R>,
+    buf: Vec<u8>,
+    name: &'static str,
+    done: bool,
+}
+
+impl<R: BufRead> Elements<R> {
+    pub fn new(reader: R, name: &'static str) -> Self {
+        Elements {
+            xml: quick_xml::Reader::from_reader(reader),
+            buf: vec![],
+            name,
+            done: false,
+        }
+    }
+
+    /// Reads the rest of `element` up to its end tag.
+    fn subtree(&mut self, element: Element) -> Result<Element, Error> {
+        let mut open = vec![element];
+        loop {
+            self.buf.clear();
+            let offset = self.xml.buffer_position();
+            let event = self.xml.read_event_into(&mut self.buf)?;
+            let Some(current) = open.last_mut() else {
+                unreachable!("the outermost element is returned at its end tag");
+            };
+            match event {
+                Event::Start(start) => open.push(Element::start(&start, offset)?),
+                Event::Empty(start) => current.children.push(Element::start(&start, offset)?),
+                Event::Text(text) => current.text.push_str(&text.xml10_content()),
+                Event::CData(text) => current.text.push_str(&text.xml10_content()),
+                Event::GeneralRef(reference) => match reference.resolve_char_ref()? {
+                    Some(c) => current.text.push(c),
+                    None => {
+                        let name = reference.into_inner();
+                        match resolve_xml_entity(&name) {
+                            Some(value) => current.text.push_str(value),
+                            None => current.text.push_str(&format!("&{};", name)),
+                        }
+                    }
+                },
+                Event::End(_) => {
+                    let Some(closed) = open.pop() else {
+                        unreachable!("an end tag always has an open element");
+                    };
+                    match open.last_mut() {
+                        Some(parent) => parent.children.push(closed),
+                        None => return Ok(closed),
+                    }
+                }
+                Event::Eof => return Err(Error::UnexpectedEnd(current.name.clone())),
+                _ => {}
+            }
+        }
+    }
+}
+
+impl<R: BufRead> Iterator for Elements<R> {
+    type Item = Result<Element, Error>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        while !self.done {
+            self.buf.clear();
+            let offset = self.xml.buffer_position();
+            let element = match self.xml.read_event_into(&mut self.buf) {
+                Ok(Event::Start(start)) if start.local_name().as_ref() == self.name => {
+                    Element::start(&start, offset).and_then(|element| self.subtree(element))
+                }
+                Ok(Event::Empty(start)) if start.local_name().as_ref() == self.name => Element::start(&start, offset),
+                Ok(Event::Eof) => {
+                    self.done = true;
+                    continue;
+                }
+                Ok(_) => continue,
+                Err(e) => Err(e.into()),
+            };
+            self.done = element.is_err();
+            return Some(element);
+        }
+        None
+    }
+}
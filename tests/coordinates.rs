@@ -0,0 +1,82 @@
+//! Property tests for the SkyDemon coordinate parser: printed positions
+//! must parse back to the same place, and no input may make it panic.
+
+use polish_airports::{Coordinate, Position};
+use proptest::prelude::*;
+use std::str::FromStr;
+
+/// A coordinate up to `max` degrees with seconds on the hundredths the
+/// string form keeps.
+fn exact(max: i32) -> impl Strategy<Value = Coordinate> {
+    (0..max, 0..60u32, 0..6000u32, any::<bool>()).prop_map(|(degrees, minutes, hundredths, negative)| Coordinate {
+        degrees: if negative { -degrees } else { degrees },
+        minutes,
+        seconds: hundredths as f32 / 100.,
+        negative,
+    })
+}
+
+/// A coordinate up to `max` degrees with any seconds below 60.
+fn any_seconds(max: i32) -> impl Strategy<Value = Coordinate> {
+    (0..max, 0..60u32, 0f32..60., any::<bool>()).prop_map(|(degrees, minutes, seconds, negative)| Coordinate {
+        degrees: if negative { -degrees } else { degrees },
+        minutes,
+        seconds,
+        negative,
+    })
+}
+
+proptest! {
+    #[test]
+    fn exact_positions_round_trip(lat in exact(90), lon in exact(180)) {
+        let position = Position { lat, lon };
+        let back = Position::from_str(&position.to_string()).unwrap();
+        prop_assert_eq!(back, position);
+    }
+
+    #[test]
+    fn positions_round_trip_to_the_hundredth_second(lat in any_seconds(90), lon in any_seconds(180)) {
+        let position = Position { lat, lon };
+        let back = Position::from_str(&position.to_string()).unwrap();
+        // Half a hundredth of a second, plus f32 rounding near 180 degrees.
+        let tolerance = 0.005 / 3600. + 2e-5;
+        prop_assert!((back.lat.to_decimal_degrees() - position.lat.to_decimal_degrees()).abs() <= tolerance);
+        prop_assert!((back.lon.to_decimal_degrees() - position.lon.to_decimal_degrees()).abs() <= tolerance);
+    }
+
+    #[test]
+    fn decimal_degrees_parse_as_themselves(degrees in -180f32..180.) {
+        prop_assert_eq!(Coordinate::parse_decimal(&degrees.to_string()).unwrap(), degrees);
+    }
+
+    #[test]
+    fn malformed_coordinates_are_errors(s in any::<String>()) {
+        let _ = Coordinate::from_str(&s);
+        let _ = Coordinate::parse_decimal(&s);
+        let _ = Position::from_str(&s);
+    }
+
+    #[test]
+    fn near_misses_are_errors(s in "[NSEW][0-9.+\\-eé٣]{0,12}") {
+        if let Ok(coordinate) = Coordinate::from_str(&s) {
+            prop_assert!(s.is_ascii());
+            prop_assert!(coordinate.minutes < 60);
+            prop_assert!((0. ..60.).contains(&coordinate.seconds));
+        }
+    }
+}
+
+#[test]
+fn out_of_range_coordinates_are_errors() {
+    for s in ["N910000.00", "N900000.01", "E1800100.00", "N526000.00", "N520160.00", "N5201-1.00", "N5201inf", "N52+101", "N", ""] {
+        assert!(Coordinate::from_str(s).is_err(), "{:?} parsed", s);
+    }
+}
+
+#[test]
+fn unusable_decimal_degrees_are_errors() {
+    for s in ["NaN", "inf", "-inf", "180.5", "-181", "1e9"] {
+        assert!(Coordinate::parse_decimal(s).is_err(), "{:?} parsed", s);
+    }
+    assert_eq!(Coordinate::parse_decimal("-180").unwrap(), -180.);
+}
@@ -0,0 +1,47 @@
+//! Property tests for the SeeYou CUP reader: no coordinate text may make it
+//! panic, only fail to read.
+
+use polish_airports::format::{self, ReadOptions};
+use proptest::prelude::*;
+use std::io::Write;
+
+fn read(lat: &str, lon: &str) -> Result<usize, String> {
+    let mut file = tempfile::NamedTempFile::new().unwrap();
+    write!(file, "name,code,country,lat,lon,elev,style\n\"Test\",TEST,PL,{},{},100m,2\n", lat, lon).unwrap();
+    let cup = format::input("cup").unwrap();
+    cup.read(file.path().to_str().unwrap(), &ReadOptions::default())
+        .map(|records| records.count())
+        .map_err(|e| e.to_string())
+}
+
+proptest! {
+    #[test]
+    fn malformed_coordinates_are_errors(lat in "[0-9.]{0,10}[NSEWŁé٣x]?", lon in "\\PC{0,12}") {
+        let _ = read(&lat, &lon.replace([',', '"', '\n', '\r'], ""));
+    }
+
+    #[test]
+    fn non_ascii_hemispheres_are_errors(digits in "[0-9]{4}\\.[0-9]{3}", hemisphere in "[ŁéżΩ٣]") {
+        let lat = format!("{}{}", digits, hemisphere);
+        prop_assert!(read(&lat, "01744.817E").is_err());
+    }
+}
+
+#[test]
+fn hemispheres_set_the_sign() {
+    assert_eq!(read("5209.933N", "01744.817E"), Ok(1));
+    assert_eq!(read("5209.933S", "01744.817W"), Ok(1));
+}
+
+#[test]
+fn columns_are_taken_by_header_name() {
+    let mut file = tempfile::NamedTempFile::new().unwrap();
+    let text = "code,name,lat,lon,style,rwdir,rwlen,rwwidth,elev,desc\nEPXX,\"Test\",5209.933N,01744.817E,5,070,800m,30m,100m,note\n";
+    file.write_all(text.as_bytes()).unwrap();
+    let cup = format::input("cup").unwrap();
+    let waypoint = cup.read(file.path().to_str().unwrap(), &ReadOptions::default()).unwrap().next().unwrap();
+    assert_eq!((waypoint.name.as_str(), waypoint.ident.as_str()), ("Test", "EPXX"));
+    assert_eq!(waypoint.elevation, Some(328.));
+    assert_eq!(waypoint.description.as_deref(), Some("note"));
+    assert_eq!(waypoint.waypoint_type, "Airport");
+}